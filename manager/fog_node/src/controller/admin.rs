@@ -0,0 +1,100 @@
+use crate::service::admin::Admin;
+use anyhow::{Context, Result};
+use model::view::admin::{
+    ChildNode, NeighborLatency, NodeResources, PendingTask, TrackedFunctions,
+};
+use std::sync::Arc;
+use tracing::trace;
+
+/// List the functions tracked by this node, grouped by state
+pub async fn get_functions(admin: &Arc<Admin>) -> TrackedFunctions {
+    trace!("getting the tracked functions");
+    admin.get_functions()
+}
+
+/// Get the resources of each k8s node managed by this node
+pub async fn get_resources(admin: &Arc<Admin>) -> Result<Vec<NodeResources>> {
+    trace!("getting the resources");
+    admin.get_resources().await.context("Failed to get the resources")
+}
+
+/// Get the latencies estimated to the neighbors of this node
+pub async fn get_neighbors(admin: &Arc<Admin>) -> Vec<NeighborLatency> {
+    trace!("getting the neighbors");
+    admin.get_neighbors().await
+}
+
+/// Get the children registered to this node
+pub async fn get_children(admin: &Arc<Admin>) -> Vec<ChildNode> {
+    trace!("getting the children");
+    admin.get_children()
+}
+
+/// Get the jobs scheduled on this node, the earliest first
+pub async fn get_pending_tasks(admin: &Arc<Admin>) -> Vec<PendingTask> {
+    trace!("getting the pending tasks");
+    admin.get_pending_tasks().await
+}
+
+#[cfg(feature = "offline")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::k8s::OFFLINE_NODE_K8S;
+    use crate::service::fixtures::{
+        get_auction_impl, market_connected, sla, Instance,
+    };
+    use model::domain::sla::Sla;
+    use model::view::auction::AccumulatedLatency;
+    use model::view::function::FunctionState;
+    use uom::si::f64::Time;
+    use uom::si::information::megabyte;
+    use uom::si::rational64::Information;
+    use uom::si::time::second;
+
+    /// The admin views reflect the paid function
+    #[tokio::test]
+    async fn test_admin_state() {
+        let Instance { auction, function_life, admin, .. } =
+            get_auction_impl(market_connected()).await;
+        let sla = Sla { duration: Time::new::<second>(60.0), ..sla() };
+        let id = sla.id.clone();
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let resources = get_resources(&admin).await.unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].reserved_memory, sla.memory);
+
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+
+        let functions = get_functions(&admin).await;
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[&FunctionState::Paid][0].id, id);
+
+        let resources = get_resources(&admin).await.unwrap();
+        assert_eq!(resources[0].node, OFFLINE_NODE_K8S);
+        assert_eq!(resources[0].used_memory, sla.memory);
+        assert_eq!(resources[0].used_cpu, sla.cpu);
+        assert_eq!(
+            resources[0].reserved_memory,
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1))
+        );
+
+        let tasks = get_pending_tasks(&admin).await;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task, "unprovisionFunction");
+        assert_eq!(tasks[0].sla, id);
+        assert_eq!(tasks[0].node.as_deref(), Some(OFFLINE_NODE_K8S));
+
+        assert!(get_children(&admin).await.is_empty());
+        assert!(get_neighbors(&admin).await.is_empty());
+    }
+}
@@ -4,6 +4,9 @@ use anyhow::{Context, Result};
 use chrono::Utc;
 use helper::monitoring::MetricsExporter;
 use model::view::auction::{BidProposals, BidRequestOwned};
+use model::view::function::FunctionStatus;
+use model::view::node::CapacitySummary;
+use model::view::sla::{ExtendSla, SlaExtension};
 use model::SlaId;
 use std::sync::Arc;
 use tracing::{instrument, trace};
@@ -57,6 +60,58 @@ pub async fn set_paid_from_sla(
     Ok(())
 }
 
+/// Releases the proposal made on an auction that was won by another bid
+#[instrument(level = "trace", skip(function))]
+pub async fn reject_from_sla(
+    id: SlaId,
+    function: &Arc<FunctionLife>,
+) -> Result<()> {
+    trace!("Releasing the rejected proposal for SLA {:?}", id);
+    function.reject_function(id.clone()).await.with_context(|| {
+        format!("Failed to release the rejected proposal for SLA {}", id)
+    })?;
+    Ok(())
+}
+
+/// Stops the function before the end of its SLA's duration
+#[instrument(level = "trace", skip(function))]
+pub async fn terminate_from_sla(
+    id: SlaId,
+    function: &Arc<FunctionLife>,
+) -> Result<()> {
+    trace!("Terminating the function of SLA {:?}", id);
+    function.terminate_function(id.clone()).await.with_context(|| {
+        format!("Failed to terminate the function from SLA {}", id)
+    })?;
+    Ok(())
+}
+
+/// Where the function of the SLA is in its lifecycle
+#[instrument(level = "trace", skip(function))]
+pub async fn get_function_status(
+    id: SlaId,
+    function: &Arc<FunctionLife>,
+) -> Result<FunctionStatus> {
+    trace!("Getting the status of the function of SLA {:?}", id);
+    function.get_function_status(&id)
+}
+
+/// Keeps the function running for longer than its SLA's duration
+#[instrument(level = "trace", skip(function))]
+pub async fn extend_from_sla(
+    id: SlaId,
+    extension: ExtendSla,
+    function: &Arc<FunctionLife>,
+) -> Result<SlaExtension> {
+    trace!("Extending the function of SLA {:?}", id);
+    function
+        .extend_function(id.clone(), extension.duration)
+        .await
+        .with_context(|| {
+            format!("Failed to extend the function from SLA {}", id)
+        })
+}
+
 /// Creates the function on OpenFaaS and use the SLA to enable the limits
 pub async fn provision_from_sla(
     id: SlaId,
@@ -68,3 +123,14 @@ pub async fn provision_from_sla(
     })?;
     Ok(())
 }
+
+/// Keep what the subtree of a child can host, to spare it the calls for bids
+/// it cannot satisfy
+pub fn update_child_capacity(
+    summary: CapacitySummary,
+    function: &Arc<FunctionLife>,
+) -> Result<()> {
+    function
+        .update_child_capacity(summary)
+        .context("Failed to update the capacity summary of a child node")
+}
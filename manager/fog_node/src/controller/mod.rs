@@ -1,2 +1,3 @@
+pub(crate) mod admin;
 pub(crate) mod auction;
 pub(crate) mod node;
@@ -1,6 +1,8 @@
 use crate::NodeLife;
 use anyhow::{Context, Result};
-use model::view::node::RegisterNode;
+use model::view::node::{
+    DeregisterNode, MoveNode, MoveNodeRequest, RegisterNode,
+};
 use std::sync::Arc;
 
 pub async fn register_child_node(
@@ -12,3 +14,44 @@ pub async fn register_child_node(
         .await
         .context("Failed to register a child node to this one")
 }
+
+pub async fn heartbeat(
+    register: RegisterNode,
+    router: &Arc<NodeLife>,
+) -> Result<()> {
+    router
+        .relay_heartbeat(register)
+        .await
+        .context("Failed to relay the heartbeat of a child node")
+}
+
+pub async fn leave(router: &Arc<NodeLife>) -> Result<()> {
+    router.leave().await.context("Failed to leave the network")
+}
+
+pub async fn deregister(
+    deregister: DeregisterNode,
+    router: &Arc<NodeLife>,
+) -> Result<()> {
+    router
+        .relay_deregistration(deregister)
+        .await
+        .context("Failed to relay the departure of a node")
+}
+
+pub async fn move_to(
+    request: MoveNodeRequest,
+    router: &Arc<NodeLife>,
+) -> Result<()> {
+    router.move_to(request).await.context("Failed to move this node")
+}
+
+pub async fn reparent(
+    move_node: MoveNode,
+    router: &Arc<NodeLife>,
+) -> Result<()> {
+    router
+        .relay_move(move_node)
+        .await
+        .context("Failed to relay the move of a node")
+}
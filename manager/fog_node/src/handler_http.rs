@@ -1,41 +1,25 @@
+use crate::service::admin::Admin;
 use crate::service::function_life::FunctionLife;
 use crate::{controller, NodeLife};
 use actix_web::web::{self, Data};
 use actix_web::HttpResponse;
+use helper::http_error::HttpError;
 use helper::monitoring::MetricsExporter;
 use model::view::auction::BidRequestOwned;
-use model::view::node::RegisterNode;
+use model::view::node::{
+    CapacitySummary, DeregisterNode, MoveNode, MoveNodeRequest, RegisterNode,
+};
+use model::view::sla::ExtendSla;
 use model::SlaId;
 use serde::Deserialize;
-use tracing::error;
-
-#[derive(Debug)]
-pub struct AnyhowErrorWrapper {
-    err: anyhow::Error,
-}
-
-impl std::fmt::Display for AnyhowErrorWrapper {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        //        error!("{:?}", self.err);
-        write!(f, "{:?}", self.err)
-    }
-}
-
-impl actix_web::error::ResponseError for AnyhowErrorWrapper {}
-
-impl From<anyhow::Error> for AnyhowErrorWrapper {
-    fn from(err: anyhow::Error) -> AnyhowErrorWrapper {
-        error!("{}", err);
-        AnyhowErrorWrapper { err }
-    }
-}
 
 /// Return a bid for the SLA.
 pub async fn post_bid(
     payload: web::Json<BidRequestOwned>,
     function: Data<FunctionLife>,
     metrics: Data<MetricsExporter>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
+    payload.sla.validate()?;
     let res =
         controller::auction::bid_on(payload.0, &function, &metrics).await;
     Ok(HttpResponse::Ok().json(res?))
@@ -52,7 +36,7 @@ pub struct PostBidAcceptParams {
 pub async fn post_bid_accept(
     params: web::Path<PostBidAcceptParams>,
     function: Data<FunctionLife>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     #[allow(clippy::let_unit_value)]
     let res =
         controller::auction::set_paid_from_sla(params.id.clone(), &function)
@@ -60,12 +44,25 @@ pub async fn post_bid_accept(
     Ok(HttpResponse::Ok().json(res?))
 }
 
+/// Called instead of [post_bid_accept] when another bid won the auction,
+/// frees what was held for the bid.
+pub async fn post_bid_reject(
+    params: web::Path<PostBidAcceptParams>,
+    function: Data<FunctionLife>,
+) -> Result<HttpResponse, HttpError> {
+    #[allow(clippy::let_unit_value)]
+    let res =
+        controller::auction::reject_from_sla(params.id.clone(), &function)
+            .await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
 // Proceeds to provision the paid for SLA ([post_bid_accept]) and thus, the
 /// function.
 pub async fn post_provision(
     params: web::Path<PostBidAcceptParams>,
     function: Data<FunctionLife>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     #[allow(clippy::let_unit_value)]
     let res =
         controller::auction::provision_from_sla(params.id.clone(), &function)
@@ -73,13 +70,135 @@ pub async fn post_provision(
     Ok(HttpResponse::Ok().json(res?))
 }
 
+/// Stops the function early, instead of waiting for the end of the duration
+/// of its SLA.
+pub async fn delete_function(
+    params: web::Path<PostBidAcceptParams>,
+    function: Data<FunctionLife>,
+) -> Result<HttpResponse, HttpError> {
+    #[allow(clippy::let_unit_value)]
+    let res =
+        controller::auction::terminate_from_sla(params.id.clone(), &function)
+            .await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
+/// Where the function is in its lifecycle on this node.
+pub async fn get_function(
+    params: web::Path<PostBidAcceptParams>,
+    function: Data<FunctionLife>,
+) -> Result<HttpResponse, HttpError> {
+    let res =
+        controller::auction::get_function_status(params.id.clone(), &function)
+            .await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
+/// Keeps the function running for longer than the duration of its SLA, the
+/// extension is priced.
+pub async fn post_extend_function(
+    params: web::Path<PostBidAcceptParams>,
+    payload: web::Json<ExtendSla>,
+    function: Data<FunctionLife>,
+) -> Result<HttpResponse, HttpError> {
+    payload.validate()?;
+    let res = controller::auction::extend_from_sla(
+        params.id.clone(),
+        payload.0,
+        &function,
+    )
+    .await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
+/// List the functions tracked by this node, grouped by state.
+pub async fn get_admin_functions(admin: Data<Admin>) -> HttpResponse {
+    HttpResponse::Ok().json(controller::admin::get_functions(&admin).await)
+}
+
+/// Get the used, reserved and available resources of each k8s node.
+pub async fn get_admin_resources(
+    admin: Data<Admin>,
+) -> Result<HttpResponse, HttpError> {
+    let res = controller::admin::get_resources(&admin).await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
+/// Get the latencies estimated to the neighbors of this node.
+pub async fn get_admin_neighbors(admin: Data<Admin>) -> HttpResponse {
+    HttpResponse::Ok().json(controller::admin::get_neighbors(&admin).await)
+}
+
+/// Get the children registered to this node.
+pub async fn get_admin_children(admin: Data<Admin>) -> HttpResponse {
+    HttpResponse::Ok().json(controller::admin::get_children(&admin).await)
+}
+
+/// Get the jobs scheduled on this node.
+pub async fn get_admin_tasks(admin: Data<Admin>) -> HttpResponse {
+    HttpResponse::Ok().json(controller::admin::get_pending_tasks(&admin).await)
+}
+
 /// Register a child node to this one
 pub async fn post_register_child_node(
     payload: web::Json<RegisterNode>,
     router: Data<NodeLife>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     controller::node::register_child_node(payload.0, &router).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Leave the network
+pub async fn post_leave(
+    router: Data<NodeLife>,
+) -> Result<HttpResponse, HttpError> {
+    controller::node::leave(&router).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Relay the departure of a node of the subtree to the market
+pub async fn post_deregister(
+    payload: web::Json<DeregisterNode>,
+    router: Data<NodeLife>,
+) -> Result<HttpResponse, HttpError> {
+    controller::node::deregister(payload.0, &router).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Move this node, along with its subtree, under another parent
+pub async fn post_move(
+    payload: web::Json<MoveNodeRequest>,
+    router: Data<NodeLife>,
+) -> Result<HttpResponse, HttpError> {
+    controller::node::move_to(payload.0, &router).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Relay the move of a node of the subtree to the market
+pub async fn post_reparent(
+    payload: web::Json<MoveNode>,
+    router: Data<NodeLife>,
+) -> Result<HttpResponse, HttpError> {
+    controller::node::reparent(payload.0, &router).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Relay the heartbeat of a node of the subtree to the market
+pub async fn post_heartbeat(
+    payload: web::Json<RegisterNode>,
+    router: Data<NodeLife>,
+) -> Result<HttpResponse, HttpError> {
+    controller::node::heartbeat(payload.0, &router).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Keep the capacity summary of the subtree of a child
+pub async fn post_capacity(
+    payload: web::Json<CapacitySummary>,
+    function: Data<FunctionLife>,
+) -> Result<HttpResponse, HttpError> {
+    controller::auction::update_child_capacity(payload.0, &function)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
 pub async fn health() -> HttpResponse { HttpResponse::Ok().finish() }
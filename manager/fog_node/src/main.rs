@@ -1,7 +1,7 @@
-#![feature(async_closure)]
 #![feature(stmt_expr_attributes)]
 
 extern crate core;
+use helper::http_error::bad_request;
 use helper::{env_load, env_var};
 
 use helper::monitoring::{
@@ -28,6 +28,7 @@ use crate::repository::k8s::K8s;
 use crate::repository::latency_estimation::LatencyEstimation;
 use crate::repository::node_query::NodeQuery;
 use crate::repository::node_situation::NodeSituation;
+use crate::service::admin::Admin;
 use crate::service::auction::Auction;
 use crate::service::function::Function;
 use crate::service::function_life::FunctionLife;
@@ -71,8 +72,15 @@ env_var!(INSTANCE_NAME);
 env_var!(OTEL_EXPORTER_OTLP_ENDPOINT_FUNCTION);
 env_var!(FUNCTION_LIVE_TIMEOUT_MSECS);
 env_var!(FUNCTION_PAYING_TIMEOUT_MSECS);
+env_var!(PLACEMENT_STRATEGY);
+env_var!(BID_VALUATION);
+env_var!(FUNCTION_TRACKING_PATH);
+env_var!(HEARTBEAT_INTERVAL_MSECS);
+env_var!(BID_TIMEOUT_LATENCY_FACTOR);
+env_var!(BID_TIMEOUT_MIN_MSECS);
 
 const INFLUX_DEFAULT_ADDRESS: &str = "127.0.0.1:9086";
+const FUNCTION_TRACKING_DEFAULT_PATH: &str = "./function_tracking";
 
 /// Load the CONFIG env variable
 fn load_config_from_env(env_var: String) -> anyhow::Result<String> {
@@ -116,9 +124,7 @@ pub fn init_subscriber(
     //  let telemetry = tracing_opentelemetry::layer().with_tracer(tracer);
 
     //let reg = reg.with(telemetry);
-    if std::env::var("ENABLE_COLLECTOR").unwrap_or("".to_string())
-        == "true".to_string()
-    {
+    if std::env::var("ENABLE_COLLECTOR").unwrap_or("".to_string()) == "true" {
         let collector_ip = std::env::var("COLLECTOR_IP")
             .unwrap_or_else(|_| "localhost".to_string());
         let collector_port = std::env::var("COLLECTOR_PORT")
@@ -195,6 +201,8 @@ async fn connect_openfaas(
 #[cfg(feature = "offline")]
 async fn connect_openfaas(
     _http_client: Arc<reqwest_middleware::ClientWithMiddleware>,
+    _node_situation: Arc<NodeSituation>,
+    _k8s: Arc<K8s>,
     _openfaas_port_external: FogNodeFaaSPortExternal,
 ) -> Arc<Box<dyn FaaSBackend>> {
     Arc::new(Box::new(repository::faas::FaaSBackendOfflineImpl::new(
@@ -286,9 +294,25 @@ async fn main() -> anyhow::Result<()> {
         .unwrap(),
     );
 
-    let disk_data = NodeSituationDisk::new(config);
+    let mut disk_data = NodeSituationDisk::new(config).unwrap();
+    if let Ok(placement_strategy) = env::var(PLACEMENT_STRATEGY) {
+        disk_data.placement_strategy = placement_strategy
+            .parse()
+            .with_context(|| {
+                format!("{} was not formatted right", PLACEMENT_STRATEGY)
+            })
+            .unwrap();
+    }
+    if let Ok(bid_valuation) = env::var(BID_VALUATION) {
+        disk_data.bid_valuation = bid_valuation
+            .parse()
+            .with_context(|| {
+                format!("{} was not formatted right", BID_VALUATION)
+            })
+            .unwrap();
+    }
     let node_situation = Arc::new(NodeSituation::new(NodeSituationData::new(
-        disk_data.unwrap(),
+        disk_data,
         port_openfaas_external.clone(),
     )));
 
@@ -296,7 +320,20 @@ async fn main() -> anyhow::Result<()> {
     info!("Current node has been tagged {:?}", node_situation.get_my_tags());
     let node_query =
         Arc::new(NodeQuery::new(node_situation.clone(), http_client.clone()));
-    let function_tracking_repo = Arc::new(FunctionTracking::default());
+    let function_tracking_path = env::var(FUNCTION_TRACKING_PATH)
+        .unwrap_or_else(|_| FUNCTION_TRACKING_DEFAULT_PATH.to_string());
+    let function_tracking_db = sled::open(&function_tracking_path)
+        .with_context(|| {
+            format!(
+                "Failed to open the function tracking database at {}",
+                function_tracking_path
+            )
+        })
+        .unwrap();
+    let function_tracking_repo = Arc::new(
+        FunctionTracking::new(&function_tracking_db)
+            .expect("Failed to load the function tracking records"),
+    );
     let k8s_repo = Arc::new(K8s::new());
     let resource_tracking_repo = Arc::new(
         crate::repository::resource_tracking::ResourceTracking::new(
@@ -356,9 +393,14 @@ async fn main() -> anyhow::Result<()> {
         metrics.clone(),
         function.clone(),
         node_situation.clone(),
+        cron_repo.clone(),
+    )?);
+    let node_life_service = Arc::new(NodeLife::new(
+        node_situation.clone(),
+        node_query.clone(),
+        auction_service.clone(),
+        neighbor_monitor_service.clone(),
     )?);
-    let node_life_service =
-        Arc::new(NodeLife::new(node_situation.clone(), node_query.clone()));
     let function_life_service = Arc::new(FunctionLife::new(
         function.clone(),
         auction_service.clone(),
@@ -367,8 +409,22 @@ async fn main() -> anyhow::Result<()> {
         node_query.clone(),
         function_tracking_repo.clone(),
         cron_repo.clone(),
+        metrics.clone(),
     )?);
 
+    let admin_service = Arc::new(Admin::new(
+        function_tracking_repo.clone(),
+        resource_tracking_repo.clone(),
+        neighbor_monitor_service.clone(),
+        node_situation.clone(),
+        cron_repo.clone(),
+    ));
+
+    function_life_service
+        .recover()
+        .await
+        .expect("Failed to recover the functions tracked before the restart");
+
     if node_situation.is_market() {
         info!("This node is a provider node located at the market node");
     } else {
@@ -387,6 +443,8 @@ async fn main() -> anyhow::Result<()> {
         neighbor_monitor_service.clone(),
         k8s_repo,
         metrics.clone(),
+        function.clone(),
+        function_life_service.clone(),
     )
     .await
     .expect("Failed to register periodic actions");
@@ -398,6 +456,7 @@ async fn main() -> anyhow::Result<()> {
     let function_life_service = Data::from(function_life_service);
     let node_life_service = Data::from(node_life_service);
     let neighbor_monitor_service = Data::from(neighbor_monitor_service);
+    let admin_service = Data::from(admin_service);
     let metrics = Data::from(metrics);
 
     // For metrics gathering
@@ -409,12 +468,18 @@ async fn main() -> anyhow::Result<()> {
         let app =
             app.wrap(TracingLogger::default()).wrap(RequestTracing::new());
 
-        app.app_data(web::JsonConfig::default().limit(4096))
+        let json_config =
+            web::JsonConfig::default().limit(4096).error_handler(bad_request);
+
+        app.app_data(json_config)
+            .app_data(web::PathConfig::default().error_handler(bad_request))
+            .app_data(web::QueryConfig::default().error_handler(bad_request))
             .app_data(Data::clone(&auction_service))
             .app_data(Data::clone(&faas_service))
             .app_data(Data::clone(&function_life_service))
             .app_data(Data::clone(&node_life_service))
             .app_data(Data::clone(&neighbor_monitor_service))
+            .app_data(Data::clone(&admin_service))
             .app_data(Data::clone(&metrics))
             // For metrics gathering
             .app_data(Data::clone(&function_tracking_repository))
@@ -423,12 +488,45 @@ async fn main() -> anyhow::Result<()> {
                 web::scope("/api")
                     .route("/bid", web::post().to(post_bid))
                     .route("/accept/{id}", web::post().to(post_bid_accept))
+                    .route("/reject/{id}", web::post().to(post_bid_reject))
                     .route("/provision/{id}", web::post().to(post_provision))
+                    .route("/function/{id}", web::get().to(get_function))
+                    .route("/function/{id}", web::delete().to(delete_function))
+                    .route(
+                        "/function/{id}/extend",
+                        web::post().to(post_extend_function),
+                    )
                     .route(
                         "/register",
                         web::post().to(post_register_child_node),
                     )
-                    .route("/health", web::get().to(health)),
+                    .route("/leave", web::post().to(post_leave))
+                    .route("/deregister", web::post().to(post_deregister))
+                    .route("/move", web::post().to(post_move))
+                    .route("/reparent", web::post().to(post_reparent))
+                    .route("/heartbeat", web::post().to(post_heartbeat))
+                    .route("/capacity", web::post().to(post_capacity))
+                    .route("/health", web::get().to(health))
+                    .service(
+                        web::scope("/admin")
+                            .route(
+                                "/functions",
+                                web::get().to(get_admin_functions),
+                            )
+                            .route(
+                                "/resources",
+                                web::get().to(get_admin_resources),
+                            )
+                            .route(
+                                "/neighbors",
+                                web::get().to(get_admin_neighbors),
+                            )
+                            .route(
+                                "/children",
+                                web::get().to(get_admin_children),
+                            )
+                            .route("/tasks", web::get().to(get_admin_tasks)),
+                    ),
             )
     })
     .bind(("0.0.0.0", my_port_http.into()))?
@@ -457,7 +555,7 @@ async fn register_to_market(
             my_ip,
             my_port_http.clone(),
             my_port_faas.clone(),
-            advertised_bandwidth.clone(),
+            advertised_bandwidth,
         )
         .await
     {
@@ -465,4 +563,20 @@ async fn register_to_market(
         interval.tick().await;
     }
     info!("Registered to market and parent.");
+
+    let mut interval = time::interval(node_life.get_heartbeat_interval());
+    loop {
+        interval.tick().await;
+        if let Err(err) = node_life
+            .send_heartbeat(
+                my_ip,
+                my_port_http.clone(),
+                my_port_faas.clone(),
+                advertised_bandwidth,
+            )
+            .await
+        {
+            warn!("Failed to send heartbeat to market: {:?}", err);
+        }
+    }
 }
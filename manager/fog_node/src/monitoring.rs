@@ -94,6 +94,21 @@ struct NeighborLatency {
     instance_address:    String,
 }
 
+/// Neighbor that did not answer a call for bids in time, its subtree is
+/// left out of the proposals
+#[influx_observation]
+struct NeighborBidTimeout {
+    #[influxdb(field)]
+    n:        u64,
+    /// In seconds
+    #[influxdb(field)]
+    timeout:  f64,
+    #[influxdb(tag)]
+    neighbor: String,
+    #[influxdb(tag)]
+    sla_id:   String,
+}
+
 /// Number of provisioned functions
 #[influx_observation]
 struct ProvisionedFunctions {
@@ -115,3 +130,15 @@ struct PaidFunctions {
     #[influxdb(tag)]
     sla_id:        String,
 }
+
+/// Drift between the tracked functions and the ones deployed on the
+/// cluster, corrected by the reconciliation
+#[influx_observation]
+struct FunctionDrift {
+    #[influxdb(field)]
+    n:             u64,
+    #[influxdb(tag)]
+    kind:          String,
+    #[influxdb(tag)]
+    function_name: String,
+}
@@ -1,6 +1,8 @@
 use crate::monitoring::{CpuObservedFromPlatform, MemoryObservedFromPlatform};
 use crate::repository::cron::Cron;
 use crate::repository::k8s::K8s;
+use crate::service::function::Function;
+use crate::service::function_life::FunctionLife;
 use crate::service::neighbor_monitor::NeighborMonitor;
 use anyhow::{Context, Result};
 use chrono::Utc;
@@ -16,6 +18,8 @@ pub async fn init(
     neighbor_monitor: Arc<NeighborMonitor>,
     k8s_repo: Arc<K8s>,
     metrics: Arc<MetricsExporter>,
+    function: Arc<Function>,
+    function_life: Arc<FunctionLife>,
 ) -> Result<()> {
     cron.add_periodic(move || {
         let neighbor_monitor = neighbor_monitor.clone();
@@ -35,6 +39,23 @@ pub async fn init(
          metrics",
     )?;
 
+    cron.add_periodic(move || {
+        let function = function.clone();
+        Box::pin(reconcile(function))
+    })
+    .await
+    .context("Failed to add periodic task to reconcile the functions")?;
+
+    cron.add_periodic(move || {
+        let function_life = function_life.clone();
+        Box::pin(push_capacity(function_life))
+    })
+    .await
+    .context(
+        "Failed to add periodic task to push the capacity summary to the \
+         parent",
+    )?;
+
     Ok(())
 }
 
@@ -44,6 +65,18 @@ async fn ping(neighbor_monitor: Arc<NeighborMonitor>) {
     };
 }
 
+async fn reconcile(function: Arc<Function>) {
+    if let Err(e) = function.reconcile().await {
+        warn!("reconcile failed: {:?}", e);
+    };
+}
+
+async fn push_capacity(function_life: Arc<FunctionLife>) {
+    if let Err(e) = function_life.push_capacity_summary().await {
+        warn!("push_capacity_summary failed: {:?}", e);
+    };
+}
+
 async fn measure(k8s_repo: Arc<K8s>, metrics: Arc<MetricsExporter>) {
     let _ = _measure(k8s_repo, metrics).await.map_err(|err| {
         warn!("An error occurred while CRON measuring from K8S: {:?}", err)
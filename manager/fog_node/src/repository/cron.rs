@@ -1,39 +1,73 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use futures::Future;
 use model::SlaId;
 use num_traits::ToPrimitive;
-use std::collections::VecDeque;
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use uom::si::f64::Time;
 use uom::si::time::{millisecond, second};
 
+pub type Callback =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Runs the periodic jobs and the oneshot jobs keyed by their [Task]. The
+/// jobs only live in memory: after a restart, the recovery of the functions
+/// and of the proposals schedules them again from the function tracking.
 pub struct Cron {
     periodic_task_period: Time,
-    pub tasks:            Arc<Mutex<VecDeque<TaskEntry>>>,
+    /// Pending oneshot jobs, a task has at most one pending job
+    tasks:                Arc<Mutex<HashMap<Task, TaskEntry>>>,
+    next_id:              AtomicU64,
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct UnprovisionFunction {
     pub sla:  SlaId,
     pub node: String, // k8s node
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Task {
     UnprovisionFunction(UnprovisionFunction),
+    /// The proposal was not paid in time
+    ExpireProposal(SlaId),
+    /// Forget about the expired proposal
+    ForgetProposal(SlaId),
+}
+
+impl Task {
+    /// The sla the task is about
+    pub fn sla(&self) -> &SlaId {
+        match self {
+            Task::UnprovisionFunction(UnprovisionFunction { sla, .. }) => sla,
+            Task::ExpireProposal(sla) | Task::ForgetProposal(sla) => sla,
+        }
+    }
 }
 
+/// A pending oneshot job
 pub struct TaskEntry {
     pub task:       Task,
     pub created_at: DateTime<Utc>,
+    pub deadline:   DateTime<Utc>,
+    /// Tells apart the successive jobs of the same task
+    id:             u64,
+    callback:       Callback,
+    handle:         JoinHandle<()>,
 }
 
 impl Cron {
     pub async fn new(periodic_task_period: Time) -> Result<Self> {
         Ok(Self {
             periodic_task_period,
-            tasks: Arc::new(Mutex::new(VecDeque::new())),
+            tasks: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(0),
         })
     }
 
@@ -63,9 +97,11 @@ impl Cron {
         Ok(())
     }
 
-    // Add a job to execute in <duration> time
+    // Add a job to execute <duration> time after <created_at>, right away if
+    // it is already past. Replaces the pending job of the same task.
     pub async fn add_oneshot<T>(
         &self,
+        created_at: DateTime<Utc>,
         duration: Time,
         task: Task,
         callback: T,
@@ -74,20 +110,197 @@ impl Cron {
         T: 'static,
         T: Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync,
     {
-        let created_at = Utc::now();
+        let deadline = duration
+            .get::<millisecond>()
+            .ceil()
+            .to_i64()
+            .and_then(chrono::Duration::try_milliseconds)
+            .and_then(|duration| created_at.checked_add_signed(duration))
+            .with_context(|| {
+                format!("Cannot schedule {:?} after {:?}", task, duration)
+            })?;
+
+        let mut tasks = self.tasks.lock().await;
+        let entry =
+            self.spawn(task.clone(), created_at, deadline, Arc::new(callback));
+        if let Some(previous) = tasks.insert(task, entry) {
+            previous.handle.abort();
+        }
+        Ok(())
+    }
+
+    // Cancel the pending job of the task, returns whether there was one
+    pub async fn cancel(&self, task: &Task) -> bool {
+        let Some(entry) = self.tasks.lock().await.remove(task) else {
+            return false;
+        };
+        entry.handle.abort();
+        true
+    }
+
+    // Cancel the pending jobs about the sla, returns how many there were
+    pub async fn cancel_sla(&self, sla: &SlaId) -> usize {
+        let mut tasks = self.tasks.lock().await;
+        let cancelled: Vec<Task> =
+            tasks.keys().filter(|task| task.sla() == sla).cloned().collect();
+        for task in cancelled.iter() {
+            if let Some(entry) = tasks.remove(task) {
+                entry.handle.abort();
+            }
+        }
+        cancelled.len()
+    }
+
+    // Move the pending job of the task to the new deadline
+    pub async fn reschedule(
+        &self,
+        task: &Task,
+        deadline: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut tasks = self.tasks.lock().await;
+        let previous = tasks
+            .remove(task)
+            .with_context(|| format!("No pending job for {:?}", task))?;
+        previous.handle.abort();
+
+        let entry = self.spawn(
+            task.clone(),
+            previous.created_at,
+            deadline,
+            previous.callback,
+        );
+        tasks.insert(task.clone(), entry);
+        Ok(())
+    }
+
+    // Deadline of the pending job of the task
+    pub async fn get_deadline(&self, task: &Task) -> Option<DateTime<Utc>> {
+        self.tasks.lock().await.get(task).map(|entry| entry.deadline)
+    }
+
+    // Pending jobs along with their deadline, the earliest first
+    pub async fn get_pending(&self) -> Vec<(Task, DateTime<Utc>)> {
+        let mut pending: Vec<_> = self
+            .tasks
+            .lock()
+            .await
+            .values()
+            .map(|entry| (entry.task.clone(), entry.deadline))
+            .collect();
+        pending.sort_by_key(|(_, deadline)| *deadline);
+        pending
+    }
+
+    fn spawn(
+        &self,
+        task: Task,
+        created_at: DateTime<Utc>,
+        deadline: DateTime<Utc>,
+        callback: Callback,
+    ) -> TaskEntry {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let remaining = (deadline - Utc::now()).to_std().unwrap_or_default();
+
         let tasks = self.tasks.clone();
-        tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_millis(
-                duration.get::<millisecond>().ceil().to_u64().unwrap(),
-            ))
-            .await;
+        let key = task.clone();
+        let cb = callback.clone();
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(remaining).await;
 
-            tasks.lock().await.pop_front();
-            callback().await
+            {
+                let mut tasks = tasks.lock().await;
+                if !tasks.get(&key).is_some_and(|entry| entry.id == id) {
+                    // Cancelled or rescheduled in the meantime
+                    return;
+                }
+                tasks.remove(&key);
+            }
+            cb().await
         });
 
-        let entry = TaskEntry { task, created_at };
-        self.tasks.lock().await.push_back(entry);
-        Ok(())
+        TaskEntry { task, created_at, deadline, id, callback, handle }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use uuid::Uuid;
+
+    fn flag_callback(
+        flag: &Arc<AtomicBool>,
+    ) -> impl Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let flag = flag.clone();
+        move || {
+            let flag = flag.clone();
+            Box::pin(async move { flag.store(true, Ordering::SeqCst) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keyed_oneshots() {
+        let cron = Cron::new(Time::new::<second>(1.0)).await.unwrap();
+        let now = Utc::now();
+        let sla: SlaId = Uuid::new_v4().into();
+        let expire = Task::ExpireProposal(sla.clone());
+        let forget = Task::ForgetProposal(sla.clone());
+        let expired = Arc::new(AtomicBool::new(false));
+        let forgotten = Arc::new(AtomicBool::new(false));
+
+        cron.add_oneshot(
+            now,
+            Time::new::<second>(10.0),
+            expire.clone(),
+            flag_callback(&expired),
+        )
+        .await
+        .unwrap();
+        cron.add_oneshot(
+            now,
+            Time::new::<second>(5.0),
+            forget.clone(),
+            flag_callback(&forgotten),
+        )
+        .await
+        .unwrap();
+
+        let pending = cron.get_pending().await;
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].0, forget, "Not sorted by deadline");
+
+        // Move the expiry before the other job, which is cancelled
+        let sooner = Utc::now() + chrono::Duration::milliseconds(100);
+        cron.reschedule(&expire, sooner).await.unwrap();
+        assert_eq!(cron.get_deadline(&expire).await, Some(sooner));
+        assert!(cron.cancel(&forget).await);
+        assert!(!cron.cancel(&forget).await);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(expired.load(Ordering::SeqCst));
+        assert!(!forgotten.load(Ordering::SeqCst));
+        assert!(cron.get_pending().await.is_empty());
+        cron.reschedule(&expire, sooner)
+            .await
+            .expect_err("The job already ran");
+    }
+
+    #[tokio::test]
+    async fn test_oneshot_out_of_range() {
+        let cron = Cron::new(Time::new::<second>(1.0)).await.unwrap();
+        let sla: SlaId = Uuid::new_v4().into();
+        let flag = Arc::new(AtomicBool::new(false));
+
+        for duration in [1e30, f64::INFINITY, f64::NAN] {
+            cron.add_oneshot(
+                Utc::now(),
+                Time::new::<second>(duration),
+                Task::ExpireProposal(sla.clone()),
+                flag_callback(&flag),
+            )
+            .await
+            .expect_err("The deadline cannot be represented");
+        }
+        assert!(cron.get_pending().await.is_empty());
     }
 }
@@ -1,6 +1,6 @@
-use super::{FaaSBackend, RemovableFunctionRecord};
+use super::{own_functions, FaaSBackend, RemovableFunctionRecord};
 use crate::repository::k8s::K8s;
-use crate::repository::node_situation::{self, NodeSituation};
+use crate::repository::node_situation::NodeSituation;
 use crate::{
     INFLUX_ADDRESS, INFLUX_BUCKET, INFLUX_ORG, INFLUX_TOKEN,
     OTEL_EXPORTER_OTLP_ENDPOINT_FUNCTION,
@@ -10,8 +10,6 @@ use helper::env_load;
 use helper::monitoring::{
     InfluxAddress, InfluxBucket, InfluxOrg, InfluxToken,
 };
-use k8s_openapi::api::core::v1::Service;
-use kube::Api;
 use model::dto::function::{Paid, Provisioned};
 use model::SlaId;
 use openfaas::models::delete_function_request::DeleteFunctionRequest;
@@ -97,7 +95,7 @@ impl FaaSBackend for FaaSBackendImpl {
         env_vars.insert("PAID_AT".to_string(), bid.paid_at.to_rfc3339());
 
         let otel_endpoint_function =
-            env::var(OTEL_EXPORTER_OTLP_ENDPOINT_FUNCTION.to_string());
+            env::var(OTEL_EXPORTER_OTLP_ENDPOINT_FUNCTION);
         if let Ok(otel_endpoint_function) = otel_endpoint_function {
             env_vars.insert(
                 OTEL_EXPORTER_OTLP_ENDPOINT_FUNCTION.to_string(),
@@ -121,11 +119,11 @@ impl FaaSBackend for FaaSBackendImpl {
             labels: Some(HashMap::from([
                 (
                     "com.openfaas.scale.max".to_string(),
-                    bid.sla.replicas.to_string(),
+                    bid.sla.replicas_per_node().to_string(),
                 ),
                 (
                     "com.openfaas.scale.min".to_string(),
-                    bid.sla.replicas.to_string(),
+                    bid.sla.replicas_per_node().to_string(),
                 ),
             ])),
             ..Default::default()
@@ -169,4 +167,44 @@ impl FaaSBackend for FaaSBackendImpl {
         self.k8s.delete_service(function.function_name.clone()).await?;
         Ok(())
     }
+
+    async fn list_functions(&self) -> Result<Vec<String>> {
+        let functions =
+            self.client.system_functions_get().await.context(
+                "Failed to list the functions deployed on OpenFaaS",
+            )?;
+        Ok(own_functions(functions.into_iter().map(|function| function.name)))
+    }
+
+    async fn list_services(&self) -> Result<Vec<String>> {
+        Ok(own_functions(self.k8s.list_services().await?))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn remove_orphan_function(
+        &self,
+        function_name: String,
+    ) -> Result<()> {
+        self.client
+            .system_functions_delete(DeleteFunctionRequest {
+                function_name: function_name.clone(),
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to delete orphan function named '{}' from the \
+                     cluster",
+                    function_name
+                )
+            })?;
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn remove_orphan_service(
+        &self,
+        function_name: String,
+    ) -> Result<()> {
+        self.k8s.delete_service(function_name).await
+    }
 }
@@ -1,20 +1,38 @@
-use super::{FaaSBackend, RemovableFunctionRecord};
+use super::{own_functions, FaaSBackend, RemovableFunctionRecord};
+use crate::repository::k8s::{exposed_functions, SERVICE_NAME_PREFIX};
 use anyhow::Result;
 use model::dto::function::{Paid, Provisioned};
 use model::SlaId;
 use std::fmt::Debug;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::warn;
 
+/// What would be deployed on the cluster: the OpenFaaS functions and the
+/// k8s services, including the ones not deployed by the node
+#[derive(Debug, Default)]
+pub struct OfflineCluster {
+    pub functions: dashmap::DashSet<String>,
+    pub services:  dashmap::DashSet<String>,
+}
+
 #[derive(Debug)]
 pub struct FaaSBackendOfflineImpl {
     online_delay: Duration,
+    cluster:      Arc<OfflineCluster>,
 }
 
 impl FaaSBackendOfflineImpl {
     pub fn new(online_delay: Duration) -> Self {
+        Self::with_cluster(online_delay, Arc::new(OfflineCluster::default()))
+    }
+
+    pub fn with_cluster(
+        online_delay: Duration,
+        cluster: Arc<OfflineCluster>,
+    ) -> Self {
         warn!("Using offline faas backend");
-        Self { online_delay }
+        Self { online_delay, cluster }
     }
 }
 #[async_trait::async_trait]
@@ -25,7 +43,11 @@ impl FaaSBackend for FaaSBackendOfflineImpl {
         bid: Paid,
     ) -> Result<Provisioned> {
         let function_name = format!("fogfn-{}", id); // Respect DNS-1035 formatting (letter as first char of name)
-        let bid = bid.to_provisioned(function_name);
+        self.cluster.functions.insert(function_name.clone());
+        self.cluster
+            .services
+            .insert(format!("{}{}", SERVICE_NAME_PREFIX, function_name));
+        let bid = bid.to_provisioned(function_name, 0); // No port is opened offline
         Ok(bid)
     }
 
@@ -36,8 +58,39 @@ impl FaaSBackend for FaaSBackendOfflineImpl {
 
     async fn remove_function(
         &self,
-        _function: RemovableFunctionRecord,
+        function: RemovableFunctionRecord,
+    ) -> Result<()> {
+        self.remove_orphan_function(function.function_name.clone()).await?;
+        self.remove_orphan_service(function.function_name).await
+    }
+
+    async fn list_functions(&self) -> Result<Vec<String>> {
+        Ok(own_functions(
+            self.cluster.functions.iter().map(|name| name.clone()),
+        ))
+    }
+
+    async fn list_services(&self) -> Result<Vec<String>> {
+        Ok(own_functions(exposed_functions(
+            self.cluster.services.iter().map(|name| name.clone()),
+        )))
+    }
+
+    async fn remove_orphan_function(
+        &self,
+        function_name: String,
+    ) -> Result<()> {
+        self.cluster.functions.remove(&function_name);
+        Ok(())
+    }
+
+    async fn remove_orphan_service(
+        &self,
+        function_name: String,
     ) -> Result<()> {
+        self.cluster
+            .services
+            .remove(&format!("{}{}", SERVICE_NAME_PREFIX, function_name));
         Ok(())
     }
 }
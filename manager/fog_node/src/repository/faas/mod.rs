@@ -18,11 +18,37 @@ pub trait FaaSBackend: Debug + Sync + Send {
         &self,
         function: RemovableFunctionRecord,
     ) -> Result<()>;
+
+    /// Names of the functions deployed by the node
+    async fn list_functions(&self) -> Result<Vec<String>>;
+
+    /// Names of the functions exposed by a service
+    async fn list_services(&self) -> Result<Vec<String>>;
+
+    /// Remove a function that is not tracked by the node
+    async fn remove_orphan_function(
+        &self,
+        function_name: String,
+    ) -> Result<()>;
+
+    /// Remove a service exposing a function that is not tracked by the node
+    async fn remove_orphan_service(&self, function_name: String)
+        -> Result<()>;
+}
+
+/// Prefix of the names of the functions deployed by the node
+pub const FUNCTION_NAME_PREFIX: &str = "fogfn-";
+
+/// Keep the functions deployed by the node, leaving the foreign ones out
+pub fn own_functions(names: impl IntoIterator<Item = String>) -> Vec<String> {
+    names
+        .into_iter()
+        .filter(|name| name.starts_with(FUNCTION_NAME_PREFIX))
+        .collect()
 }
 
 #[derive(Debug)]
 pub struct RemovableFunctionRecord {
-    #[allow(dead_code)]
     function_name: String,
 }
 
@@ -42,6 +68,7 @@ impl From<Live> for RemovableFunctionRecord {
 pub struct FunctionTimeout(u64);
 
 #[cfg(not(feature = "offline"))]
+#[allow(clippy::module_inception)]
 pub mod faas;
 #[cfg(not(feature = "offline"))]
 pub use faas::*;
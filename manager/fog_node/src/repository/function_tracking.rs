@@ -1,13 +1,25 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use helper::http_error::{CodeContext, ErrorCode};
 use model::domain::sla::Sla;
 use model::dto::function::{
     Finishable, Finished, Live, Paid, Proposed, Provisioned,
 };
+use model::view::function::{FunctionState, FunctionStatus};
 use model::SlaId;
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use tracing::warn;
+use uom::si::f64::Time;
+use uom::si::time::millisecond;
 
 use super::faas::RemovableFunctionRecord;
 
-#[derive(Debug)]
+/// Name of the tree storing the records in the embedded database
+const FUNCTION_TRACKING_TREE: &str = "function_tracking";
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum States {
     Proposed(Proposed),
     Paid(Paid),
@@ -15,8 +27,18 @@ pub enum States {
     Live(Live),
     #[allow(dead_code)]
     Finished(Finished),
+    /// The function disappeared from the cluster before its end
+    Failed(Finished),
+    /// The proposal was not paid in time, only kept to tell the late
+    /// payments apart
+    Expired,
 }
 
+/// The bid was not paid before the paying timeout
+#[derive(Debug, thiserror::Error)]
+#[error("The bid on sla {0} expired before being paid")]
+pub struct BidExpired(pub SlaId);
+
 impl From<Proposed> for States {
     fn from(value: Proposed) -> Self { States::Proposed(value) }
 }
@@ -33,15 +55,74 @@ impl From<Finished> for States {
     fn from(value: Finished) -> Self { States::Finished(value) }
 }
 
+/// Lifecycle of the functions on the node. The proposals and the paid
+/// functions are also written to the disk, if a store is configured, so that
+/// they can be recovered after a restart.
 #[derive(Debug, Default)]
 pub struct FunctionTracking {
     database: dashmap::DashMap<SlaId, States>,
+    store:    Option<sled::Tree>,
 }
 
 impl FunctionTracking {
-    pub fn insert(&self, record: Proposed) {
+    /// Persist the lifecycle of the functions into the `db`, loading the
+    /// records it already contains
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let store = db
+            .open_tree(FUNCTION_TRACKING_TREE)
+            .context("Failed to open the function tracking tree")?;
+        let database = dashmap::DashMap::new();
+        for entry in store.iter() {
+            let (key, value) =
+                entry.context("Failed to read a function tracking record")?;
+            let key = String::from_utf8_lossy(&key);
+            let id: SlaId = key.parse().with_context(|| {
+                format!("Failed to parse the function tracking key {}", key)
+            })?;
+            let record: States =
+                serde_json::from_slice(&value).with_context(|| {
+                    format!(
+                        "Failed to deserialize the function tracking record \
+                         {}",
+                        id
+                    )
+                })?;
+            database.insert(id, record);
+        }
+        Ok(Self { database, store: Some(store) })
+    }
+
+    /// Write the new state of the record to the disk. Only the proposals and
+    /// the paid functions need to be recovered, the others are removed. The
+    /// tree is flushed periodically by sled, not to block the runtime.
+    fn persist(&self, id: &SlaId, record: &States) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let key = id.to_string();
+        match record {
+            States::Proposed(_)
+            | States::Paid(_)
+            | States::Provisioned(_)
+            | States::Live(_) => {
+                let value = serde_json::to_vec(record).with_context(|| {
+                    format!("Failed to serialize the record of {}", id)
+                })?;
+                store.insert(key, value)
+            }
+            States::Finished(_) | States::Failed(_) | States::Expired => {
+                store.remove(key)
+            }
+        }
+        .with_context(|| format!("Failed to write the record of {}", id))?;
+        Ok(())
+    }
+
+    pub fn insert(&self, record: Proposed) -> Result<()> {
         let id = record.sla.id.clone();
-        self.database.insert(id.clone(), record.into());
+        let record = record.into();
+        self.persist(&id, &record)?;
+        self.database.insert(id.clone(), record);
         #[cfg(test)]
         assert!(matches!(
             self.database.get(&id).unwrap().value(),
@@ -49,6 +130,54 @@ impl FunctionTracking {
         ));
         #[cfg(test)]
         assert_eq!(self.get_proposed(&id).unwrap().sla.id, id);
+        Ok(())
+    }
+
+    /// Remove the record from the disk, the node would otherwise recover a
+    /// proposal that is gone
+    fn unpersist(&self, id: &SlaId) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        if let Err(err) = store.remove(id.to_string()) {
+            warn!("Failed to remove the record of {}: {:?}", id, err);
+        }
+    }
+
+    /// Drop the proposal if it has not been paid yet
+    pub fn expire_proposed(&self, id: &SlaId) {
+        let Some(mut previous_record) = self.database.get_mut(id) else {
+            return;
+        };
+        let value = previous_record.value_mut();
+        if let States::Proposed(_) = value {
+            *value = States::Expired;
+            self.unpersist(id);
+        };
+    }
+
+    /// Drop the proposal if it has not been paid yet, returning it
+    pub fn remove_proposed(&self, id: &SlaId) -> Option<Proposed> {
+        let (_, record) = self.database.remove_if(id, |_, record| {
+            matches!(record, States::Proposed(_))
+        })?;
+        self.unpersist(id);
+        match record {
+            States::Proposed(record) => Some(record),
+            _ => None,
+        }
+    }
+
+    /// Forget about the expired proposal
+    pub fn forget_expired(&self, id: &SlaId) {
+        self.database
+            .remove_if(id, |_, record| matches!(record, States::Expired));
+    }
+
+    pub fn is_expired(&self, id: &SlaId) -> bool {
+        self.database
+            .get(id)
+            .is_some_and(|x| matches!(x.value(), States::Expired))
     }
 
     pub fn get_proposed(&self, id: &SlaId) -> Option<Proposed> {
@@ -65,42 +194,92 @@ impl FunctionTracking {
         })
     }
 
-    pub fn save_paid(&self, id: &SlaId, record: Paid) {
+    pub fn save_paid(&self, id: &SlaId, record: Paid) -> Result<()> {
         let Some(mut previous_record) = self.database.get_mut(id) else {
-            return;
+            return Ok(());
         };
         let value = previous_record.value_mut();
         if let States::Proposed(_) = value {
             *value = record.into();
+            self.persist(id, value)?;
         };
+        Ok(())
     }
 
-    pub fn save_provisioned(&self, id: &SlaId, record: Provisioned) {
+    pub fn save_provisioned(
+        &self,
+        id: &SlaId,
+        record: Provisioned,
+    ) -> Result<()> {
         let Some(mut previous_record) = self.database.get_mut(id) else {
-            return;
+            return Ok(());
         };
         let value = previous_record.value_mut();
         if let States::Paid(_) = value {
             *value = record.into();
+            self.persist(id, value)?;
         };
+        Ok(())
     }
 
-    pub fn save_live(&self, id: &SlaId, record: Live) {
+    pub fn save_live(&self, id: &SlaId, record: Live) -> Result<()> {
         let Some(mut previous_record) = self.database.get_mut(id) else {
-            return;
+            return Ok(());
         };
         let value = previous_record.value_mut();
         if let States::Provisioned(_) = value {
             *value = record.into();
+            self.persist(id, value)?;
         };
+        Ok(())
     }
 
-    pub fn save_finished(&self, id: &SlaId, record: Finished) {
+    /// Lengthen the duration of the running function, returns the updated
+    /// sla
+    pub fn extend(&self, id: &SlaId, duration: Time) -> Result<Sla> {
+        let mut record = self
+            .database
+            .get_mut(id)
+            .with_code(ErrorCode::NotFound, || {
+                format!("No record of the function {}", id)
+            })?;
+        let value = record.value_mut();
+        let sla = match value {
+            States::Paid(Paid { sla, .. })
+            | States::Provisioned(Provisioned { sla, .. })
+            | States::Live(Live { sla, .. }) => sla,
+            _ => {
+                return Err(ErrorCode::Conflict.error(format!(
+                    "Function {} is not running, cannot extend it",
+                    id
+                )))
+            }
+        };
+        sla.duration += duration;
+        let sla = sla.clone();
+        self.persist(id, value)?;
+        Ok(sla)
+    }
+
+    pub fn save_finished(&self, id: &SlaId, record: Finished) -> Result<()> {
         let Some(mut previous_record) = self.database.get_mut(id) else {
-            return;
+            return Ok(());
         };
         let value = previous_record.value_mut();
         *value = record.into();
+        self.persist(id, value)
+    }
+
+    pub fn save_failed(&self, id: &SlaId, record: Finished) -> Result<()> {
+        let Some(mut previous_record) = self.database.get_mut(id) else {
+            return Ok(());
+        };
+        let value = previous_record.value_mut();
+        if let States::Provisioned(_) | States::Live(_) = value {
+            *value = States::Failed(record);
+            self.persist(id, value)?;
+        };
+        Ok(())
     }
 
     pub fn get_provisioned(&self, id: &SlaId) -> Option<Provisioned> {
@@ -156,4 +335,357 @@ impl FunctionTracking {
             _ => None,
         })
     }
+
+    pub fn get_failed(&self, id: &SlaId) -> Option<Finished> {
+        self.database.get(id).and_then(|x| match x.value() {
+            States::Failed(x) => Some(x.clone()),
+            _ => None,
+        })
+    }
+
+    /// Where the function is in its lifecycle, along with the details known
+    /// at that step
+    pub fn get_status(
+        &self,
+        id: &SlaId,
+        now: DateTime<Utc>,
+    ) -> Option<FunctionStatus> {
+        let record = self.database.get(id)?;
+        let mut status = FunctionStatus {
+            id:            id.clone(),
+            state:         FunctionState::Expired,
+            sla:           None,
+            node:          None,
+            function_name: None,
+            opened_port:   None,
+            paid_at:       None,
+            ends_at:       None,
+            remaining:     None,
+        };
+        match record.value() {
+            States::Proposed(Proposed { sla, node, .. }) => {
+                status.state = FunctionState::Proposed;
+                status.sla = Some(sla.clone());
+                status.node = Some(node.clone());
+            }
+            States::Paid(Paid { sla, node, paid_at, .. }) => {
+                status.state = FunctionState::Paid;
+                status.sla = Some(sla.clone());
+                status.node = Some(node.clone());
+                status.paid_at = Some(*paid_at);
+            }
+            States::Provisioned(Provisioned {
+                sla,
+                node,
+                function_name,
+                opened_port,
+                paid_at,
+                ..
+            }) => {
+                status.state = FunctionState::Provisioned;
+                status.sla = Some(sla.clone());
+                status.node = Some(node.clone());
+                status.function_name = Some(function_name.clone());
+                status.opened_port = Some(*opened_port);
+                status.paid_at = Some(*paid_at);
+            }
+            States::Live(Live {
+                sla,
+                node,
+                function_name,
+                opened_port,
+                paid_at,
+                ..
+            }) => {
+                status.state = FunctionState::Live;
+                status.sla = Some(sla.clone());
+                status.node = Some(node.clone());
+                status.function_name = Some(function_name.clone());
+                status.opened_port = Some(*opened_port);
+                status.paid_at = Some(*paid_at);
+            }
+            States::Finished(Finished { sla, node, .. }) => {
+                status.state = FunctionState::Finished;
+                status.sla = Some(sla.clone());
+                status.node = Some(node.clone());
+            }
+            States::Failed(Finished { sla, node, .. }) => {
+                status.state = FunctionState::Failed;
+                status.sla = Some(sla.clone());
+                status.node = Some(node.clone());
+            }
+            States::Expired => (),
+        }
+
+        if let (Some(paid_at), Some(sla)) = (status.paid_at, &status.sla) {
+            let ends_at = paid_at
+                + chrono::Duration::milliseconds(
+                    sla.duration.get::<millisecond>().ceil().to_i64().unwrap(),
+                );
+            let remaining = (ends_at - now).num_milliseconds().max(0);
+            status.ends_at = Some(ends_at);
+            status.remaining =
+                Some(Time::new::<millisecond>(remaining as f64));
+        }
+        Some(status)
+    }
+
+    /// The status of all the tracked functions
+    pub fn get_all_status(&self, now: DateTime<Utc>) -> Vec<FunctionStatus> {
+        let ids: Vec<SlaId> =
+            self.database.iter().map(|x| x.key().clone()).collect();
+        ids.iter().filter_map(|id| self.get_status(id, now)).collect()
+    }
+
+    /// The functions expected to be deployed on the cluster, along with
+    /// their function name
+    pub fn get_deployed(&self) -> Vec<(SlaId, String)> {
+        self.database
+            .iter()
+            .filter_map(|x| match x.value() {
+                States::Provisioned(Provisioned { function_name, .. })
+                | States::Live(Live { function_name, .. }) => {
+                    Some((x.key().clone(), function_name.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The proposals that are not paid yet, as loaded from the disk
+    pub fn get_recoverable_proposals(&self) -> Vec<Proposed> {
+        self.database
+            .iter()
+            .filter_map(|x| match x.value() {
+                States::Proposed(x) => Some(x.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The payment of the functions that are paid and not finished yet, as
+    /// loaded from the disk
+    pub fn get_recoverable(&self) -> Vec<Paid> {
+        self.database
+            .iter()
+            .filter_map(|x| match x.value() {
+                States::Paid(x) => Some(x.clone()),
+                States::Provisioned(Provisioned {
+                    bid,
+                    sla,
+                    node,
+                    paid_at,
+                    ..
+                })
+                | States::Live(Live { bid, sla, node, paid_at, .. }) => {
+                    Some(Paid {
+                        bid:     *bid,
+                        sla:     sla.clone(),
+                        node:    node.clone(),
+                        paid_at: *paid_at,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "offline")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::cron::Task;
+    use crate::repository::k8s::OFFLINE_NODE_K8S;
+    use crate::service::fixtures::{
+        get_auction_impl, get_auction_impl_tracked, market_connected, sla,
+        Instance,
+    };
+    use helper::uom_helper::cpu_ratio::cpu;
+    use model::dto::node::{BidValuationConfig, PlacementStrategyKind};
+    use model::view::auction::AccumulatedLatency;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use uom::si::information::megabyte;
+    use uom::si::rational64::{Information, Ratio};
+
+    /// The proposal is not paid in time: it expires and the late payment is
+    /// told so
+    #[tokio::test]
+    async fn test_proposal_expiry() {
+        let Instance {
+            auction, function_life, function_tracking, cron, ..
+        } = get_auction_impl(market_connected()).await;
+        let sla = sla();
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        assert!(function_tracking.get_proposed(&id).is_some());
+
+        // Only sleep until the expiry, the proposal is forgotten later on
+        tokio::time::pause();
+        let expiry = cron
+            .get_deadline(&Task::ExpireProposal(id.clone()))
+            .await
+            .expect("The expiry of the proposal is not scheduled");
+        let remaining =
+            expiry - Utc::now() + chrono::Duration::milliseconds(100);
+        tokio::time::sleep(remaining.to_std().unwrap_or_default()).await;
+
+        assert!(function_tracking.get_proposed(&id).is_none());
+        let err = function_life
+            .pay_function(id.clone())
+            .await
+            .expect_err("Paying an expired bid should fail");
+        assert!(err.downcast_ref::<BidExpired>().is_some());
+    }
+
+    /// The node restarts while its bids are pending: they must still hold
+    /// their resources, and expire on time
+    #[tokio::test]
+    async fn test_recovery_of_proposals() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let Instance { auction, function_life, .. } =
+            get_auction_impl_tracked(
+                market_connected(),
+                PlacementStrategyKind::default(),
+                BidValuationConfig::default(),
+                Arc::new(FunctionTracking::new(&db).unwrap()),
+            )
+            .await;
+
+        let (pending, rejected) = (sla(), sla());
+        let acc = AccumulatedLatency::default();
+        for sla in [&pending, &rejected] {
+            auction
+                .bid_on(sla.clone(), &acc)
+                .await
+                .expect("Bidding failed")
+                .expect("Bid refused");
+        }
+        function_life
+            .reject_function(rejected.id.clone())
+            .await
+            .expect("Rejecting failed");
+
+        // Restart the node on the same database
+        let Instance {
+            function_life,
+            function_tracking,
+            resource_tracking,
+            cron,
+            ..
+        } = get_auction_impl_tracked(
+            market_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig::default(),
+            Arc::new(FunctionTracking::new(&db).unwrap()),
+        )
+        .await;
+
+        function_life.recover().await.expect("Recovery failed");
+
+        assert!(function_tracking.get_proposed(&rejected.id).is_none());
+        assert!(function_tracking.get_proposed(&pending.id).is_some());
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (pending.memory, pending.cpu));
+
+        tokio::time::pause();
+        let expiry = cron
+            .get_deadline(&Task::ExpireProposal(pending.id.clone()))
+            .await
+            .expect("The expiry of the proposal is not scheduled");
+        let remaining =
+            expiry - Utc::now() + chrono::Duration::milliseconds(100);
+        tokio::time::sleep(remaining.to_std().unwrap_or_default()).await;
+
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(
+            reserved,
+            (
+                Information::new::<megabyte>(num_rational::Ratio::new(0, 1)),
+                Ratio::new::<cpu>(num_rational::Ratio::new(0, 1))
+            )
+        );
+        let err = function_life
+            .pay_function(pending.id.clone())
+            .await
+            .expect_err("Paying an expired bid should fail");
+        assert!(err.downcast_ref::<BidExpired>().is_some());
+    }
+
+    /// The node restarts while the function is running: the function must
+    /// still be accounted for, and finish on time
+    #[tokio::test]
+    async fn test_recovery_after_restart() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let Instance { auction, function_life, .. } =
+            get_auction_impl_tracked(
+                market_connected(),
+                PlacementStrategyKind::default(),
+                BidValuationConfig::default(),
+                Arc::new(FunctionTracking::new(&db).unwrap()),
+            )
+            .await;
+
+        let sla = sla();
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+        function_life
+            .provision_function(id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+
+        // Restart the node on the same database
+        let Instance {
+            function_life,
+            function_tracking,
+            resource_tracking,
+            ..
+        } = get_auction_impl_tracked(
+            market_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig::default(),
+            Arc::new(FunctionTracking::new(&db).unwrap()),
+        )
+        .await;
+
+        function_life.recover().await.expect("Recovery failed");
+
+        let (ram, cc) =
+            resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(cc, sla.cpu);
+        assert_eq!(ram, sla.memory);
+        assert!(function_tracking.get_removable(&id).is_some());
+
+        tokio::time::sleep(Duration::from_secs(4)).await;
+
+        function_tracking
+            .get_finished(&id)
+            .expect("Function is not finished, not ok");
+        let (ram, cc) =
+            resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(
+            ram,
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1))
+        );
+        assert_eq!(cc, Ratio::new::<cpu>(num_rational::Ratio::new(0, 1)));
+    }
 }
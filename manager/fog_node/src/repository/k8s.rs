@@ -9,6 +9,22 @@ pub struct K8s;
 #[cfg(feature = "offline")]
 pub const OFFLINE_NODE_K8S: &str = "node";
 
+/// Prefix of the names of the services exposing the functions
+pub const SERVICE_NAME_PREFIX: &str = "dynamic-service-";
+
+/// Names of the functions exposed by the services, leaving the other
+/// services out
+pub fn exposed_functions(
+    service_names: impl IntoIterator<Item = String>,
+) -> Vec<String> {
+    service_names
+        .into_iter()
+        .filter_map(|name| {
+            name.strip_prefix(SERVICE_NAME_PREFIX).map(str::to_string)
+        })
+        .collect()
+}
+
 impl K8s {
     #[allow(dead_code)]
     pub fn new() -> Self { Self }
@@ -108,8 +124,7 @@ impl K8s {
         let services: Api<Service> =
             Api::namespaced(client.clone(), "openfaas-fn");
 
-        let service_name =
-            format!("dynamic-service-{}", function_name.clone());
+        let service_name = format!("{}{}", SERVICE_NAME_PREFIX, function_name);
 
         let target_port = 30432;
 
@@ -164,14 +179,37 @@ impl K8s {
         let services: Api<Service> =
             Api::namespaced(client.clone(), "openfaas-fn");
 
-        let service_name =
-            format!("dynamic-service-{}", function_name.clone());
+        let service_name = format!("{}{}", SERVICE_NAME_PREFIX, function_name);
 
         services.delete(&service_name, &Default::default()).await?;
 
         Ok(())
     }
 
+    /// Names of the functions exposed by a [`SERVICE_NAME_PREFIX`] service
+    #[cfg(not(feature = "offline"))]
+    pub async fn list_services(&self) -> anyhow::Result<Vec<String>> {
+        use anyhow::Context;
+        use k8s_openapi::api::core::v1::Service;
+        use kube::api::ListParams;
+        use kube::{Api, Client};
+
+        let client = Client::try_default()
+            .await
+            .context("Failed to create the K8S client")?;
+        let services: Api<Service> =
+            Api::namespaced(client.clone(), "openfaas-fn");
+
+        let services = services
+            .list(&ListParams::default())
+            .await
+            .context("Failed to list services from the k8s cluster")?;
+
+        Ok(exposed_functions(
+            services.into_iter().filter_map(|service| service.metadata.name),
+        ))
+    }
+
     #[cfg(feature = "offline")]
     pub async fn get_k8s_metrics(&self) -> Result<HashMap<String, Metrics>> {
         use helper::uom_helper::cpu_ratio::cpu;
@@ -24,14 +24,14 @@ const TIMEOUT_MS: u64 = 500;
 #[derive(Debug)]
 struct Latencies {
     moving_average: ExponentialMovingAverage,
-    moving_median: MovingMedian,
-    packet_loss: PacketLossRing,
+    moving_median:  MovingMedian,
+    packet_loss:    PacketLossRing,
 }
 
 #[derive(Debug)]
 struct PacketLossRing {
-    buffer: Vec<Ratio>,
-    cursor: usize,
+    buffer:      Vec<Ratio>,
+    cursor:      usize,
     window_size: usize,
 }
 
@@ -65,10 +65,10 @@ impl PacketLossRing {
 
 #[derive(Debug)]
 pub struct LatencyEstimationImpl {
-    node_situation: Arc<NodeSituation>,
-    metrics: Arc<MetricsExporter>,
-    latency: Arc<dashmap::DashMap<NodeId, Latencies>>,
-    alpha: model::domain::exp_average::Alpha,
+    node_situation:            Arc<NodeSituation>,
+    metrics:                   Arc<MetricsExporter>,
+    latency:                   Arc<dashmap::DashMap<NodeId, Latencies>>,
+    alpha:                     model::domain::exp_average::Alpha,
     moving_median_window_size: MovingMedianSize,
 }
 #[async_trait]
@@ -179,8 +179,7 @@ impl LatencyEstimationImpl {
 
         let nb = (NB_ICMP_SENT - nb_failed) as f64;
         let raw_latency = durations / nb;
-        let raw_packet_loss =
-            ((nb_failed as f64) / (NB_ICMP_SENT as f64)) as f64;
+        let raw_packet_loss = (nb_failed as f64) / (NB_ICMP_SENT as f64);
 
         self.update_latency(
             &self.latency,
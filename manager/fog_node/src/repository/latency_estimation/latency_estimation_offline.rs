@@ -20,15 +20,14 @@ impl LatencyEstimation for LatencyEstimationOfflineImpl {
     async fn latency_to_neighbors(&self) -> Result<()> { Ok(()) }
 
     async fn get_latency_to(&self, id: &NodeId) -> Option<Latency> {
-        match self.node_situation.get_fog_node_neighbor(id) {
-            None => None,
-            Some(NodeDescription { latency, .. }) => Some(Latency {
+        self.node_situation.get_fog_node_neighbor(id).map(
+            |NodeDescription { latency, .. }| Latency {
                 median:              latency,
                 average:             latency,
                 interquantile_range: Time::new::<second>(0.0),
                 packet_loss:         Ratio::new::<ratio>(0.0),
-            }),
-        }
+            },
+        )
     }
 }
 
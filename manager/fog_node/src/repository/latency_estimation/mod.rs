@@ -19,6 +19,7 @@ pub trait LatencyEstimation: Debug + Send + Sync {
 }
 
 #[cfg(not(feature = "offline"))]
+#[allow(clippy::module_inception)]
 pub mod latency_estimation;
 #[cfg(not(feature = "offline"))]
 pub use latency_estimation::*;
@@ -1,13 +1,17 @@
 use crate::NodeSituation;
 use anyhow::{Context, Result};
 use backoff::ExponentialBackoffBuilder;
+use helper::http_error::{CodeContext, ErrorCode};
 use model::dto::node::NodeDescription;
 use model::view::auction::{BidProposals, BidRequest};
-use model::view::node::RegisterNode;
-use model::NodeId;
+use model::view::node::{
+    CapacitySummary, DeregisterNode, MoveNode, MoveNodeRequest, RegisterNode,
+};
+use model::{FogNodeHTTPPort, NodeId};
 use reqwest::Response;
 use serde::Serialize;
 use std::fmt::Debug;
+use std::net::IpAddr;
 use std::sync::Arc;
 use tracing::{instrument, trace};
 
@@ -39,26 +43,32 @@ impl NodeQuery {
         Ok(response)
     }
 
-    #[instrument(level = "trace", skip(self))]
-    pub async fn register_to_parent(
-        &self,
-        register: RegisterNode,
-    ) -> Result<()> {
+    /// URL of the route `path` on the parent, the market for the root node
+    fn parent_url(&self, path: &str) -> Result<String> {
         // Ignore the type of port since it doesn't matter-explicitely-here
         let url = if self.node_situation.is_market() {
             let (addr, port) =
                 self.node_situation.get_market_node_address().context(
                     "Failed to retrieve the URL of the parent (market) node",
                 )?;
-            format!("http://{addr}:{port}/api/register")
+            format!("http://{addr}:{port}/api/{path}")
         } else {
             let (addr, port) =
                 self.node_situation.get_parent_node_address().context(
                     "Failed to retrieve the URL of the parent (other node) \
                      node",
                 )?;
-            format!("http://{addr}:{port}/api/register")
+            format!("http://{addr}:{port}/api/{path}")
         };
+        Ok(url)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    pub async fn register_to_parent(
+        &self,
+        register: RegisterNode,
+    ) -> Result<()> {
+        let url = self.parent_url("register")?;
 
         trace!("Registering to {}", url);
         let backoff = ExponentialBackoffBuilder::default().build();
@@ -73,6 +83,93 @@ impl NodeQuery {
         Ok(())
     }
 
+    #[instrument(level = "trace", skip(self))]
+    pub async fn deregister_to_parent(
+        &self,
+        deregister: &DeregisterNode,
+    ) -> Result<()> {
+        let url = self.parent_url("deregister")?;
+        self.post(url.as_str(), deregister)
+            .await
+            .with_code(ErrorCode::UpstreamFailure, || {
+                format!("Failed to deregister to {}", url)
+            })?;
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    pub async fn move_to_parent(&self, move_node: &MoveNode) -> Result<()> {
+        let url = self.parent_url("reparent")?;
+        self.post(url.as_str(), move_node)
+            .await
+            .with_code(ErrorCode::UpstreamFailure, || {
+                format!("Failed to report the move to {}", url)
+            })?;
+        Ok(())
+    }
+
+    /// Report the move to the parent this node is moving under, before it
+    /// becomes its parent
+    #[instrument(level = "trace", skip(self))]
+    pub async fn move_to_new_parent(
+        &self,
+        move_node: &MoveNode,
+        ip: IpAddr,
+        port_http: &FogNodeHTTPPort,
+    ) -> Result<()> {
+        let url = format!("http://{ip}:{port_http}/api/reparent");
+        self.post(url.as_str(), move_node)
+            .await
+            .with_code(ErrorCode::UpstreamFailure, || {
+                format!("Failed to report the move to {}", url)
+            })?;
+        Ok(())
+    }
+
+    /// Ask a child to re-attach itself, along with its subtree, under
+    /// another parent
+    #[instrument(level = "trace", skip(self))]
+    pub async fn move_child(
+        &self,
+        child: &NodeDescription,
+        request: &MoveNodeRequest,
+    ) -> Result<()> {
+        let NodeDescription { ip, port_http, .. } = child;
+        let url = format!("http://{ip}:{port_http}/api/move");
+        self.post(url.as_str(), request)
+            .await
+            .with_code(ErrorCode::UpstreamFailure, || {
+                format!("Failed to move the child at {}", url)
+            })?;
+        Ok(())
+    }
+
+    /// Single attempt, the next heartbeat acts as a retry
+    #[instrument(level = "trace", skip(self))]
+    pub async fn heartbeat_to_parent(
+        &self,
+        register: &RegisterNode,
+    ) -> Result<()> {
+        let url = self.parent_url("heartbeat")?;
+        self.post(url.as_str(), register)
+            .await
+            .with_context(|| format!("Failed to send heartbeat to {}", url))?;
+        Ok(())
+    }
+
+    /// Single attempt, the next summary replaces it anyway
+    #[instrument(level = "trace", skip(self))]
+    pub async fn capacity_to_parent(
+        &self,
+        summary: &CapacitySummary,
+    ) -> Result<()> {
+        let url = self.parent_url("capacity")?;
+        self.post(url.as_str(), summary).await.with_context(|| {
+            format!("Failed to send the capacity summary to {}", url)
+        })?;
+        Ok(())
+    }
+
     #[instrument(level = "trace", skip(self, request))]
     pub async fn request_neighbor_bid(
         &self,
@@ -1,5 +1,8 @@
 use model::dto::node::NodeCategory::{MarketConnected, NodeConnected};
-use model::dto::node::{MaxInFlight, NodeDescription, NodeSituationData};
+use model::dto::node::{
+    BidValuationConfig, MaxInFlight, NodeCategory, NodeDescription,
+    NodeSituationData, PlacementStrategyKind,
+};
 use model::{
     FogNodeFaaSPortExternal, FogNodeHTTPPort, MarketHTTPPort, NodeId,
 };
@@ -19,17 +22,51 @@ impl NodeSituation {
         Self { database: situation }
     }
 
+    fn situation(&self) -> NodeCategory {
+        self.database
+            .situation
+            .read()
+            .expect("The lock on the situation of the node is poisoned")
+            .clone()
+    }
+
     pub fn register(&self, id: NodeId, description: NodeDescription) {
         self.database.children.insert(id, description);
     }
 
+    pub fn unregister(&self, id: &NodeId) {
+        self.database.children.remove(id);
+    }
+
+    /// Attach this node to another parent
+    pub fn set_situation(&self, situation: NodeCategory) {
+        *self
+            .database
+            .situation
+            .write()
+            .expect("The lock on the situation of the node is poisoned") =
+            situation;
+    }
+
+    pub fn get_children(&self) -> Vec<(NodeId, NodeDescription)> {
+        self.database
+            .children
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect()
+    }
+
+    pub fn is_child(&self, id: &NodeId) -> bool {
+        self.database.children.contains_key(id)
+    }
+
     pub fn get_fog_node_neighbor(
         &self,
         id: &NodeId,
     ) -> Option<NodeDescription> {
         match self.database.children.get(id).map(|x| x.clone()) {
             Some(x) => Some(x),
-            None => match &self.database.situation {
+            None => match self.situation() {
                 NodeConnected {
                     parent_node_ip,
                     parent_node_port_http,
@@ -38,14 +75,14 @@ impl NodeSituation {
                     parent_latency,
                     ..
                 } => {
-                    if parent_id == id {
+                    if &parent_id == id {
                         Some(NodeDescription {
-                            ip: *parent_node_ip,
-                            port_http: parent_node_port_http.clone(),
+                            ip: parent_node_ip,
+                            port_http: parent_node_port_http,
                             advertised_bandwidth: self
                                 .get_my_advertised_bandwidth(),
                             #[cfg(feature = "offline")]
-                            latency: *parent_latency,
+                            latency: parent_latency,
                         })
                     } else {
                         None
@@ -59,16 +96,24 @@ impl NodeSituation {
     pub fn get_my_id(&self) -> NodeId { self.database.my_id.clone() }
 
     pub fn get_my_advertised_bandwidth(&self) -> InformationRate {
-        self.database.my_advertised_bandwidth.clone()
+        self.database.my_advertised_bandwidth
     }
 
     pub fn get_max_in_flight_functions_proposals(&self) -> MaxInFlight {
         self.database.max_in_flight_functions_proposals.clone()
     }
 
+    pub fn get_placement_strategy(&self) -> PlacementStrategyKind {
+        self.database.placement_strategy
+    }
+
+    pub fn get_bid_valuation(&self) -> BidValuationConfig {
+        self.database.bid_valuation.clone()
+    }
+
     pub fn get_parent_id(&self) -> Option<NodeId> {
-        match &self.database.situation {
-            NodeConnected { parent_id, .. } => Some(parent_id.clone()),
+        match self.situation() {
+            NodeConnected { parent_id, .. } => Some(parent_id),
             _ => None,
         }
     }
@@ -76,24 +121,24 @@ impl NodeSituation {
     pub fn get_my_tags(&self) -> Vec<String> { self.database.tags.clone() }
 
     pub fn is_market(&self) -> bool {
-        matches!(self.database.situation, MarketConnected { .. })
+        matches!(self.situation(), MarketConnected { .. })
     }
 
     pub fn get_parent_node_address(
         &self,
     ) -> Option<(IpAddr, FogNodeHTTPPort)> {
-        match &self.database.situation {
+        match self.situation() {
             NodeConnected {
                 parent_node_ip, parent_node_port_http, ..
-            } => Some((*parent_node_ip, parent_node_port_http.clone())),
+            } => Some((parent_node_ip, parent_node_port_http)),
             _ => None,
         }
     }
 
     pub fn get_market_node_address(&self) -> Option<(IpAddr, MarketHTTPPort)> {
-        match &self.database.situation {
+        match self.situation() {
             MarketConnected { market_ip, market_port, .. } => {
-                Some((*market_ip, market_port.clone()))
+                Some((market_ip, market_port))
             }
             _ => None,
         }
@@ -103,9 +148,9 @@ impl NodeSituation {
         let mut ret: Vec<NodeId> =
             self.database.children.iter().map(|x| x.key().clone()).collect();
 
-        match &self.database.situation {
+        match self.situation() {
             NodeConnected { parent_id, .. } => {
-                ret.push(parent_id.clone());
+                ret.push(parent_id);
             }
             MarketConnected { .. } => (),
         }
@@ -134,8 +179,8 @@ impl NodeSituation {
 
     #[cfg(feature = "offline")]
     pub fn get_my_offline_latency(&self) -> Time {
-        match &self.database.situation {
-            NodeConnected { parent_latency, .. } => *parent_latency,
+        match self.situation() {
+            NodeConnected { parent_latency, .. } => parent_latency,
             MarketConnected { .. } => Time::new::<uom::si::time::second>(0.0),
         }
     }
@@ -4,6 +4,7 @@ use anyhow::{ensure, Context, Result};
 use chrono::Utc;
 use helper::monitoring::MetricsExporter;
 use helper::uom_helper::cpu_ratio::cpu;
+use model::SlaId;
 use std::fmt::Debug;
 use std::sync::Arc;
 use tracing::warn;
@@ -11,11 +12,23 @@ use uom::num_traits::ToPrimitive;
 use uom::si::information::{gigabyte, megabyte};
 use uom::si::rational64::{Information, Ratio};
 
+/// Resources tentatively held for a bid, until it is paid or expires
+#[derive(Debug, Clone)]
+pub struct Reservation {
+    pub node:   String,
+    pub memory: Information,
+    pub cpu:    Ratio,
+}
+
 #[derive(Debug)]
 pub struct ResourceTracking {
     metrics:             Arc<MetricsExporter>,
     resources_available: dashmap::DashMap<String, (Information, Ratio)>,
     resources_used:      dashmap::DashMap<String, (Information, Ratio)>,
+    reservations:        dashmap::DashMap<SlaId, Reservation>,
+    /// Serializes the decisions made on the reservations, so that bids and
+    /// payments cannot overcommit a node
+    reservations_lock:   tokio::sync::Mutex<()>,
     nodes:               Vec<String>,
 }
 
@@ -111,7 +124,14 @@ impl ResourceTracking {
         let nodes =
             { resources_available.iter().map(|x| x.key().clone()).collect() };
 
-        Ok(Self { resources_available, metrics, resources_used, nodes })
+        Ok(Self {
+            resources_available,
+            metrics,
+            resources_used,
+            reservations: dashmap::DashMap::new(),
+            reservations_lock: tokio::sync::Mutex::new(()),
+            nodes,
+        })
     }
 
     /// Check if the key exists in all storages
@@ -231,5 +251,143 @@ impl ResourceTracking {
         Ok(*self.resources_available.get(name).unwrap())
     }
 
+    /// Hold the resources for the bid on the sla, replacing any previous
+    /// hold for the same sla
+    pub async fn reserve(
+        &self,
+        id: SlaId,
+        name: String,
+        memory: Information,
+        cc: Ratio,
+    ) -> Result<()> {
+        self.key_exists(&name)
+            .await
+            .with_context(|| format!("Cannot reserve on {}", name))?;
+        self.reservations
+            .insert(id, Reservation { node: name, memory, cpu: cc });
+        Ok(())
+    }
+
+    /// Sum of the resources held for the bids on the node
+    pub async fn get_reserved(
+        &self,
+        name: &'_ str,
+    ) -> Result<(Information, Ratio)> {
+        self.key_exists(name)
+            .await
+            .with_context(|| format!("Cannot get reserved metric {}", name))?;
+        let zero = (
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1)),
+            Ratio::new::<cpu>(num_rational::Ratio::new(0, 1)),
+        );
+        Ok(self
+            .reservations
+            .iter()
+            .filter(|reservation| reservation.node == name)
+            .fold(zero, |(memory, cc), reservation| {
+                (memory + reservation.memory, cc + reservation.cpu)
+            }))
+    }
+
+    /// Hold it while checking the resources and reserving or committing them
+    pub async fn lock_reservations(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.reservations_lock.lock().await
+    }
+
+    pub fn get_reservation(&self, id: &SlaId) -> Option<Reservation> {
+        self.reservations.get(id).map(|x| x.value().clone())
+    }
+
+    /// Give back the resources held for the bid on the sla
+    pub fn release_reservation(&self, id: &SlaId) -> Option<Reservation> {
+        self.reservations.remove(id).map(|(_, reservation)| reservation)
+    }
+
     pub fn get_nodes(&self) -> &Vec<String> { &self.nodes }
 }
+
+#[cfg(feature = "offline")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::cron::Task;
+    use crate::repository::k8s::OFFLINE_NODE_K8S;
+    use crate::service::fixtures::{
+        get_auction_impl, market_connected, sla, Instance,
+    };
+    use helper::uom_helper::cpu_ratio::millicpu;
+    use model::domain::sla::Sla;
+    use model::view::auction::AccumulatedLatency;
+
+    /// A bid holds its resources until it is paid or expires, so that
+    /// concurrent bids cannot overcommit the node
+    #[tokio::test]
+    async fn test_soft_reservation() {
+        let Instance {
+            auction, function_life, resource_tracking, cron, ..
+        } = get_auction_impl(market_connected()).await;
+        let (available_ram, available_cpu) =
+            resource_tracking.get_available(OFFLINE_NODE_K8S).await.unwrap();
+        let sla = |memory, cc| Sla { memory, cpu: cc, ..sla() };
+        let zero_ram =
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1));
+        let zero_cpu = Ratio::new::<cpu>(num_rational::Ratio::new(0, 1));
+        let big = sla(
+            available_ram * num_rational::Ratio::new(3, 5),
+            available_cpu * num_rational::Ratio::new(3, 5),
+        );
+        let acc = AccumulatedLatency::default();
+
+        auction
+            .bid_on(big.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (big.memory, big.cpu));
+
+        let other = sla(big.memory, big.cpu);
+        let refused =
+            auction.bid_on(other.clone(), &acc).await.expect("Bidding failed");
+        assert!(refused.is_none(), "The reservation was not accounted for");
+
+        // Paying turns the reservation into used resources
+        function_life
+            .pay_function(big.id.clone())
+            .await
+            .expect("Paying failed");
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (zero_ram, zero_cpu));
+        let used = resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(used, (big.memory, big.cpu));
+
+        // An unpaid bid gives its resources back when it expires
+        let small = sla(
+            Information::new::<megabyte>(num_rational::Ratio::new(100, 1)),
+            Ratio::new::<millicpu>(num_rational::Ratio::new(100, 1)),
+        );
+        auction
+            .bid_on(small.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (small.memory, small.cpu));
+
+        tokio::time::pause();
+        let expiry = cron
+            .get_deadline(&Task::ExpireProposal(small.id.clone()))
+            .await
+            .expect("The expiry of the proposal is not scheduled");
+        let remaining =
+            expiry - Utc::now() + chrono::Duration::milliseconds(100);
+        tokio::time::sleep(remaining.to_std().unwrap_or_default()).await;
+
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (zero_ram, zero_cpu));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::repository::cron::{Cron, Task, UnprovisionFunction};
+use crate::repository::function_tracking::FunctionTracking;
+use crate::repository::node_situation::NodeSituation;
+use crate::repository::resource_tracking::ResourceTracking;
+use crate::service::neighbor_monitor::NeighborMonitor;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use model::view::admin::{
+    ChildNode, NeighborLatency, NodeResources, PendingTask, TrackedFunctions,
+};
+use std::sync::Arc;
+
+/// Read-only view on the local state of the node, to debug it
+pub struct Admin {
+    function_tracking: Arc<FunctionTracking>,
+    resource_tracking: Arc<ResourceTracking>,
+    neighbor_monitor:  Arc<NeighborMonitor>,
+    node_situation:    Arc<NodeSituation>,
+    cron:              Arc<Cron>,
+}
+
+impl Admin {
+    pub fn new(
+        function_tracking: Arc<FunctionTracking>,
+        resource_tracking: Arc<ResourceTracking>,
+        neighbor_monitor: Arc<NeighborMonitor>,
+        node_situation: Arc<NodeSituation>,
+        cron: Arc<Cron>,
+    ) -> Self {
+        Self {
+            function_tracking,
+            resource_tracking,
+            neighbor_monitor,
+            node_situation,
+            cron,
+        }
+    }
+
+    pub fn get_functions(&self) -> TrackedFunctions {
+        let mut functions = TrackedFunctions::new();
+        for status in self.function_tracking.get_all_status(Utc::now()) {
+            functions.entry(status.state).or_default().push(status);
+        }
+        functions
+    }
+
+    pub async fn get_resources(&self) -> Result<Vec<NodeResources>> {
+        let mut resources = Vec::new();
+        for node in self.resource_tracking.get_nodes() {
+            let (used_memory, used_cpu) =
+                self.resource_tracking.get_used(node).await.with_context(
+                    || format!("Failed to get the used resources of {}", node),
+                )?;
+            let (reserved_memory, reserved_cpu) = self
+                .resource_tracking
+                .get_reserved(node)
+                .await
+                .with_context(|| {
+                    format!("Failed to get the reserved resources of {}", node)
+                })?;
+            let (available_memory, available_cpu) = self
+                .resource_tracking
+                .get_available(node)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to get the available resources of {}",
+                        node
+                    )
+                })?;
+            resources.push(NodeResources {
+                node: node.clone(),
+                used_memory,
+                used_cpu,
+                reserved_memory,
+                reserved_cpu,
+                available_memory,
+                available_cpu,
+            });
+        }
+        Ok(resources)
+    }
+
+    pub async fn get_neighbors(&self) -> Vec<NeighborLatency> {
+        let mut neighbors = Vec::new();
+        for id in self.node_situation.get_neighbors() {
+            let latency = self.neighbor_monitor.get_latency_to(&id).await;
+            neighbors.push(NeighborLatency {
+                id,
+                median: latency.as_ref().map(|x| x.median),
+                average: latency.as_ref().map(|x| x.average),
+                interquantile_range: latency
+                    .as_ref()
+                    .map(|x| x.interquantile_range),
+                packet_loss: latency.as_ref().map(|x| x.packet_loss),
+            });
+        }
+        neighbors
+    }
+
+    pub fn get_children(&self) -> Vec<ChildNode> {
+        self.node_situation
+            .get_children()
+            .into_iter()
+            .map(|(id, description)| ChildNode {
+                id,
+                ip: description.ip,
+                port_http: description.port_http,
+                advertised_bandwidth: description.advertised_bandwidth,
+            })
+            .collect()
+    }
+
+    pub async fn get_pending_tasks(&self) -> Vec<PendingTask> {
+        self.cron
+            .get_pending()
+            .await
+            .into_iter()
+            .map(|(task, deadline)| {
+                let sla = task.sla().clone();
+                let (task, node) = match task {
+                    Task::UnprovisionFunction(UnprovisionFunction {
+                        node,
+                        ..
+                    }) => ("unprovisionFunction", Some(node)),
+                    Task::ExpireProposal(_) => ("expireProposal", None),
+                    Task::ForgetProposal(_) => ("forgetProposal", None),
+                };
+                PendingTask { task: task.to_string(), sla, node, deadline }
+            })
+            .collect()
+    }
+}
@@ -1,61 +1,50 @@
 use crate::monitoring::BidGauge;
+use crate::repository::cron::{Cron, Task};
+use crate::repository::faas::FunctionTimeout;
 use crate::repository::function_tracking::FunctionTracking;
 use crate::repository::node_situation::NodeSituation;
 use crate::repository::resource_tracking::ResourceTracking;
-use anyhow::{bail, Context, Result};
-use chrono::{Timelike, Utc};
-use helper::env_var;
+use crate::service::bid_valuation::{self, BidValuation, ComputedBid};
+use crate::FUNCTION_PAYING_TIMEOUT_MSECS;
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Timelike, Utc};
+use dashmap::DashMap;
+use helper::env_load;
+use helper::http_error::ErrorCode;
 use helper::monitoring::MetricsExporter;
 use helper::uom_helper::cpu_ratio::cpu;
 use model::domain::sla::Sla;
-#[cfg(feature = "reduction")]
-use model::domain::sla::SlaFogPoint;
 use model::dto::function::Proposed;
+use model::dto::node::{BidValuationModifier, PlacementStrategyKind};
 use model::view::auction::AccumulatedLatency;
-use model::BidId;
-use nutype::nutype;
+use model::{BidId, SlaId};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
-use tracing::{instrument, trace};
-use uom::num_traits::ToPrimitive;
+use tracing::{debug, info, instrument, trace};
+use uom::si::f64::Time;
+use uom::si::information::byte;
 use uom::si::rational64::{Information, Ratio};
+use uom::si::time::millisecond;
 use uuid::Uuid;
 
 use super::function::Function;
 
-#[nutype(derive(PartialEq, PartialOrd), validate(finite, greater = 0.0))]
-pub struct PricingRatio(f64);
-env_var!(PRICING_CPU);
-env_var!(PRICING_CPU_INITIAL);
-env_var!(PRICING_MEM);
-env_var!(PRICING_MEM_INITIAL);
-env_var!(PRICING_GEOLOCATION);
-env_var!(RATIO_AA);
-env_var!(RATIO_BB);
-env_var!(ELECTRICITY_PRICE);
-struct ComputedBid {
-    pub(crate) name:          String,
-    #[allow(dead_code)]
-    pub(crate) available_ram: Information,
-    #[allow(dead_code)]
-    pub(crate) available_cpu: Ratio,
-    #[allow(dead_code)]
-    pub(crate) used_ram:      Information,
-    #[allow(dead_code)]
-    pub(crate) used_cpu:      Ratio,
-    pub(crate) bid:           f64,
-}
-
 pub struct Auction {
     resource_tracking:             Arc<ResourceTracking>,
     db:                            Arc<FunctionTracking>,
     metrics:                       Arc<MetricsExporter>,
     #[allow(dead_code)]
     function:                      Arc<Function>,
+    valuation:                     Box<dyn BidValuation>,
     in_flight_functions_per_sec_1: AtomicU32,
     in_flight_functions_per_sec_2: AtomicU32,
-    #[allow(dead_code)]
+    /// Window (0 or 1) that counted the proposal of the sla, until the
+    /// window is reset
+    in_flight_windows:             DashMap<SlaId, usize>,
     node_situation:                Arc<NodeSituation>,
+    cron:                          Arc<Cron>,
+    /// Time given to pay for a proposal before it expires
+    paying_timeout:                Time,
 }
 
 impl Auction {
@@ -65,46 +54,200 @@ impl Auction {
         metrics: Arc<MetricsExporter>,
         function: Arc<Function>,
         node_situation: Arc<NodeSituation>,
+        cron: Arc<Cron>,
     ) -> Result<Self> {
+        let paying_timeout =
+            env_load!(FunctionTimeout, FUNCTION_PAYING_TIMEOUT_MSECS, u64);
+        let paying_timeout =
+            Time::new::<millisecond>(paying_timeout.into_inner() as f64);
+
+        let mut valuation_config = node_situation.get_bid_valuation();
+        if matches!(
+            node_situation.get_placement_strategy(),
+            PlacementStrategyKind::MaxCpu
+                | PlacementStrategyKind::MinCpuRandom
+        ) && !valuation_config
+            .modifiers
+            .contains(&BidValuationModifier::CpuUtilisation)
+        {
+            // Those placements compare the nodes on their cpu utilisation
+            valuation_config
+                .modifiers
+                .insert(0, BidValuationModifier::CpuUtilisation);
+        }
+        info!("Using {} bid valuation", valuation_config);
+        let valuation = bid_valuation::from_config(
+            &valuation_config,
+            function.clone(),
+            resource_tracking.clone(),
+            node_situation.clone(),
+        )
+        .context("Failed to set up the bid valuation")?;
+
         Ok(Self {
             resource_tracking,
             db,
             metrics,
             function,
+            valuation,
             in_flight_functions_per_sec_1: AtomicU32::new(0),
             in_flight_functions_per_sec_2: AtomicU32::new(0),
+            in_flight_windows: DashMap::new(),
             node_situation,
+            cron,
+            paying_timeout,
         })
     }
 
-    /// Get a suitable (free enough) node to potentially run the designated SLA
-    #[instrument(level = "trace", skip(self, sla))]
-    async fn get_a_node(
+    /// Expire the proposal if it is not paid in time. The expired proposal
+    /// is forgotten after another timeout, late payments in between are
+    /// told that the bid expired.
+    async fn schedule_expiry(
         &self,
-        sla: &Sla,
-    ) -> Result<Option<(String, Information, Ratio, Information, Ratio)>> {
-        for node in self.resource_tracking.get_nodes() {
-            let (used_ram, used_cpu) =
-                self.resource_tracking.get_used(node).await.with_context(
-                    || {
-                        format!(
-                            "Failed to get used resources from tracking data \
-                             for node {}",
-                            node
-                        )
-                    },
-                )?;
-            let (available_ram, available_cpu) = self
-                .resource_tracking
-                .get_available(node)
+        id: SlaId,
+        proposed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let db = self.db.clone();
+        let resource_tracking = self.resource_tracking.clone();
+        let expired = id.clone();
+        self.cron
+            .add_oneshot(
+                proposed_at,
+                self.paying_timeout,
+                Task::ExpireProposal(id.clone()),
+                move || {
+                    let db = db.clone();
+                    let resource_tracking = resource_tracking.clone();
+                    let id = expired.clone();
+                    Box::pin(async move {
+                        trace!("Expiring proposal for sla {}", id);
+                        db.expire_proposed(&id);
+                        resource_tracking.release_reservation(&id);
+                    })
+                },
+            )
+            .await
+            .context("Failed to schedule the expiry of the proposal")?;
+
+        let db = self.db.clone();
+        let forgotten = id.clone();
+        self.cron
+            .add_oneshot(
+                proposed_at,
+                self.paying_timeout * 2.0,
+                Task::ForgetProposal(id),
+                move || {
+                    let db = db.clone();
+                    let id = forgotten.clone();
+                    Box::pin(async move {
+                        db.forget_expired(&id);
+                    })
+                },
+            )
+            .await
+            .context("Failed to schedule forgetting the expired proposal")
+    }
+
+    /// Hold the resources of the proposals loaded from the disk again and
+    /// expire them on time, the restart lost both
+    pub async fn recover_proposals(&self) -> Result<()> {
+        for proposed in self.db.get_recoverable_proposals() {
+            let id = proposed.sla.id.clone();
+            info!("Recovered proposal for sla {}", id);
+            self.resource_tracking
+                .reserve(
+                    id.clone(),
+                    proposed.node,
+                    proposed.sla.memory_per_node(),
+                    proposed.sla.cpu_per_node(),
+                )
                 .await
                 .with_context(|| {
+                    format!("Failed to reserve resources for proposal {}", id)
+                })?;
+            self.schedule_expiry(id, proposed.proposed_at).await?;
+        }
+        Ok(())
+    }
+
+    /// Resources of the node: the committed ones, that is the used ones and
+    /// the ones reserved for pending bids, and the available ones
+    #[allow(clippy::type_complexity)]
+    async fn get_node_resources(
+        &self,
+        node: &str,
+    ) -> Result<(Information, Ratio, Information, Ratio)> {
+        let (used_ram, used_cpu) =
+            self.resource_tracking.get_used(node).await.with_context(
+                || {
+                    format!(
+                        "Failed to get used resources from tracking data for \
+                         node {}",
+                        node
+                    )
+                },
+            )?;
+        let (reserved_ram, reserved_cpu) =
+            self.resource_tracking.get_reserved(node).await.with_context(
+                || {
+                    format!(
+                        "Failed to get reserved resources from tracking data \
+                         for node {}",
+                        node
+                    )
+                },
+            )?;
+        let (available_ram, available_cpu) =
+            self.resource_tracking.get_available(node).await.with_context(
+                || {
                     format!(
                         "Failed to get available resources from tracking \
                          data for node {}",
                         node
                     )
-                })?;
+                },
+            )?;
+        Ok((
+            used_ram + reserved_ram,
+            used_cpu + reserved_cpu,
+            available_ram,
+            available_cpu,
+        ))
+    }
+
+    /// Largest free memory and largest free cpu left on any of the k8s
+    /// nodes, the resources reserved for pending bids count as used
+    pub async fn get_max_free_resources(
+        &self,
+    ) -> Result<(Information, Ratio)> {
+        let mut max_free_ram =
+            Information::new::<byte>(num_rational::Ratio::new(0, 1));
+        let mut max_free_cpu =
+            Ratio::new::<cpu>(num_rational::Ratio::new(0, 1));
+        for node in self.resource_tracking.get_nodes() {
+            let (used_ram, used_cpu, available_ram, available_cpu) =
+                self.get_node_resources(node).await?;
+            if available_ram - used_ram > max_free_ram {
+                max_free_ram = available_ram - used_ram;
+            }
+            if available_cpu - used_cpu > max_free_cpu {
+                max_free_cpu = available_cpu - used_cpu;
+            }
+        }
+        Ok((max_free_ram, max_free_cpu))
+    }
+
+    /// Get a suitable (free enough) node to potentially run the designated
+    /// SLA. The resources reserved for pending bids count as used.
+    #[allow(clippy::type_complexity)]
+    #[instrument(level = "trace", skip(self, sla))]
+    async fn get_a_node(
+        &self,
+        sla: &Sla,
+    ) -> Result<Option<(String, Information, Ratio, Information, Ratio)>> {
+        for node in self.resource_tracking.get_nodes() {
+            let (used_ram, used_cpu, available_ram, available_cpu) =
+                self.get_node_resources(node).await?;
             if super::function::satisfiability_check(
                 &used_ram,
                 &used_cpu,
@@ -124,211 +267,116 @@ impl Auction {
         Ok(None)
     }
 
-    #[cfg(feature = "linear_rates")]
-    #[instrument(level = "trace", skip(self, sla, _accumulated_latency))]
-    async fn compute_bid(
+    /// Price keeping the function of the sla running on the node for the
+    /// duration of the extension, the function must still fit on the node
+    #[instrument(level = "trace", skip(self, extension))]
+    pub async fn price_extension(
         &self,
-        sla: &Sla,
-        _accumulated_latency: &AccumulatedLatency,
-    ) -> Result<Option<ComputedBid>> {
-        use helper::env_load;
-
-        // let pricing_cpu =
-        //     env_load!(PricingRatio, PRICING_CPU, f64).into_inner();
-        let pricing_cpu_initial =
-            env_load!(PricingRatio, PRICING_CPU_INITIAL, f64).into_inner();
-        // let pricing_mem =
-        //     env_load!(PricingRatio, PRICING_MEM, f64).into_inner();
-        let pricing_mem_initial =
-            env_load!(PricingRatio, PRICING_MEM_INITIAL, f64).into_inner();
-
-        let Some((name, used_ram, used_cpu, available_ram, available_cpu)) =
-            self.get_a_node(sla)
-                .await
-                .context("Failed to found a suitable node for the sla")?
-        else {
-            return Ok(None);
-        };
-
-        let ram_ratio_sla = (sla.memory / available_ram)
-            .get::<cpu>()
-            .to_f64()
-            .context("Overflow while converting ratio of memory")?;
-        let cpu_ratio_sla = (sla.cpu / available_cpu)
-            .get::<cpu>()
-            .to_f64()
-            .context("Overflow while converting ratio of cpu")?;
-        let bid: f64 = ram_ratio_sla * pricing_mem_initial
-            + cpu_ratio_sla * pricing_cpu_initial;
-
-        trace!("price on {:?} is {:?}", name, bid);
+        extension: &Sla,
+        node: &str,
+    ) -> Result<ComputedBid> {
+        let _reservations = self.resource_tracking.lock_reservations().await;
+        let (used_ram, used_cpu, available_ram, available_cpu) =
+            self.get_node_resources(node).await?;
+        ensure!(
+            super::function::satisfiability_check(
+                &(used_ram - extension.memory_per_node()),
+                &(used_cpu - extension.cpu_per_node()),
+                &available_ram,
+                &available_cpu,
+                extension,
+            ),
+            ErrorCode::Unsatisfiable.error(format!(
+                "Node {} cannot keep the function of sla {} any longer",
+                node, extension.id
+            ))
+        );
 
-        Ok(Some(ComputedBid {
-            name,
-            bid,
-            available_cpu,
+        let computed = ComputedBid {
+            name: node.to_string(),
             available_ram,
-            used_cpu,
+            available_cpu,
             used_ram,
-        }))
+            used_cpu,
+            bid: 0.0,
+            price: 0.0,
+        };
+        self.valuation
+            .valuate(extension, &AccumulatedLatency::default(), computed)
+            .await
+            .context("Failed to valuate the extension")
     }
 
-    #[cfg(feature = "quadratic_rates")]
-    #[instrument(level = "trace", skip(self, sla, _accumulated_latency))]
-    async fn compute_bid(
+    /// Find a node free enough for the sla and reserve the resources of its
+    /// replicas there, so that concurrent bids cannot claim them too
+    #[allow(clippy::type_complexity)]
+    #[instrument(level = "trace", skip(self, sla))]
+    async fn reserve_a_node(
         &self,
         sla: &Sla,
-        _accumulated_latency: &AccumulatedLatency,
-    ) -> Result<Option<ComputedBid>> {
-        use crate::service::function::UnprovisionEvent;
-        use chrono::Duration;
-        use helper::env_load;
-        use uom::si::time::second;
-
-        let Some((name, used_ram, used_cpu, available_ram, available_cpu)) =
-            self.get_a_node(sla)
-                .await
-                .context("Failed to found a suitable node for the sla")?
+    ) -> Result<Option<(String, Information, Ratio, Information, Ratio)>> {
+        let _reservations = self.resource_tracking.lock_reservations().await;
+        let Some(found) = self
+            .get_a_node(sla)
+            .await
+            .context("Failed to found a suitable node for the sla")?
         else {
             return Ok(None);
         };
-
-        let aa = env_load!(PricingRatio, RATIO_AA, f64).into_inner();
-        let bb = env_load!(PricingRatio, RATIO_BB, f64).into_inner();
-        let now = Utc::now();
-        let mut utilisation = 0.0;
-        for UnprovisionEvent { timestamp, sla, node, .. } in
-            self.function.get_utilisation_variations().await.iter()
-        {
-            let duration = if *timestamp > now {
-                let duration = *timestamp - now;
-                tracing::trace!(
-                    "timestamp: {:?}, now: {:?}, duration: {:?}",
-                    *timestamp,
-                    now,
-                    duration
-                );
-                duration
-            } else {
-                Duration::microseconds(0)
-            };
-            let (_available_ram, available_cpu) = self
-                .resource_tracking
-                .get_available(node)
-                .await
-                .with_context(|| {
-                    format!(
-                        "Failed to get available resources from tracking \
-                         data for node {}",
-                        node
-                    )
-                })?;
-
-            let duration = duration.num_seconds() as f64;
-            utilisation += (sla.cpu / available_cpu)
-                .get::<cpu>()
-                .to_f64()
-                .context("Overlfow while converting to f64")?
-                * duration;
-        }
-        let sla_cpu = (sla.cpu / available_cpu)
-            .get::<cpu>()
-            .to_f64()
-            .context("Overflow during conversion to f64")?;
-        let sla_duration = sla.duration.get::<second>();
-        let electricity_price =
-            env_load!(PricingRatio, ELECTRICITY_PRICE, f64).into_inner();
-        let bid = electricity_price
-            * sla_cpu
-            * (2.0 * aa * utilisation + (aa * sla_cpu + bb) * sla_duration);
-
-        tracing::event!(
-            tracing::Level::TRACE,
-            bid,
-            sla_cpu,
-            sla_duration,
-            electricity_price,
-            utilisation,
-            aa,
-            bb
-        );
-
-        trace!("(quadratic) price on is {:?}", bid);
-        assert!(bid > 0.000001, "the bid wasn't > 0");
-
-        Ok(Some(ComputedBid {
-            name,
-            bid,
-            available_cpu,
-            available_ram,
-            used_cpu,
-            used_ram,
-        }))
+        self.resource_tracking
+            .reserve(
+                sla.id.clone(),
+                found.0.clone(),
+                sla.memory_per_node(),
+                sla.cpu_per_node(),
+            )
+            .await
+            .context("Failed to reserve resources for the bid")?;
+        Ok(Some(found))
     }
 
-    #[cfg(any(feature = "maxcpu", feature = "mincpurandom"))]
     #[instrument(level = "trace", skip(self, sla))]
-    async fn compute_bid_cpu(
+    async fn compute_bid(
         &self,
         sla: &Sla,
         accumulated_latency: &AccumulatedLatency,
-    ) -> Result<Option<(String, f64, f64)>> {
-        use uom::si::ratio::basis_point;
-
-        match self.compute_bid(sla, accumulated_latency).await? {
-            Some(computed) => {
-                // The more the cpu is used the lower the price and the easiest
-                // to win
-                let cpu_ratio_sla = computed.used_cpu / computed.available_cpu;
-                let bid = cpu_ratio_sla
-                    .get::<basis_point>()
-                    .to_f64()
-                    .context("Overflow during bid conversion to f64")?;
-                // The normal "bid" is also the price usually, but not in that
-                // valuation method
-                let price = computed.bid;
-
-                trace!(
-                    "(maxcpu|mincpu) price on {:?} is {:?}, and bid is {:?}",
-                    computed.name,
-                    price,
-                    bid
-                );
-                Ok(Some((computed.name, bid, price)))
-            }
-            None => Ok(None),
-        }
-    }
-
-    #[cfg(feature = "reduction")]
-    fn reduce_bid_if_on_device(&self, sla: &Sla, bid: f64) -> Option<f64> {
-        for flow in &sla.data_flow {
-            let res = self.reduce_bid_if_on_device_inside(&flow.from, bid);
-            if res.is_some() {
-                return res;
-            }
-            let res = self.reduce_bid_if_on_device_inside(&flow.to, bid);
-            if res.is_some() {
-                return res;
-            }
+        (name, used_ram, used_cpu, available_ram, available_cpu): (
+            String,
+            Information,
+            Ratio,
+            Information,
+            Ratio,
+        ),
+    ) -> Result<Option<ComputedBid>> {
+        let computed = ComputedBid {
+            name,
+            available_ram,
+            available_cpu,
+            used_ram,
+            used_cpu,
+            bid: 0.0,
+            price: 0.0,
+        };
+        let computed = self
+            .valuation
+            .valuate(sla, accumulated_latency, computed)
+            .await
+            .context("Failed to valuate the sla")?;
+        // The price, not the bid, is what the client pays for
+        if let Some(max_price) =
+            sla.max_price.filter(|max_price| computed.price > *max_price)
+        {
+            debug!(
+                "Not bidding {} on {}, above its maximum price of {}",
+                computed.price, sla.id, max_price
+            );
+            return Ok(None);
         }
-        None
-    }
 
-    #[cfg(feature = "reduction")]
-    fn reduce_bid_if_on_device_inside(
-        &self,
-        point: &SlaFogPoint,
-        bid: f64,
-    ) -> Option<f64> {
-        let reduction_factor = 0.75;
-        let me = &self.node_situation.get_my_id();
-        match point {
-            SlaFogPoint::DataSource(source) if source == me => {
-                Some(bid * reduction_factor)
-            }
-            _ => None,
+        if self.check_in_flight(&sla.id).await.is_err() {
+            return Ok(None);
         }
+        Ok(Some(computed))
     }
 
     #[instrument(level = "trace", skip(self))]
@@ -337,41 +385,37 @@ impl Auction {
         sla: Sla,
         accumulated_latency: &AccumulatedLatency,
     ) -> Result<Option<(BidId, Proposed)>> {
-        #[cfg(not(any(feature = "maxcpu", feature = "mincpurandom")))]
-        let Some(ComputedBid { name, bid, .. }) = self
-            .compute_bid(&sla, accumulated_latency)
-            .await
-            .context("Failed to compute bid for sla")?
-        else {
+        let Some(found) = self.reserve_a_node(&sla).await? else {
             return Ok(None);
         };
-
-        if self.check_in_flight().await.is_err() {
-            return Ok(None);
-        }
-
-        #[cfg(not(any(feature = "maxcpu", feature = "mincpurandom")))]
-        let price = bid;
-
-        #[cfg(any(feature = "maxcpu", feature = "mincpurandom"))]
-        let Some((name, bid, price)) = self
-            .compute_bid_cpu(&sla, accumulated_latency)
+        let computed = self
+            .compute_bid(&sla, accumulated_latency, found)
             .await
-            .context("Failed to compute bid for sla")?
-        else {
-            return Ok(None);
+            .context("Failed to compute bid for sla");
+        let Ok(Some(ComputedBid { name, bid, price, .. })) = computed else {
+            // Not bidding after all, give the resources back
+            self.resource_tracking.release_reservation(&sla.id);
+            return computed.map(|_| None);
         };
 
-        #[cfg(feature = "reduction")]
-        let bid: f64 =
-            if let Some(reduced) = self.reduce_bid_if_on_device(&sla, bid) {
-                reduced
-            } else {
-                bid
-            };
         let node = name;
         let record = Proposed::new(bid, sla, node);
-        self.db.insert(record.clone());
+        let tracked = async {
+            self.db
+                .insert(record.clone())
+                .context("Failed to save the proposal")?;
+            self.schedule_expiry(record.sla.id.clone(), record.proposed_at)
+                .await
+        }
+        .await;
+        if let Err(err) = tracked {
+            // Nothing would ever expire the proposal, give everything back
+            self.db.remove_proposed(&record.sla.id);
+            self.resource_tracking.release_reservation(&record.sla.id);
+            self.cron.cancel_sla(&record.sla.id).await;
+            self.release_in_flight(&record.sla.id);
+            return Err(err);
+        }
         let id = Uuid::new_v4();
         let id = BidId::from(id);
 
@@ -391,9 +435,52 @@ impl Auction {
         Ok(Some((id, record)))
     }
 
+    /// The market chose another bid: forget the proposal and give back what
+    /// it held
+    #[instrument(level = "trace", skip(self))]
+    pub async fn reject(&self, id: &SlaId) -> Result<()> {
+        let Some(_) = self.db.remove_proposed(id) else {
+            return Err(ErrorCode::NotFound.error(format!(
+                "No pending proposal for sla {} to reject",
+                id
+            )));
+        };
+        self.resource_tracking.release_reservation(id);
+        self.cron.cancel_sla(id).await;
+        self.release_in_flight(id);
+        Ok(())
+    }
+
+    /// Decrease the counter of the window that counted the proposal of the
+    /// sla, if it was not reset since
+    fn release_in_flight(&self, id: &SlaId) {
+        let Some((_, window)) = self.in_flight_windows.remove(id) else {
+            return;
+        };
+        let counter = if window == 0 {
+            &self.in_flight_functions_per_sec_1
+        } else {
+            &self.in_flight_functions_per_sec_2
+        };
+        let _ =
+            counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+                x.checked_sub(1)
+            });
+    }
+
     /// Allow and increase the counter or refuses
-    async fn check_in_flight(&self) -> Result<()> {
+    async fn check_in_flight(&self, id: &SlaId) -> Result<()> {
         let current_second = (Utc::now().second() % 2) as usize; // 0 or 1
+        self.check_in_flight_at(id, current_second)
+    }
+
+    /// Count the proposal of the sla in the window `current_second` (0 or 1),
+    /// resetting the other one
+    fn check_in_flight_at(
+        &self,
+        id: &SlaId,
+        current_second: usize,
+    ) -> Result<()> {
         let inc;
         let err;
 
@@ -420,6 +507,8 @@ impl Auction {
         if err.is_err() {
             bail!("Failed to update atomic");
         }
+        // The other window was reset, its proposals no longer count
+        self.in_flight_windows.retain(|_, window| *window == current_second);
 
         let size = self
             .node_situation
@@ -430,6 +519,7 @@ impl Auction {
             bail!("Too many in flight functions I have bidded upon")
         }
 
+        self.in_flight_windows.insert(id.clone(), current_second);
         Ok(())
     }
 }
@@ -438,26 +528,26 @@ impl Auction {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repository::cron::Cron;
-    use crate::repository::faas::{FaaSBackend, FaaSBackendOfflineImpl};
-    use crate::repository::k8s::{K8s, OFFLINE_NODE_K8S};
-    use crate::repository::latency_estimation::{
-        LatencyEstimation, LatencyEstimationOfflineImpl,
+    use crate::repository::k8s::OFFLINE_NODE_K8S;
+    use crate::service::fixtures::{
+        get_auction_impl, get_auction_impl_with, market_connected,
+        node_connected, sla, Instance,
     };
-    use crate::repository::node_query::NodeQuery;
-    use crate::repository::node_situation::NodeSituation;
     use crate::service::function_life::FunctionLife;
-    use crate::service::neighbor_monitor::NeighborMonitor;
-    use helper::monitoring::InfluxAddress;
-    use helper::uom_helper::cpu_ratio::{cpu, millicpu};
-    use model::dto::node::{MaxInFlight, NodeCategory, NodeSituationData};
-    use model::SlaId;
+    use helper::uom_helper::cpu_ratio::millicpu;
+    use model::dto::node::{
+        BidValuationConfig, BidValuationKind, NodeCategory, NodeDescription,
+    };
+    use model::view::auction::BidRequestOwned;
+    use model::view::node::CapacitySummary;
+    use model::{NodeId, SlaId};
     use rand::rngs::StdRng;
     use rand::SeedableRng;
     use rand_distr::{Distribution, Uniform};
     use std::net::Ipv4Addr;
     use std::sync::atomic::AtomicU64;
     use std::time::Duration;
+    use uom::num_traits::ToPrimitive;
     use uom::si::f64::Time;
     use uom::si::information::{gigabyte, megabyte};
     use uom::si::information_rate::gigabit_per_second;
@@ -485,178 +575,28 @@ mod tests {
         }
     }
 
-    struct Instance {
-        pub auction:           Arc<Auction>,
-        pub function_life:     Arc<FunctionLife>,
-        pub function_tracking: Arc<FunctionTracking>,
-        pub resource_tracking: Arc<ResourceTracking>,
-        pub reserved_cpu:      Ratio,
-        pub reserved_memory:   Information,
-        pub function:          Arc<Function>,
-        pub node_situation:    Arc<NodeSituation>,
-    }
-
-    fn market_connected() -> NodeCategory {
-        NodeCategory::MarketConnected {
-            market_ip:   std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-            market_port: 1234.into(),
-        }
-    }
-
-    fn node_connected() -> NodeCategory {
-        NodeCategory::NodeConnected {
-            parent_latency: Time::new::<second>(15.0),
-            parent_id:      Uuid::new_v4().into(),
-            parent_node_ip: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-
-            parent_node_port_http: 1234.into(),
-        }
-    }
-
-    async fn get_auction_impl(situation: NodeCategory) -> Instance {
-        let _ = crate::init_subscriber("test".into(), "trace".into());
-        let k8s = Arc::new(K8s::new());
-        let metrics = Arc::new(
-            MetricsExporter::new(
-                InfluxAddress::try_new("127.0.0.1:1234").unwrap(),
-                helper::monitoring::InfluxOrg::try_new("toto").unwrap(),
-                helper::monitoring::InfluxToken::try_new("xowyTh1iGcNAZsZeydESOHKvENvcyPaWg8hUe3tO4vPOw_buZVwOdUrqG3gwV314aYd9SWKHcxlykcQY_rwYVQ==").unwrap(),
-                helper::monitoring::InfluxBucket::try_new("toto").unwrap(),
-                helper::monitoring::InstanceName::try_new("toto").unwrap(),
-            )
-            .await
-            .unwrap(),
-        );
-        let reserved_cpu = Ratio::new::<cpu>(num_rational::Ratio::new(20, 1));
-        let reserved_memory =
-            Information::new::<gigabyte>(num_rational::Ratio::new(8, 1));
-        let resource_tracking = Arc::new(
-            ResourceTracking::new(
-                k8s,
-                metrics.clone(),
-                reserved_cpu,
-                reserved_memory,
-            )
-            .await
-            .unwrap(),
-        );
-        let backend: Arc<Box<dyn FaaSBackend>> = Arc::new(Box::new(
-            FaaSBackendOfflineImpl::new(Duration::from_secs(2)),
-        ));
-        let node_situation = Arc::new(NodeSituation::new(NodeSituationData {
-            situation,
-            my_id: Uuid::new_v4().into(),
-            my_public_ip: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-            my_public_port_http: 12345.into(),
-            my_public_port_faas: 1234.into(),
-            tags: vec!["toto".to_string()],
-            reserved_memory,
-            reserved_cpu,
-            max_in_flight_functions_proposals: MaxInFlight::try_new(160)
-                .unwrap(),
-            my_advertised_bandwidth: InformationRate::new::<gigabit_per_second>(
-                num_rational::Ratio::new(1, 1),
-            ),
-            children: dashmap::DashMap::new(),
-        }));
-        let latency_estimation_repo: Arc<Box<dyn LatencyEstimation>> =
-            Arc::new(Box::new(LatencyEstimationOfflineImpl::new(
-                node_situation.clone(),
-            )));
-
-        let neighbor_monitor =
-            Arc::new(NeighborMonitor::new(latency_estimation_repo));
-
-        let client_builder = reqwest_middleware::ClientBuilder::new(
-            reqwest::Client::builder().build().unwrap(),
-        );
-        let http_client = Arc::new(client_builder.build());
-
-        let node_query = Arc::new(NodeQuery::new(
-            node_situation.clone(),
-            http_client.clone(),
-        ));
-
-        let function_tracking = Arc::new(FunctionTracking::default());
-        let cron = Arc::new(
-            Cron::new(uom::si::f64::Time::new::<uom::si::time::second>(15.0))
-                .await
-                .expect("Failed to start Cron repository"),
-        );
-
-        let function = Arc::new(Function::new(
-            backend.clone(),
-            node_situation.clone(),
-            neighbor_monitor.clone(),
-            node_query.clone(),
-            resource_tracking.clone(),
-            function_tracking.clone(),
-            metrics.clone(),
-            cron.clone(),
-        ));
-        let auction = Arc::new(
-            Auction::new(
-                resource_tracking.clone(),
-                function_tracking.clone(),
-                metrics,
-                function.clone(),
-                node_situation.clone(),
-            )
-            .unwrap(),
-        );
-
-        let function_life = Arc::new(
-            FunctionLife::new(
-                function.clone(),
-                auction.clone(),
-                node_situation.clone(),
-                neighbor_monitor,
-                node_query,
-                function_tracking.clone(),
-                cron,
-            )
-            .unwrap(),
-        );
-        Instance {
-            auction,
-            function_life,
-            function_tracking,
-            resource_tracking,
-            reserved_cpu,
-            reserved_memory,
-            function,
-            node_situation,
-        }
-    }
-
-    #[cfg(feature = "reduction")]
     #[tokio::test()]
     async fn test_reduction() {
         use model::domain::sla::{DataFlow, SlaFogPoint};
 
-        let Instance { auction, node_situation, .. } =
-            get_auction_impl(node_connected()).await;
+        let Instance { auction, node_situation, .. } = get_auction_impl_with(
+            node_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig {
+                base:      BidValuationKind::QuadraticRates,
+                modifiers: vec![BidValuationModifier::Reduction],
+            },
+        )
+        .await;
         let sla = Sla {
-            id:                 Uuid::new_v4().into(),
-            memory:             Information::new::<megabyte>(
-                num_rational::Ratio::new(1000, 1),
-            ),
-            cpu:                Ratio::new::<millicpu>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            latency_max:        Time::new::<second>(1.0),
-            duration:           Time::new::<second>(5.0),
-            replicas:           1,
-            function_image:     "toto".to_string(),
-            function_live_name: "toto".to_string(),
-            data_flow:          vec![DataFlow {
+            memory: Information::new::<megabyte>(num_rational::Ratio::new(
+                1000, 1,
+            )),
+            data_flow: vec![DataFlow {
                 from: SlaFogPoint::DataSource(node_situation.get_my_id()),
                 to:   SlaFogPoint::ThisFunction,
             }],
-            env_vars:           vec![],
-            input_max_size:     Information::new::<megabyte>(
-                num_rational::Ratio::new(1, 1),
-            ),
+            ..sla()
         };
         let mut sla2 = sla.clone();
         sla2.data_flow = vec![];
@@ -671,6 +611,42 @@ mod tests {
         assert!(bid <= bid2);
     }
 
+    #[parameterized(
+        linear = { BidValuationKind::LinearRates, vec![] },
+        quadratic = { BidValuationKind::QuadraticRates, vec![] },
+        cpu_utilisation = {
+            BidValuationKind::QuadraticRates,
+            vec![BidValuationModifier::CpuUtilisation]
+        },
+        cpu_utilisation_reduction = {
+            BidValuationKind::LinearRates,
+            vec![
+                BidValuationModifier::CpuUtilisation,
+                BidValuationModifier::Reduction
+            ]
+        },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_bid_valuation(
+        base: BidValuationKind,
+        modifiers: Vec<BidValuationModifier>,
+    ) {
+        let Instance { auction, .. } = get_auction_impl_with(
+            market_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig { base, modifiers },
+        )
+        .await;
+        let sla = sla();
+
+        let (_, Proposed { bid, .. }) = auction
+            .bid_on(sla, &AccumulatedLatency::default())
+            .await
+            .expect("Error bidding")
+            .expect("The sla should be accepted");
+        assert!(bid >= 0.0);
+    }
+
     #[parameterized(
         node = { node_connected(), },
         market = { market_connected(), },
@@ -679,23 +655,10 @@ mod tests {
     async fn test_sla_refusal(situation: NodeCategory) {
         let auction = get_auction_impl(situation).await.auction;
         let sla = Sla {
-            id:                 Uuid::new_v4().into(),
-            memory:             Information::new::<gigabyte>(
-                num_rational::Ratio::new(1000, 1),
-            ),
-            cpu:                Ratio::new::<millicpu>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            latency_max:        Time::new::<second>(1.0),
-            duration:           Time::new::<second>(5.0),
-            replicas:           1,
-            function_image:     "toto".to_string(),
-            function_live_name: "toto".to_string(),
-            data_flow:          vec![],
-            env_vars:           vec![],
-            input_max_size:     Information::new::<megabyte>(
-                num_rational::Ratio::new(1, 1),
-            ),
+            memory: Information::new::<gigabyte>(num_rational::Ratio::new(
+                1000, 1,
+            )),
+            ..sla()
         };
 
         let acc = AccumulatedLatency::default();
@@ -704,6 +667,165 @@ mod tests {
         assert!(res.is_none());
     }
 
+    #[tokio::test]
+    async fn test_bid_above_max_price() {
+        let auction = get_auction_impl(market_connected()).await.auction;
+        let sla = |max_price| Sla { max_price, ..sla() };
+        let acc = AccumulatedLatency::default();
+
+        let (_, Proposed { bid, .. }) = auction
+            .bid_on(sla(None), &acc)
+            .await
+            .expect("Error bidding")
+            .expect("The sla should be accepted");
+
+        let too_cheap = sla(Some(bid / 2.0));
+        let res = auction
+            .bid_on(too_cheap.clone(), &acc)
+            .await
+            .expect("Error bidding");
+        assert!(res.is_none());
+        assert!(
+            auction.reject(&too_cheap.id).await.is_err(),
+            "No proposal should have been recorded"
+        );
+
+        let res = auction
+            .bid_on(sla(Some(bid * 2.0)), &acc)
+            .await
+            .expect("Error bidding");
+        assert!(res.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_max_price_with_bids_not_prices() {
+        let auction = get_auction_impl_with(
+            market_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig {
+                base:      BidValuationKind::LinearRates,
+                modifiers: vec![BidValuationModifier::CpuUtilisation],
+            },
+        )
+        .await
+        .auction;
+        let sla = |max_price| Sla { max_price, ..sla() };
+        let acc = AccumulatedLatency::default();
+
+        let res = auction
+            .bid_on(sla(Some(f64::MAX)), &acc)
+            .await
+            .expect("Error bidding");
+        assert!(res.is_some(), "The price is within the budget");
+
+        let res = auction
+            .bid_on(sla(Some(f64::MIN_POSITIVE)), &acc)
+            .await
+            .expect("Error bidding");
+        assert!(res.is_none(), "The price is above the budget");
+    }
+
+    #[tokio::test]
+    async fn test_spread_replicas() {
+        let auction = get_auction_impl(market_connected()).await.auction;
+        let sla = |spread_replicas| Sla {
+            replicas: 100_000,
+            spread_replicas,
+            ..sla()
+        };
+        let acc = AccumulatedLatency::default();
+
+        let res =
+            auction.bid_on(sla(false), &acc).await.expect("Error bidding");
+        assert!(res.is_none(), "All the replicas cannot fit on the node");
+
+        let res =
+            auction.bid_on(sla(true), &acc).await.expect("Error bidding");
+        assert!(res.is_some(), "The node only hosts one of the replicas");
+    }
+
+    #[parameterized(
+        auction = { PlacementStrategyKind::Auction },
+        edge_first = { PlacementStrategyKind::EdgeFirst },
+        edge_furthest = { PlacementStrategyKind::EdgeFurthest },
+        edge_ward = { PlacementStrategyKind::EdgeWard },
+        maxcpu = { PlacementStrategyKind::MaxCpu },
+        mincpurandom = { PlacementStrategyKind::MinCpuRandom },
+        cloud_only = { PlacementStrategyKind::CloudOnly },
+        cloud_only_v2 = { PlacementStrategyKind::CloudOnlyV2 },
+        edge_ward_v2 = { PlacementStrategyKind::EdgeWardV2 },
+        edge_ward_v3 = { PlacementStrategyKind::EdgeWardV3 },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_placement_lone_node(placement: PlacementStrategyKind) {
+        let Instance { function_life, node_situation, .. } =
+            get_auction_impl_with(
+                market_connected(),
+                placement,
+                BidValuationConfig::default(),
+            )
+            .await;
+        let sla = sla();
+
+        let proposals = function_life
+            .bid_on_new_function_and_transmit(&BidRequestOwned {
+                node_origin: node_situation.get_my_id(),
+                sla,
+                accumulated_latency: AccumulatedLatency::default(),
+                nb_propositions_required: 1,
+                request_id: Uuid::new_v4().into(),
+                hop_budget: 8,
+                visited: vec![],
+            })
+            .await
+            .expect("Placement failed");
+
+        assert_eq!(proposals.bids.len(), 1);
+        assert_eq!(proposals.bids[0].node_id, node_situation.get_my_id());
+    }
+
+    /// The parent is unreachable, only the placements bidding on the node
+    /// itself before asking the parent can succeed
+    #[parameterized(
+        cloud_only = { PlacementStrategyKind::CloudOnly, false },
+        edge_ward_v2 = { PlacementStrategyKind::EdgeWardV2, true },
+        edge_ward_v3 = { PlacementStrategyKind::EdgeWardV3, true },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_placement_with_parent(
+        placement: PlacementStrategyKind,
+        bids_on_itself: bool,
+    ) {
+        let Instance { function_life, node_situation, .. } =
+            get_auction_impl_with(
+                node_connected(),
+                placement,
+                BidValuationConfig::default(),
+            )
+            .await;
+        let sla = sla();
+
+        let proposals = function_life
+            .bid_on_new_function_and_transmit(&BidRequestOwned {
+                node_origin: node_situation.get_my_id(),
+                sla,
+                accumulated_latency: AccumulatedLatency::default(),
+                nb_propositions_required: 1,
+                request_id: Uuid::new_v4().into(),
+                hop_budget: 8,
+                visited: vec![],
+            })
+            .await;
+
+        if bids_on_itself {
+            let proposals = proposals.expect("Placement failed");
+            assert_eq!(proposals.bids.len(), 1);
+            assert_eq!(proposals.bids[0].node_id, node_situation.get_my_id());
+        } else {
+            assert!(proposals.is_err());
+        }
+    }
+
     #[parameterized(
         node = {node_connected()},
         market = {market_connected()}
@@ -718,25 +840,7 @@ mod tests {
             ..
         } = get_auction_impl(situation).await;
 
-        let sla = Sla {
-            id:                 Uuid::new_v4().into(),
-            memory:             Information::new::<megabyte>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            cpu:                Ratio::new::<millicpu>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            latency_max:        Time::new::<second>(1.0),
-            duration:           Time::new::<second>(5.0),
-            replicas:           1,
-            function_image:     "toto".to_string(),
-            function_live_name: "toto".to_string(),
-            data_flow:          vec![],
-            env_vars:           vec![],
-            input_max_size:     Information::new::<megabyte>(
-                num_rational::Ratio::new(1, 1),
-            ),
-        };
+        let sla = sla();
 
         let acc = AccumulatedLatency::default();
 
@@ -779,6 +883,348 @@ mod tests {
         assert_eq!(cc, Ratio::new::<cpu>(num_rational::Ratio::new(0, 1)));
     }
 
+    /// The function is terminated before the end of its duration: it is
+    /// finished right away and its scheduled end is dropped
+    #[tokio::test]
+    async fn test_early_termination() {
+        let Instance {
+            auction,
+            function_life,
+            function_tracking,
+            resource_tracking,
+            ..
+        } = get_auction_impl(market_connected()).await;
+        let sla = Sla { duration: Time::new::<second>(60.0), ..sla() };
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+        function_life
+            .provision_function(id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+
+        function_life
+            .terminate_function(id.clone())
+            .await
+            .expect("Terminating the function failed");
+
+        function_tracking.get_finished(&id).expect("Function is not finished");
+        let (ram, cc) =
+            resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(
+            ram,
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1))
+        );
+        assert_eq!(cc, Ratio::new::<cpu>(num_rational::Ratio::new(0, 1)));
+        assert!(!auction
+            .cron
+            .get_pending()
+            .await
+            .iter()
+            .any(|(task, _)| *task.sla() == id));
+        function_life
+            .terminate_function(id)
+            .await
+            .expect_err("The function is already terminated");
+    }
+
+    /// A call for bids reaching the node again by another path gets no bid
+    #[tokio::test]
+    async fn test_duplicate_bid_request() {
+        let Instance { function_life, node_situation, .. } =
+            get_auction_impl(market_connected()).await;
+        let sla = sla();
+        let request = BidRequestOwned {
+            node_origin: node_situation.get_my_id(),
+            sla,
+            accumulated_latency: AccumulatedLatency::default(),
+            nb_propositions_required: 1,
+            request_id: Uuid::new_v4().into(),
+            hop_budget: 8,
+            visited: vec![],
+        };
+
+        let proposals = function_life
+            .bid_on_new_function_and_transmit(&request)
+            .await
+            .expect("Placement failed");
+        assert_eq!(proposals.bids.len(), 1);
+
+        let proposals = function_life
+            .bid_on_new_function_and_transmit(&request)
+            .await
+            .expect("Placement failed");
+        assert!(proposals.bids.is_empty());
+
+        // Another call, that already went through this node
+        let proposals = function_life
+            .bid_on_new_function_and_transmit(&BidRequestOwned {
+                request_id: Uuid::new_v4().into(),
+                visited: vec![node_situation.get_my_id()],
+                ..request
+            })
+            .await
+            .expect("Placement failed");
+        assert!(proposals.bids.is_empty());
+    }
+
+    /// The nodes deeper in the fog get less time to answer, but never less
+    /// than the configured minimum
+    #[tokio::test]
+    async fn test_bid_timeout() {
+        let Instance { function_life, .. } =
+            get_auction_impl(market_connected()).await;
+        let sla = sla();
+        let at = |median: f64| AccumulatedLatency {
+            median: Time::new::<second>(median),
+            ..AccumulatedLatency::default()
+        };
+
+        let shallow = function_life.bid_timeout(&sla, &at(0.1));
+        let deep = function_life.bid_timeout(&sla, &at(0.5));
+        let spent = function_life.bid_timeout(&sla, &at(1.0));
+        let overspent = function_life.bid_timeout(&sla, &at(2.0));
+        assert!(deep < shallow);
+        assert!(spent <= deep);
+        assert!(spent > Duration::ZERO);
+        assert_eq!(spent, overspent);
+    }
+
+    /// The calls for bids skip the children whose subtree is known to be too
+    /// small or too far away for the sla, the others are still asked
+    #[tokio::test]
+    async fn test_capacity_pruning() {
+        let Instance { function_life, node_situation, .. } =
+            get_auction_impl(market_connected()).await;
+        let sla = sla();
+        let request = BidRequestOwned {
+            node_origin: node_situation.get_my_id(),
+            sla,
+            accumulated_latency: AccumulatedLatency::default(),
+            nb_propositions_required: 1,
+            request_id: Uuid::new_v4().into(),
+            hop_budget: 8,
+            visited: vec![],
+        };
+        let summary = |node_id: &NodeId, millicpus, megabytes, latency| {
+            CapacitySummary {
+                node_id:         node_id.clone(),
+                max_free_cpu:    Ratio::new::<millicpu>(
+                    num_rational::Ratio::new(millicpus, 1),
+                ),
+                max_free_memory: Information::new::<megabyte>(
+                    num_rational::Ratio::new(megabytes, 1),
+                ),
+                min_latency:     Time::new::<second>(latency),
+            }
+        };
+
+        let stranger: NodeId = Uuid::new_v4().into();
+        assert!(function_life
+            .update_child_capacity(summary(&stranger, 1000, 1000, 0.0))
+            .is_err());
+
+        // Nothing listens there, asking the child fails
+        let child: NodeId = Uuid::new_v4().into();
+        node_situation.register(
+            child.clone(),
+            NodeDescription {
+                ip: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                port_http: 1.into(),
+                advertised_bandwidth:
+                    InformationRate::new::<gigabit_per_second>(
+                        num_rational::Ratio::new(1, 1),
+                    ),
+                latency: Time::new::<second>(0.1),
+            },
+        );
+        let follow_up = |latency| {
+            function_life.follow_up(
+                &request,
+                child.clone(),
+                AccumulatedLatency {
+                    median: Time::new::<second>(latency),
+                    ..AccumulatedLatency::default()
+                },
+                1,
+            )
+        };
+        assert!(follow_up(0.1).await.is_err());
+
+        function_life
+            .update_child_capacity(summary(&child, 50, 1000, 0.0))
+            .unwrap();
+        assert!(follow_up(0.1).await.unwrap().bids.is_empty());
+
+        function_life
+            .update_child_capacity(summary(&child, 1000, 1000, 0.5))
+            .unwrap();
+        assert!(follow_up(0.6).await.unwrap().bids.is_empty());
+        assert!(follow_up(0.1).await.is_err());
+
+        // The summary of the node covers the subtree of its child
+        let own = function_life.get_capacity_summary().await.unwrap();
+        function_life
+            .update_child_capacity(summary(
+                &child,
+                1_000_000_000,
+                1_000_000_000,
+                0.5,
+            ))
+            .unwrap();
+        let merged = function_life.get_capacity_summary().await.unwrap();
+        assert!(own.max_free_cpu < merged.max_free_cpu);
+        assert!(own.max_free_memory < merged.max_free_memory);
+        assert_eq!(own.min_latency, merged.min_latency);
+    }
+
+    /// Another bid won: the rejected proposal is dropped along with what it
+    /// held
+    #[tokio::test]
+    async fn test_proposal_rejection() {
+        let Instance {
+            auction,
+            function_life,
+            function_tracking,
+            resource_tracking,
+            ..
+        } = get_auction_impl(market_connected()).await;
+        let sla = sla();
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        assert!(resource_tracking.get_reservation(&id).is_some());
+
+        function_life
+            .reject_function(id.clone())
+            .await
+            .expect("Rejecting failed");
+
+        assert!(function_tracking.get_proposed(&id).is_none());
+        assert!(resource_tracking.get_reservation(&id).is_none());
+        function_life
+            .reject_function(id.clone())
+            .await
+            .expect_err("There is no proposal left to reject");
+        function_life
+            .pay_function(id)
+            .await
+            .expect_err("Paying a rejected bid should fail");
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_release() {
+        let Instance { auction, .. } =
+            get_auction_impl(market_connected()).await;
+        let counter = |window| {
+            if window == 0 {
+                auction.in_flight_functions_per_sec_1.load(Ordering::Relaxed)
+            } else {
+                auction.in_flight_functions_per_sec_2.load(Ordering::Relaxed)
+            }
+        };
+
+        let first = sla();
+        auction.check_in_flight_at(&first.id, 0).expect("Proposal refused");
+        assert_eq!(counter(0), 1);
+        assert_eq!(*auction.in_flight_windows.get(&first.id).unwrap(), 0);
+
+        // The next second resets the first window
+        let other = sla();
+        auction.check_in_flight_at(&other.id, 1).expect("Proposal refused");
+        assert_eq!(counter(0), 0);
+        assert_eq!(counter(1), 1);
+        assert!(auction.in_flight_windows.get(&first.id).is_none());
+        assert_eq!(*auction.in_flight_windows.get(&other.id).unwrap(), 1);
+
+        // The first window was reset, releasing its proposal leaves the
+        // current window alone
+        auction.release_in_flight(&first.id);
+        assert_eq!(counter(1), 1);
+
+        auction.release_in_flight(&other.id);
+        assert_eq!(counter(1), 0);
+        assert!(auction.in_flight_windows.get(&other.id).is_none());
+
+        // Releasing twice does not underflow nor touch another proposal
+        let last = sla();
+        auction.check_in_flight_at(&last.id, 1).expect("Proposal refused");
+        auction.release_in_flight(&other.id);
+        assert_eq!(counter(1), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replicas_reservation() {
+        let Instance { auction, function_life, resource_tracking, .. } =
+            get_auction_impl(market_connected()).await;
+        let (available_ram, available_cpu) =
+            resource_tracking.get_available(OFFLINE_NODE_K8S).await.unwrap();
+        let sla = |max_price| Sla {
+            memory: available_ram * num_rational::Ratio::new(3, 10),
+            cpu: available_cpu * num_rational::Ratio::new(3, 10),
+            replicas: 2,
+            max_price,
+            ..sla()
+        };
+        let zero_ram =
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1));
+        let zero_cpu = Ratio::new::<cpu>(num_rational::Ratio::new(0, 1));
+        let acc = AccumulatedLatency::default();
+
+        // A bid given up after the valuation holds nothing
+        let cheap = sla(Some(f64::MIN_POSITIVE));
+        let refused =
+            auction.bid_on(cheap.clone(), &acc).await.expect("Bidding failed");
+        assert!(refused.is_none(), "Bid above the maximum price");
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (zero_ram, zero_cpu));
+
+        // Both replicas are held, and then used once paid
+        let both = sla(None);
+        auction
+            .bid_on(both.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let reserved =
+            resource_tracking.get_reserved(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(reserved, (both.memory_per_node(), both.cpu_per_node()));
+        assert_eq!(
+            reserved,
+            (
+                both.memory * num_rational::Ratio::new(2, 1),
+                both.cpu * num_rational::Ratio::new(2, 1)
+            )
+        );
+
+        let other = sla(None);
+        let refused =
+            auction.bid_on(other.clone(), &acc).await.expect("Bidding failed");
+        assert!(refused.is_none(), "The replicas were not all reserved");
+
+        function_life
+            .pay_function(both.id.clone())
+            .await
+            .expect("Paying failed");
+        let used = resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(used, (both.memory_per_node(), both.cpu_per_node()));
+    }
+
     /// The function has a duration < to the time it takes to provision the
     /// function on the node
     #[parameterized(
@@ -794,25 +1240,7 @@ mod tests {
             resource_tracking,
             ..
         } = get_auction_impl(situation).await;
-        let sla = Sla {
-            id:                 Uuid::new_v4().into(),
-            memory:             Information::new::<megabyte>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            cpu:                Ratio::new::<millicpu>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            latency_max:        Time::new::<second>(1.0),
-            duration:           Time::new::<second>(1.0),
-            replicas:           1,
-            function_image:     "toto".to_string(),
-            function_live_name: "toto".to_string(),
-            data_flow:          vec![],
-            env_vars:           vec![],
-            input_max_size:     Information::new::<megabyte>(
-                num_rational::Ratio::new(1, 1),
-            ),
-        };
+        let sla = Sla { duration: Time::new::<second>(1.0), ..sla() };
 
         let acc = AccumulatedLatency::default();
 
@@ -866,25 +1294,7 @@ mod tests {
             ..
         } = get_auction_impl(situation).await;
 
-        let sla = Sla {
-            id:                 Uuid::new_v4().into(),
-            memory:             Information::new::<megabyte>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            cpu:                Ratio::new::<millicpu>(
-                num_rational::Ratio::new(100, 1),
-            ),
-            latency_max:        Time::new::<second>(1.0),
-            duration:           Time::new::<second>(1.0),
-            replicas:           1,
-            function_image:     "toto".to_string(),
-            function_live_name: "toto".to_string(),
-            data_flow:          vec![],
-            env_vars:           vec![],
-            input_max_size:     Information::new::<megabyte>(
-                num_rational::Ratio::new(1, 1),
-            ),
-        };
+        let sla = Sla { duration: Time::new::<second>(1.0), ..sla() };
 
         let mut sla2 = sla.clone();
         sla2.id = Uuid::new_v4().into();
@@ -998,34 +1408,28 @@ mod tests {
                     Time::new::<second>(law.sample(&mut r) as f64);
 
                 let sla = Sla {
-                    id:                 sla_id.clone(),
-                    memory:             Information::new::<megabyte>(
-                        num_rational::Ratio::new(
-                            law.sample(&mut r) as i64 * 10,
-                            1,
-                        ),
-                    ),
-                    cpu:                Ratio::new::<millicpu>(
+                    id: sla_id.clone(),
+                    memory: Information::new::<megabyte>(
                         num_rational::Ratio::new(
                             law.sample(&mut r) as i64 * 10,
                             1,
                         ),
                     ),
-                    latency_max:        Time::new::<second>(
+                    cpu: Ratio::new::<millicpu>(num_rational::Ratio::new(
+                        law.sample(&mut r) as i64 * 10,
+                        1,
+                    )),
+                    latency_max: Time::new::<second>(
                         law.sample(&mut r) as f64 * 14.0,
                     ),
-                    duration:           sla_duration.clone(),
-                    replicas:           1,
-                    function_image:     "toto".to_string(),
-                    function_live_name: "toto".to_string(),
-                    data_flow:          vec![],
-                    env_vars:           vec![],
-                    input_max_size:     Information::new::<megabyte>(
+                    duration: sla_duration,
+                    input_max_size: Information::new::<megabyte>(
                         num_rational::Ratio::new(
                             law.sample(&mut r) as i64 * 10,
                             2,
                         ),
                     ),
+                    ..sla()
                 };
 
                 let mut acc = AccumulatedLatency::default();
@@ -1091,7 +1495,6 @@ mod tests {
         assert_eq!(cc, Ratio::new::<millicpu>(num_rational::Ratio::new(0, 1)));
     }
 
-    #[cfg(feature = "quadratic_rates")]
     #[parameterized(
         node = {node_connected()},
         market = {market_connected()},
@@ -1117,23 +1520,16 @@ mod tests {
                 let sla_duration = Time::new::<second>(15.0);
 
                 let sla = Sla {
-                    id:                 sla_id.clone(),
-                    memory:             Information::new::<megabyte>(
-                        num_rational::Ratio::new(50, 1),
-                    ),
-                    cpu:                Ratio::new::<millicpu>(
+                    id: sla_id.clone(),
+                    memory: Information::new::<megabyte>(
                         num_rational::Ratio::new(50, 1),
                     ),
-                    latency_max:        Time::new::<second>(10.0),
-                    duration:           sla_duration.clone(),
-                    replicas:           1,
-                    function_image:     "toto".to_string(),
-                    function_live_name: "toto".to_string(),
-                    data_flow:          vec![],
-                    env_vars:           vec![],
-                    input_max_size:     Information::new::<megabyte>(
-                        num_rational::Ratio::new(1, 1),
-                    ),
+                    cpu: Ratio::new::<millicpu>(num_rational::Ratio::new(
+                        50, 1,
+                    )),
+                    latency_max: Time::new::<second>(10.0),
+                    duration: sla_duration,
+                    ..sla()
                 };
 
                 let bid = auction
@@ -1178,7 +1574,6 @@ mod tests {
         }
     }
 
-    #[cfg(feature = "quadratic_rates")]
     async fn _test_quadratic_rates_inner_loop(
         function_life: Arc<FunctionLife>,
         auction: Arc<Auction>,
@@ -1191,23 +1586,14 @@ mod tests {
         let sla_duration = Time::new::<second>(15.0);
 
         let sla = Sla {
-            id:                 sla_id.clone(),
-            memory:             Information::new::<megabyte>(
-                num_rational::Ratio::new(50, 1),
-            ),
-            cpu:                Ratio::new::<millicpu>(
-                num_rational::Ratio::new(50, 1),
-            ),
-            latency_max:        Time::new::<second>(10.0),
-            duration:           sla_duration.clone(),
-            replicas:           1,
-            function_image:     "toto".to_string(),
-            function_live_name: "toto".to_string(),
-            data_flow:          vec![],
-            env_vars:           vec![],
-            input_max_size:     Information::new::<megabyte>(
-                num_rational::Ratio::new(1, 1),
-            ),
+            id: sla_id.clone(),
+            memory: Information::new::<megabyte>(num_rational::Ratio::new(
+                50, 1,
+            )),
+            cpu: Ratio::new::<millicpu>(num_rational::Ratio::new(50, 1)),
+            latency_max: Time::new::<second>(10.0),
+            duration: sla_duration,
+            ..sla()
         };
 
         let bid =
@@ -1232,10 +1618,9 @@ mod tests {
             .await;
         }
 
-        return ret;
+        ret
     }
 
-    #[cfg(feature = "quadratic_rates")]
     #[parameterized(
         node = {node_connected()},
         market = {market_connected()},
@@ -1297,14 +1682,14 @@ mod tests {
             .into_iter()
             .map(|res| res.unwrap())
             .collect();
-        let mut ii = 0;
-        for (result, result2) in results.iter().zip(results2.iter()) {
+        for (ii, (result, result2)) in
+            results.iter().zip(results2.iter()).enumerate()
+        {
             assert!(
                 *result < *result2 + 0.001 || *result2 < *result + 0.001,
                 "Failed on {}",
                 ii
             );
-            ii += 1;
         }
 
         assert_eq!(function.get_utilisation_variations().await.len(), 0);
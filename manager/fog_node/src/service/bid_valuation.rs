@@ -0,0 +1,327 @@
+use crate::repository::node_situation::NodeSituation;
+use crate::repository::resource_tracking::ResourceTracking;
+use crate::service::function::{Function, UnprovisionEvent};
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use helper::uom_helper::cpu_ratio::cpu;
+use helper::{env_load, env_var};
+use model::domain::sla::{Sla, SlaFogPoint};
+use model::dto::node::{
+    BidValuationConfig, BidValuationKind, BidValuationModifier,
+};
+use model::view::auction::AccumulatedLatency;
+use nutype::nutype;
+use std::fmt;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::trace;
+use uom::num_traits::ToPrimitive;
+use uom::si::ratio::basis_point;
+use uom::si::rational64::{Information, Ratio};
+use uom::si::time::second;
+
+#[nutype(derive(PartialEq, PartialOrd), validate(finite, greater = 0.0))]
+pub struct PricingRatio(f64);
+env_var!(PRICING_CPU);
+env_var!(PRICING_CPU_INITIAL);
+env_var!(PRICING_MEM);
+env_var!(PRICING_MEM_INITIAL);
+env_var!(PRICING_GEOLOCATION);
+env_var!(RATIO_AA);
+env_var!(RATIO_BB);
+env_var!(ELECTRICITY_PRICE);
+
+/// The node retained to host the [Sla], along with the bid computed so far
+pub struct ComputedBid {
+    pub(crate) name:          String,
+    pub(crate) available_ram: Information,
+    pub(crate) available_cpu: Ratio,
+    #[allow(dead_code)]
+    pub(crate) used_ram:      Information,
+    pub(crate) used_cpu:      Ratio,
+    pub(crate) bid:           f64,
+    /// The price the function will cost if the bid wins, usually the same
+    /// as the bid
+    pub(crate) price:         f64,
+}
+
+/// Valuation of an [Sla] on a node; the valuations are chained so that one
+/// can adjust the bid the previous ones computed
+#[async_trait::async_trait]
+pub trait BidValuation: Debug + Send + Sync {
+    async fn valuate(
+        &self,
+        sla: &Sla,
+        accumulated_latency: &AccumulatedLatency,
+        computed: ComputedBid,
+    ) -> Result<ComputedBid>;
+}
+
+/// Build the chain of valuations described in the node configuration
+pub fn from_config(
+    config: &BidValuationConfig,
+    function: Arc<Function>,
+    resource_tracking: Arc<ResourceTracking>,
+    node_situation: Arc<NodeSituation>,
+) -> Result<Box<dyn BidValuation>> {
+    let mut valuations: Vec<Box<dyn BidValuation>> = Vec::new();
+    valuations.push(match config.base {
+        BidValuationKind::LinearRates => Box::new(LinearRates::new()?),
+        BidValuationKind::QuadraticRates => {
+            Box::new(QuadraticRates::new(function, resource_tracking)?)
+        }
+    });
+    for modifier in &config.modifiers {
+        valuations.push(match modifier {
+            BidValuationModifier::CpuUtilisation => Box::new(CpuUtilisation),
+            BidValuationModifier::Reduction => {
+                Box::new(Reduction::new(node_situation.clone()))
+            }
+        });
+    }
+    Ok(Box::new(ChainedValuation { valuations }))
+}
+
+/// Apply the valuations one after the other
+#[derive(Debug)]
+pub struct ChainedValuation {
+    valuations: Vec<Box<dyn BidValuation>>,
+}
+
+#[async_trait::async_trait]
+impl BidValuation for ChainedValuation {
+    async fn valuate(
+        &self,
+        sla: &Sla,
+        accumulated_latency: &AccumulatedLatency,
+        mut computed: ComputedBid,
+    ) -> Result<ComputedBid> {
+        for valuation in &self.valuations {
+            computed =
+                valuation.valuate(sla, accumulated_latency, computed).await?;
+        }
+        Ok(computed)
+    }
+}
+
+/// Valuation made from a constant rate, given by an ENV variable
+#[derive(Debug)]
+pub struct LinearRates {
+    pricing_cpu_initial: f64,
+    pricing_mem_initial: f64,
+}
+
+impl LinearRates {
+    pub fn new() -> Result<Self> {
+        // let pricing_cpu =
+        //     env_load!(PricingRatio, PRICING_CPU, f64).into_inner();
+        let pricing_cpu_initial =
+            env_load!(PricingRatio, PRICING_CPU_INITIAL, f64).into_inner();
+        // let pricing_mem =
+        //     env_load!(PricingRatio, PRICING_MEM, f64).into_inner();
+        let pricing_mem_initial =
+            env_load!(PricingRatio, PRICING_MEM_INITIAL, f64).into_inner();
+        Ok(Self { pricing_cpu_initial, pricing_mem_initial })
+    }
+}
+
+#[async_trait::async_trait]
+impl BidValuation for LinearRates {
+    async fn valuate(
+        &self,
+        sla: &Sla,
+        _accumulated_latency: &AccumulatedLatency,
+        computed: ComputedBid,
+    ) -> Result<ComputedBid> {
+        let ram_ratio_sla = (sla.memory / computed.available_ram)
+            .get::<cpu>()
+            .to_f64()
+            .context("Overflow while converting ratio of memory")?;
+        let cpu_ratio_sla = (sla.cpu / computed.available_cpu)
+            .get::<cpu>()
+            .to_f64()
+            .context("Overflow while converting ratio of cpu")?;
+        let bid: f64 = ram_ratio_sla * self.pricing_mem_initial
+            + cpu_ratio_sla * self.pricing_cpu_initial;
+
+        trace!("price on {:?} is {:?}", computed.name, bid);
+
+        Ok(ComputedBid { bid, price: bid, ..computed })
+    }
+}
+
+/// Use quadratic rates from the ENV variables, taking into account the
+/// functions already running until their end
+pub struct QuadraticRates {
+    function:          Arc<Function>,
+    resource_tracking: Arc<ResourceTracking>,
+    aa:                f64,
+    bb:                f64,
+    electricity_price: f64,
+}
+
+impl Debug for QuadraticRates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuadraticRates")
+            .field("aa", &self.aa)
+            .field("bb", &self.bb)
+            .field("electricity_price", &self.electricity_price)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QuadraticRates {
+    pub fn new(
+        function: Arc<Function>,
+        resource_tracking: Arc<ResourceTracking>,
+    ) -> Result<Self> {
+        let aa = env_load!(PricingRatio, RATIO_AA, f64).into_inner();
+        let bb = env_load!(PricingRatio, RATIO_BB, f64).into_inner();
+        let electricity_price =
+            env_load!(PricingRatio, ELECTRICITY_PRICE, f64).into_inner();
+        Ok(Self { function, resource_tracking, aa, bb, electricity_price })
+    }
+}
+
+#[async_trait::async_trait]
+impl BidValuation for QuadraticRates {
+    async fn valuate(
+        &self,
+        sla: &Sla,
+        _accumulated_latency: &AccumulatedLatency,
+        computed: ComputedBid,
+    ) -> Result<ComputedBid> {
+        let aa = self.aa;
+        let bb = self.bb;
+        let now = Utc::now();
+        let mut utilisation = 0.0;
+        for UnprovisionEvent { timestamp, sla, node, .. } in
+            self.function.get_utilisation_variations().await.iter()
+        {
+            let duration = if *timestamp > now {
+                let duration = *timestamp - now;
+                tracing::trace!(
+                    "timestamp: {:?}, now: {:?}, duration: {:?}",
+                    *timestamp,
+                    now,
+                    duration
+                );
+                duration
+            } else {
+                Duration::microseconds(0)
+            };
+            let (_available_ram, available_cpu) = self
+                .resource_tracking
+                .get_available(node)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to get available resources from tracking \
+                         data for node {}",
+                        node
+                    )
+                })?;
+
+            let duration = duration.num_seconds() as f64;
+            utilisation += (sla.cpu / available_cpu)
+                .get::<cpu>()
+                .to_f64()
+                .context("Overlfow while converting to f64")?
+                * duration;
+        }
+        let sla_cpu = (sla.cpu / computed.available_cpu)
+            .get::<cpu>()
+            .to_f64()
+            .context("Overflow during conversion to f64")?;
+        let sla_duration = sla.duration.get::<second>();
+        let electricity_price = self.electricity_price;
+        let bid = electricity_price
+            * sla_cpu
+            * (2.0 * aa * utilisation + (aa * sla_cpu + bb) * sla_duration);
+
+        tracing::event!(
+            tracing::Level::TRACE,
+            bid,
+            sla_cpu,
+            sla_duration,
+            electricity_price,
+            utilisation,
+            aa,
+            bb
+        );
+
+        trace!("(quadratic) price on is {:?}", bid);
+        assert!(bid > 0.000001, "the bid wasn't > 0");
+
+        Ok(ComputedBid { bid, price: bid, ..computed })
+    }
+}
+
+/// The bid is the cpu utilisation of the node: the more the cpu is used the
+/// lower the bid and the easiest to win. The previous valuation is kept as
+/// the price.
+#[derive(Debug)]
+pub struct CpuUtilisation;
+
+#[async_trait::async_trait]
+impl BidValuation for CpuUtilisation {
+    async fn valuate(
+        &self,
+        _sla: &Sla,
+        _accumulated_latency: &AccumulatedLatency,
+        computed: ComputedBid,
+    ) -> Result<ComputedBid> {
+        let cpu_ratio_sla = computed.used_cpu / computed.available_cpu;
+        let bid = cpu_ratio_sla
+            .get::<basis_point>()
+            .to_f64()
+            .context("Overflow during bid conversion to f64")?;
+
+        trace!(
+            "(cpu utilisation) price on {:?} is {:?}, and bid is {:?}",
+            computed.name,
+            computed.price,
+            bid
+        );
+        Ok(ComputedBid { bid, ..computed })
+    }
+}
+
+/// Advantage when the node is the data source of the function
+#[derive(Debug)]
+pub struct Reduction {
+    node_situation:   Arc<NodeSituation>,
+    reduction_factor: f64,
+}
+
+impl Reduction {
+    pub fn new(node_situation: Arc<NodeSituation>) -> Self {
+        Self { node_situation, reduction_factor: 0.75 }
+    }
+
+    fn is_on_device(&self, point: &SlaFogPoint) -> bool {
+        let me = &self.node_situation.get_my_id();
+        matches!(point, SlaFogPoint::DataSource(source) if source == me)
+    }
+}
+
+#[async_trait::async_trait]
+impl BidValuation for Reduction {
+    async fn valuate(
+        &self,
+        sla: &Sla,
+        _accumulated_latency: &AccumulatedLatency,
+        computed: ComputedBid,
+    ) -> Result<ComputedBid> {
+        let on_device = sla.data_flow.iter().any(|flow| {
+            self.is_on_device(&flow.from) || self.is_on_device(&flow.to)
+        });
+        if !on_device {
+            return Ok(computed);
+        }
+
+        let bid = computed.bid * self.reduction_factor;
+        trace!("(reduction) bid on {:?} is {:?}", computed.name, bid);
+        Ok(ComputedBid { bid, ..computed })
+    }
+}
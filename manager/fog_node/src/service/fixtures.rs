@@ -0,0 +1,248 @@
+use crate::repository::cron::Cron;
+use crate::repository::faas::{
+    FaaSBackend, FaaSBackendOfflineImpl, OfflineCluster,
+};
+use crate::repository::function_tracking::FunctionTracking;
+use crate::repository::k8s::K8s;
+use crate::repository::latency_estimation::{
+    LatencyEstimation, LatencyEstimationOfflineImpl,
+};
+use crate::repository::node_query::NodeQuery;
+use crate::repository::node_situation::NodeSituation;
+use crate::repository::resource_tracking::ResourceTracking;
+use crate::service::admin::Admin;
+use crate::service::auction::Auction;
+use crate::service::function::Function;
+use crate::service::function_life::FunctionLife;
+use crate::service::neighbor_monitor::NeighborMonitor;
+use helper::monitoring::{InfluxAddress, MetricsExporter};
+use helper::uom_helper::cpu_ratio::{cpu, millicpu};
+use model::domain::sla::Sla;
+use model::dto::node::{
+    BidValuationConfig, MaxInFlight, NodeCategory, NodeSituationData,
+    PlacementStrategyKind,
+};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+use uom::si::f64::Time;
+use uom::si::information::{gigabyte, megabyte};
+use uom::si::information_rate::gigabit_per_second;
+use uom::si::rational64::{Information, InformationRate, Ratio};
+use uom::si::time::second;
+use uuid::Uuid;
+
+/// The services of a node wired together on the offline backends
+pub struct Instance {
+    pub auction:           Arc<Auction>,
+    pub function_life:     Arc<FunctionLife>,
+    pub function_tracking: Arc<FunctionTracking>,
+    pub resource_tracking: Arc<ResourceTracking>,
+    pub reserved_cpu:      Ratio,
+    pub reserved_memory:   Information,
+    pub function:          Arc<Function>,
+    pub node_situation:    Arc<NodeSituation>,
+    pub admin:             Arc<Admin>,
+    pub cluster:           Arc<OfflineCluster>,
+    pub cron:              Arc<Cron>,
+}
+
+/// A small function the nodes accept, tests override what they need
+pub fn sla() -> Sla {
+    Sla {
+        id:                 Uuid::new_v4().into(),
+        memory:             Information::new::<megabyte>(
+            num_rational::Ratio::new(100, 1),
+        ),
+        cpu:                Ratio::new::<millicpu>(num_rational::Ratio::new(
+            100, 1,
+        )),
+        latency_max:        Time::new::<second>(1.0),
+        duration:           Time::new::<second>(5.0),
+        replicas:           1,
+        function_image:     "toto".to_string(),
+        function_live_name: "toto".to_string(),
+        data_flow:          vec![],
+        env_vars:           vec![],
+        env_process:        None,
+        max_price:          None,
+        spread_replicas:    false,
+        input_max_size:     Information::new::<megabyte>(
+            num_rational::Ratio::new(1, 1),
+        ),
+    }
+}
+
+pub fn market_connected() -> NodeCategory {
+    NodeCategory::MarketConnected {
+        market_ip:   std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+        market_port: 1234.into(),
+    }
+}
+
+pub fn node_connected() -> NodeCategory {
+    NodeCategory::NodeConnected {
+        parent_latency: Time::new::<second>(15.0),
+        parent_id:      Uuid::new_v4().into(),
+        parent_node_ip: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+
+        parent_node_port_http: 1234.into(),
+    }
+}
+
+pub async fn get_auction_impl(situation: NodeCategory) -> Instance {
+    get_auction_impl_with(
+        situation,
+        PlacementStrategyKind::default(),
+        BidValuationConfig::default(),
+    )
+    .await
+}
+
+pub async fn get_auction_impl_with(
+    situation: NodeCategory,
+    placement_strategy: PlacementStrategyKind,
+    bid_valuation: BidValuationConfig,
+) -> Instance {
+    get_auction_impl_tracked(
+        situation,
+        placement_strategy,
+        bid_valuation,
+        Arc::new(FunctionTracking::default()),
+    )
+    .await
+}
+
+pub async fn get_auction_impl_tracked(
+    situation: NodeCategory,
+    placement_strategy: PlacementStrategyKind,
+    bid_valuation: BidValuationConfig,
+    function_tracking: Arc<FunctionTracking>,
+) -> Instance {
+    let _ = crate::init_subscriber("test".into(), "trace".into());
+    let k8s = Arc::new(K8s::new());
+    let metrics = Arc::new(
+        MetricsExporter::new(
+            InfluxAddress::try_new("127.0.0.1:1234").unwrap(),
+            helper::monitoring::InfluxOrg::try_new("toto").unwrap(),
+            helper::monitoring::InfluxToken::try_new("xowyTh1iGcNAZsZeydESOHKvENvcyPaWg8hUe3tO4vPOw_buZVwOdUrqG3gwV314aYd9SWKHcxlykcQY_rwYVQ==").unwrap(),
+            helper::monitoring::InfluxBucket::try_new("toto").unwrap(),
+            helper::monitoring::InstanceName::try_new("toto").unwrap(),
+        )
+        .await
+        .unwrap(),
+    );
+    let reserved_cpu = Ratio::new::<cpu>(num_rational::Ratio::new(20, 1));
+    let reserved_memory =
+        Information::new::<gigabyte>(num_rational::Ratio::new(8, 1));
+    let resource_tracking = Arc::new(
+        ResourceTracking::new(
+            k8s,
+            metrics.clone(),
+            reserved_cpu,
+            reserved_memory,
+        )
+        .await
+        .unwrap(),
+    );
+    let cluster = Arc::new(OfflineCluster::default());
+    let backend: Arc<Box<dyn FaaSBackend>> =
+        Arc::new(Box::new(FaaSBackendOfflineImpl::with_cluster(
+            Duration::from_secs(2),
+            cluster.clone(),
+        )));
+    let node_situation = Arc::new(NodeSituation::new(NodeSituationData {
+        situation: std::sync::RwLock::new(situation),
+        my_id: Uuid::new_v4().into(),
+        my_public_ip: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+        my_private_ip: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+        my_public_port_http: 12345.into(),
+        my_public_port_faas: 1234.into(),
+        tags: vec!["toto".to_string()],
+        reserved_memory,
+        reserved_cpu,
+        max_in_flight_functions_proposals: MaxInFlight::try_new(160).unwrap(),
+        my_advertised_bandwidth: InformationRate::new::<gigabit_per_second>(
+            num_rational::Ratio::new(1, 1),
+        ),
+        children: dashmap::DashMap::new(),
+        placement_strategy,
+        bid_valuation,
+    }));
+    let latency_estimation_repo: Arc<Box<dyn LatencyEstimation>> = Arc::new(
+        Box::new(LatencyEstimationOfflineImpl::new(node_situation.clone())),
+    );
+
+    let neighbor_monitor =
+        Arc::new(NeighborMonitor::new(latency_estimation_repo));
+
+    let client_builder = reqwest_middleware::ClientBuilder::new(
+        reqwest::Client::builder().build().unwrap(),
+    );
+    let http_client = Arc::new(client_builder.build());
+
+    let node_query =
+        Arc::new(NodeQuery::new(node_situation.clone(), http_client.clone()));
+
+    let cron = Arc::new(
+        Cron::new(uom::si::f64::Time::new::<uom::si::time::second>(15.0))
+            .await
+            .expect("Failed to start Cron repository"),
+    );
+
+    let function = Arc::new(Function::new(
+        backend.clone(),
+        node_situation.clone(),
+        neighbor_monitor.clone(),
+        node_query.clone(),
+        resource_tracking.clone(),
+        function_tracking.clone(),
+        metrics.clone(),
+        cron.clone(),
+    ));
+    let auction = Arc::new(
+        Auction::new(
+            resource_tracking.clone(),
+            function_tracking.clone(),
+            metrics.clone(),
+            function.clone(),
+            node_situation.clone(),
+            cron.clone(),
+        )
+        .unwrap(),
+    );
+
+    let function_life = Arc::new(
+        FunctionLife::new(
+            function.clone(),
+            auction.clone(),
+            node_situation.clone(),
+            neighbor_monitor.clone(),
+            node_query,
+            function_tracking.clone(),
+            cron.clone(),
+            metrics,
+        )
+        .unwrap(),
+    );
+    let admin = Arc::new(Admin::new(
+        function_tracking.clone(),
+        resource_tracking.clone(),
+        neighbor_monitor,
+        node_situation.clone(),
+        cron.clone(),
+    ));
+    Instance {
+        auction,
+        function_life,
+        function_tracking,
+        resource_tracking,
+        reserved_cpu,
+        reserved_memory,
+        function,
+        node_situation,
+        admin,
+        cluster,
+        cron,
+    }
+}
@@ -1,23 +1,24 @@
-use crate::monitoring::{PaidFunctions, ProvisionedFunctions};
-use crate::repository::cron::{Cron, Task, TaskEntry, UnprovisionFunction};
+use crate::monitoring::{FunctionDrift, PaidFunctions, ProvisionedFunctions};
+use crate::repository::cron::{Cron, Task, UnprovisionFunction};
 use crate::repository::faas::FaaSBackend;
-use crate::repository::function_tracking::FunctionTracking;
+use crate::repository::function_tracking::{BidExpired, FunctionTracking};
 use crate::repository::resource_tracking::ResourceTracking;
 use crate::service::neighbor_monitor::NeighborMonitor;
 use crate::{NodeQuery, NodeSituation};
 use anyhow::{ensure, Context, Result};
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
 use helper::err::IndividualErrorList;
+use helper::http_error::{CodeContext, ErrorCode};
 use helper::monitoring::MetricsExporter;
 use model::domain::sla::Sla;
-use model::dto::function::Paid;
+use model::dto::function::{Finished, Paid};
 use model::SlaId;
+use std::collections::HashSet;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
-use tracing::trace;
+use tracing::{trace, warn};
 use uom::si::rational64::{Information, Ratio};
-use uom::si::time::millisecond;
 
 pub struct UnprovisionEvent {
     #[allow(dead_code)]
@@ -55,15 +56,14 @@ pub(in crate::service) fn satisfiability_check(
     available_cpu: &Ratio,
     sla: &Sla,
 ) -> bool {
-    let would_be_used_ram = *used_ram
-        + (sla.memory * num_rational::Ratio::new(sla.replicas as i64, 1));
-    let would_be_used_cpu = *used_cpu
-        + (sla.cpu * num_rational::Ratio::new(sla.replicas as i64, 1));
+    let would_be_used_ram = *used_ram + sla.memory_per_node();
+    let would_be_used_cpu = *used_cpu + sla.cpu_per_node();
 
     would_be_used_cpu < *available_cpu && would_be_used_ram < *available_ram
 }
 
 impl Function {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         function: Arc<Box<dyn FaaSBackend>>,
         node_situation: Arc<NodeSituation>,
@@ -130,32 +130,31 @@ impl Function {
         self.function.check_is_live(&function).await?;
 
         let live = function.to_live();
-        self.function_tracking.save_live(&id, live);
+        self.function_tracking
+            .save_live(&id, live)
+            .context("Failed to save the live function")?;
         Ok(())
     }
 
+    /// Compare the functions deployed on the cluster with the tracked ones,
+    /// correcting the drifts
+    pub async fn reconcile(&self) -> Result<()> {
+        let function = self.lock().await?;
+        function.reconcile_deployed().await
+    }
+
     #[allow(dead_code)]
     pub async fn get_utilisation_variations(&self) -> Vec<UnprovisionEvent> {
         let mut ret = Vec::new();
-        for elem in self.cron.tasks.lock().await.iter() {
-            let TaskEntry {
-                task:
-                    Task::UnprovisionFunction(UnprovisionFunction {
-                        sla,
-                        node,
-                        ..
-                    }),
-                created_at,
-            } = elem;
+        for (task, deadline) in self.cron.get_pending().await {
+            let Task::UnprovisionFunction(UnprovisionFunction { sla, node }) =
+                task
+            else {
+                continue;
+            };
             if let Some(sla) = self.function_tracking.get_finishable_sla(&sla)
             {
-                let timestamp = *created_at
-                    + Duration::try_milliseconds(
-                        sla.duration.get::<millisecond>() as i64,
-                    )
-                    .unwrap();
-                let node = node.to_string();
-                ret.push(UnprovisionEvent { timestamp, sla, node });
+                ret.push(UnprovisionEvent { timestamp: deadline, sla, node });
             }
         }
         ret
@@ -177,16 +176,26 @@ impl Function<Locked> {
     pub(in crate::service) async fn pay_function(
         &self,
         id: SlaId,
+        paid_at: DateTime<Utc>,
     ) -> Result<Paid> {
-        let proposal =
-            self.function_tracking.get_proposed(&id).with_context(|| {
+        if self.function_tracking.is_expired(&id) {
+            return Err(BidExpired(id.clone()))
+                .with_code(ErrorCode::Expired, || {
+                    format!("Cannot pay for the function {}", id)
+                });
+        }
+        let proposal = self.function_tracking.get_proposed(&id).with_code(
+            ErrorCode::NotFound,
+            || {
                 format!(
                     "Failed to get proposed function {} from the tracking \
                      data",
                     id
                 )
-            })?;
+            },
+        )?;
 
+        let _reservations = self.resource_tracking.lock_reservations().await;
         let (used_ram, used_cpu) = self
             .resource_tracking
             .get_used(&proposal.node)
@@ -195,7 +204,7 @@ impl Function<Locked> {
                 format!(
                     "Failed to get used resources from tracking data for \
                      node {}",
-                    &proposal.node
+                    proposal.node
                 )
             })?;
         let (available_ram, available_cpu) = self
@@ -206,27 +215,47 @@ impl Function<Locked> {
                 format!(
                     "Failed to get available resources from tracking data \
                      for node {}",
-                    &proposal.node
+                    proposal.node
+                )
+            })?;
+        // The other bids pending on the node still hold their resources
+        let (mut reserved_ram, mut reserved_cpu) = self
+            .resource_tracking
+            .get_reserved(&proposal.node)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to get reserved resources from tracking data for \
+                     node {}",
+                    proposal.node
                 )
             })?;
+        if let Some(own) = self.resource_tracking.get_reservation(&id) {
+            if own.node == proposal.node {
+                reserved_ram -= own.memory;
+                reserved_cpu -= own.cpu;
+            }
+        }
 
         ensure!(
             satisfiability_check(
-                &used_ram,
-                &used_cpu,
+                &(used_ram + reserved_ram),
+                &(used_cpu + reserved_cpu),
                 &available_ram,
                 &available_cpu,
                 &proposal.sla
             ),
-            "The SLA cannot be respected because at least a constrainst is \
-             not satisfiable (anymore?!)"
+            ErrorCode::Unsatisfiable.error(
+                "The SLA cannot be respected because at least a constrainst \
+                 is not satisfiable (anymore?!)"
+            )
         );
 
         let name = proposal.node.clone();
-        let sla_cpu = proposal.sla.cpu;
-        let sla_memory = proposal.sla.memory;
+        let sla_cpu = proposal.sla.cpu_per_node();
+        let sla_memory = proposal.sla.memory_per_node();
 
-        let paid = proposal.to_paid(Utc::now());
+        let paid = proposal.to_paid(paid_at);
 
         self.metrics
             .observe(PaidFunctions {
@@ -236,7 +265,9 @@ impl Function<Locked> {
                 timestamp:     Utc::now(),
             })
             .await?;
-        self.function_tracking.save_paid(&id, paid);
+        self.function_tracking
+            .save_paid(&id, paid)
+            .context("Failed to save the payment of the function")?;
         let paid = self.function_tracking.get_paid(&id).context(
             "Could not find any record of the just registerd payment",
         )?;
@@ -245,21 +276,52 @@ impl Function<Locked> {
             .set_used(name, used_ram + sla_memory, used_cpu + sla_cpu)
             .await
             .context("Could not set updated tracked cpu and memory")?;
+        self.resource_tracking.release_reservation(&id);
         Ok(paid)
     }
 
+    /// Account for the resources used by the functions recovered from the
+    /// disk, returning their payment to schedule their end
+    pub(in crate::service) async fn recover_functions(&self) -> Vec<Paid> {
+        let recovered = self.function_tracking.get_recoverable();
+        for paid in recovered.iter() {
+            let res = async {
+                let (used_ram, used_cpu) =
+                    self.resource_tracking.get_used(&paid.node).await?;
+                self.resource_tracking
+                    .set_used(
+                        paid.node.clone(),
+                        used_ram + paid.sla.memory_per_node(),
+                        used_cpu + paid.sla.cpu_per_node(),
+                    )
+                    .await
+            }
+            .await;
+            if let Err(err) = res {
+                warn!(
+                    "Failed to account for the resources of the recovered \
+                     function {} on node {}: {:?}",
+                    paid.sla.id, paid.node, err
+                );
+            }
+        }
+        recovered
+    }
+
     pub(in crate::service) async fn provision_function(
         &self,
         id: SlaId,
     ) -> Result<()> {
-        let paid_proposal =
-            self.function_tracking.get_paid(&id).with_context(|| {
+        let paid_proposal = self.function_tracking.get_paid(&id).with_code(
+            ErrorCode::Conflict,
+            || {
                 format!(
                     "Failed to get proposed function {} from the tracking \
                      data",
                     id
                 )
-            })?;
+            },
+        )?;
 
         let provisioned = self
             .function
@@ -276,7 +338,9 @@ impl Function<Locked> {
                 timestamp:     Utc::now(),
             })
             .await?;
-        self.function_tracking.save_provisioned(&id, provisioned);
+        self.function_tracking
+            .save_provisioned(&id, provisioned)
+            .context("Failed to save the provisioned function")?;
 
         Ok(())
     }
@@ -299,16 +363,24 @@ impl Function<Locked> {
             )?;
 
         let record = boxed_record.to_finished();
-        self.function_tracking.save_finished(function, record);
+        self.function_tracking
+            .save_finished(function, record)
+            .context("Failed to save the finished function")?;
 
         let record = self.function_tracking.get_finished(function).context(
             "Failed to get the record associated to the sla id, it is not in \
              a 'finished' state",
         )?;
 
+        self.release_resources(&record).await
+    }
+
+    /// Give back the resources used by the function that is no longer
+    /// running
+    async fn release_resources(&self, record: &Finished) -> Result<()> {
         let name = record.node.clone();
-        let sla_cpu = record.sla.cpu;
-        let sla_memory = record.sla.memory;
+        let sla_cpu = record.sla.cpu_per_node();
+        let sla_memory = record.sla.memory_per_node();
 
         let (memory, cpu) = self
             .resource_tracking
@@ -331,10 +403,139 @@ impl Function<Locked> {
             .await
     }
 
+    /// The function disappeared from the cluster before its end
+    async fn fail_function(&self, function: &SlaId) -> Result<()> {
+        let boxed_record =
+            self.function_tracking.get_finishable(function).context(
+                "Failed to get the record associated to the sla id, it is \
+                 not in a 'finishable' state",
+            )?;
+
+        let record = boxed_record.to_finished();
+        self.function_tracking
+            .save_failed(function, record)
+            .context("Failed to save the failed function")?;
+
+        let record = self.function_tracking.get_failed(function).context(
+            "Failed to get the record associated to the sla id, it is not in \
+             a 'failed' state",
+        )?;
+
+        self.release_resources(&record).await
+    }
+
+    async fn observe_drift(
+        &self,
+        kind: &str,
+        function_name: String,
+    ) -> Result<()> {
+        self.metrics
+            .observe(FunctionDrift {
+                n: 1,
+                kind: kind.to_string(),
+                function_name,
+                timestamp: Utc::now(),
+            })
+            .await
+    }
+
+    /// Compare the functions deployed on the cluster with the tracked ones:
+    /// the tracked functions that disappeared are marked as failed, and the
+    /// functions and services not tracked are removed
+    pub(in crate::service) async fn reconcile_deployed(&self) -> Result<()> {
+        let functions: HashSet<String> = self
+            .function
+            .list_functions()
+            .await
+            .context("Failed to list the deployed functions")?
+            .into_iter()
+            .collect();
+        let services: HashSet<String> = self
+            .function
+            .list_services()
+            .await
+            .context("Failed to list the deployed services")?
+            .into_iter()
+            .collect();
+
+        let mut errors = vec![];
+        let mut tracked = HashSet::new();
+        for (id, function_name) in self.function_tracking.get_deployed() {
+            if functions.contains(&function_name)
+                && services.contains(&function_name)
+            {
+                tracked.insert(function_name);
+                continue;
+            }
+
+            warn!(
+                "Tracked function {} disappeared from the cluster, marking \
+                 it as failed",
+                function_name
+            );
+            if let Err(err) = self.fail_function(&id).await {
+                errors.push(err);
+                continue;
+            }
+            if let Err(err) =
+                self.observe_drift("disappeared", function_name).await
+            {
+                errors.push(err);
+            }
+        }
+
+        for function_name in functions.difference(&tracked) {
+            warn!("Removing orphan function {}", function_name);
+            if let Err(err) = self
+                .function
+                .remove_orphan_function(function_name.clone())
+                .await
+            {
+                errors.push(err);
+                continue;
+            }
+            if let Err(err) = self
+                .observe_drift("orphan_function", function_name.clone())
+                .await
+            {
+                errors.push(err);
+            }
+        }
+
+        for function_name in services.difference(&tracked) {
+            warn!("Removing orphan service of {}", function_name);
+            if let Err(err) = self
+                .function
+                .remove_orphan_service(function_name.clone())
+                .await
+            {
+                errors.push(err);
+                continue;
+            }
+            if let Err(err) = self
+                .observe_drift("orphan_service", function_name.clone())
+                .await
+            {
+                errors.push(err);
+            }
+        }
+
+        ensure!(
+            errors.is_empty(),
+            "Failed to reconcile the deployed functions: {}",
+            IndividualErrorList::from(errors)
+        );
+        Ok(())
+    }
+
     pub(in crate::service) async fn finish_function(
         &self,
         function: SlaId,
     ) -> Result<()> {
+        if self.function_tracking.get_failed(&function).is_some() {
+            trace!("Function {} already failed, nothing to finish", function);
+            return Ok(());
+        }
         let mut errors = vec![];
         if let Err(err) = self.finish_record(&function).await {
             errors.push(err);
@@ -350,3 +551,133 @@ impl Function<Locked> {
         Ok(())
     }
 }
+
+#[cfg(feature = "offline")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::k8s::OFFLINE_NODE_K8S;
+    use crate::service::fixtures::{
+        get_auction_impl, get_auction_impl_tracked, market_connected, sla,
+        Instance,
+    };
+    use helper::uom_helper::cpu_ratio::cpu;
+    use model::dto::node::{BidValuationConfig, PlacementStrategyKind};
+    use model::view::auction::AccumulatedLatency;
+    use uom::si::information::megabyte;
+
+    /// The function is lost by the cluster during a restart of the node: it
+    /// must be marked as failed and its resources given back
+    #[tokio::test]
+    async fn test_reconcile_disappeared() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let Instance {
+            auction,
+            function_life,
+            function,
+            function_tracking,
+            ..
+        } = get_auction_impl_tracked(
+            market_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig::default(),
+            Arc::new(FunctionTracking::new(&db).unwrap()),
+        )
+        .await;
+
+        let sla = sla();
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+        function_life
+            .provision_function(id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+
+        function.reconcile().await.expect("Reconciliation failed");
+        assert!(function_tracking.get_removable(&id).is_some());
+
+        // Restart the node on the same database, the functions deployed are
+        // lost
+        let Instance {
+            function_life,
+            function,
+            function_tracking,
+            resource_tracking,
+            ..
+        } = get_auction_impl_tracked(
+            market_connected(),
+            PlacementStrategyKind::default(),
+            BidValuationConfig::default(),
+            Arc::new(FunctionTracking::new(&db).unwrap()),
+        )
+        .await;
+        function_life.recover().await.expect("Recovery failed");
+
+        function.reconcile().await.expect("Reconciliation failed");
+
+        function_tracking.get_failed(&id).expect("Function is not failed");
+        let (ram, cc) =
+            resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(
+            ram,
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1))
+        );
+        assert_eq!(cc, Ratio::new::<cpu>(num_rational::Ratio::new(0, 1)));
+    }
+
+    /// Functions and services left on the cluster by the node but not
+    /// tracked are removed, the ones not deployed by the node are left alone
+    #[tokio::test]
+    async fn test_reconcile_orphans() {
+        let Instance { auction, function_life, function, cluster, .. } =
+            get_auction_impl(market_connected()).await;
+
+        let sla = sla();
+        auction
+            .bid_on(sla.clone(), &AccumulatedLatency::default())
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        function_life
+            .pay_function(sla.id.clone())
+            .await
+            .expect("Function paid failed");
+        function_life
+            .provision_function(sla.id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+        let tracked = format!("fogfn-{}", sla.id);
+
+        // Left over by the node
+        cluster.functions.insert("fogfn-orphan".to_string());
+        cluster.services.insert("dynamic-service-fogfn-orphan".to_string());
+        cluster.services.insert("dynamic-service-fogfn-lonely".to_string());
+        // Deployed by someone else
+        cluster.functions.insert("nginx".to_string());
+        cluster.services.insert("dynamic-service-nginx".to_string());
+        cluster.services.insert("kube-dns".to_string());
+
+        function.reconcile().await.expect("Reconciliation failed");
+
+        assert!(cluster.functions.contains(&tracked));
+        assert!(cluster
+            .services
+            .contains(&format!("dynamic-service-{}", tracked)));
+        assert!(!cluster.functions.contains("fogfn-orphan"));
+        assert!(!cluster.services.contains("dynamic-service-fogfn-orphan"));
+        assert!(!cluster.services.contains("dynamic-service-fogfn-lonely"));
+        assert!(cluster.functions.contains("nginx"));
+        assert!(cluster.services.contains("dynamic-service-nginx"));
+        assert!(cluster.services.contains("kube-dns"));
+    }
+}
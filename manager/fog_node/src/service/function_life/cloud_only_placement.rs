@@ -1,32 +1,46 @@
 use super::*;
-use anyhow::{anyhow, ensure, Context, Result};
-use model::domain::sla::Sla;
-use model::view::auction::{BidProposal, BidProposals, BidRequest};
-use model::NodeId;
-use uom::si::f64::Time;
+use anyhow::{anyhow, ensure};
+use model::dto::node::NodeDescription;
+use model::view::auction::{BidProposal, BidProposals};
 
-impl FunctionLife {
-    /// Here the operation will be sequential, first looking to place on a
-    /// bottom node, or a child at least, and only then to consider
-    /// itself as a candidate
-    pub async fn bid_on_new_function_and_transmit(
+#[derive(Debug)]
+pub struct CloudOnlyPlacement;
+
+#[async_trait::async_trait]
+impl PlacementStrategy for CloudOnlyPlacement {
+    /// Pass the [Sla] up to the parent until reaching the Cloud, that is to
+    /// say the node without a parent, which is the only one to bid
+    async fn bid_on_new_function_and_transmit(
         &self,
-        sla: &Sla,
-        _from: NodeId,
-        accumulated_latency: Time,
+        life: &FunctionLife,
+        bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
-        todo!("Maintance to be done");
-        match self.node_situation.get_parent_id() {
+        let sla = &bid_request.sla;
+        let accumulated_latency = &bid_request.accumulated_latency;
+
+        match life.node_situation.get_parent_id() {
             Some(parent) => {
-                let bid = self
-                    .node_query
-                    .request_neighbor_bid(
-                        &BidRequest {
-                            sla,
-                            node_origin: self.node_situation.get_my_id(),
-                            accumulated_latency,
-                        },
+                let latency = life
+                    .neighbor_monitor
+                    .get_latency_to(&parent)
+                    .await
+                    .ok_or_else(|| {
+                        anyhow!("Cannot get Latency of {}", parent)
+                    })?;
+                let NodeDescription { advertised_bandwidth, .. } = life
+                    .node_situation
+                    .get_fog_node_neighbor(&parent)
+                    .ok_or_else(|| {
+                        anyhow!("Cannot get bandwidth of {}", parent)
+                    })?;
+
+                let bid = life
+                    .follow_up(
+                        bid_request,
                         parent,
+                        accumulated_latency
+                            .accumulate(latency, advertised_bandwidth),
+                        1,
                     )
                     .await
                     .context(
@@ -42,7 +56,7 @@ impl FunctionLife {
                 Ok(bid)
             }
             None => {
-                let (id, record) = self
+                let (id, record) = life
                     .auction
                     .bid_on(sla.clone(), accumulated_latency)
                     .await
@@ -50,9 +64,10 @@ impl FunctionLife {
                     .ok_or_else(|| anyhow!("Cannot accept sla"))?;
                 Ok(BidProposals {
                     bids: vec![BidProposal {
-                        node_id: self.node_situation.get_my_id(),
+                        node_id: life.node_situation.get_my_id(),
                         id,
-                        bid: record.0.bid,
+                        bid: record.bid,
+                        accumulated_latency: accumulated_latency.median,
                     }],
                 })
             }
@@ -1,52 +1,66 @@
 use super::*;
-use anyhow::{Context, Result};
 use futures::future::{join, try_join_all};
-use model::domain::sla::Sla;
-use model::view::auction::{BidProposal, BidProposals, BidRequest};
-use model::NodeId;
-use uom::si::f64::Time;
+use model::dto::node::NodeDescription;
+use model::view::auction::{BidProposal, BidProposals};
+use tracing::debug;
+
+#[derive(Debug)]
+pub struct CloudOnlyV2Placement {
+    is_cloud: bool,
+}
+
+impl CloudOnlyV2Placement {
+    /// A node is a Cloud if told so by the IS_CLOUD env var; the root of the
+    /// fog, connected to the market, always is one
+    pub fn new(is_root: bool) -> Self {
+        let is_cloud = is_root
+            || std::env::var("IS_CLOUD")
+                .map(|x| x == "is_cloud")
+                .unwrap_or(false);
+        Self { is_cloud }
+    }
 
-impl FunctionLife {
     /// Follow up the [Sla] to the neighbors, and ignore the path where it
     /// came from.
     async fn follow_up_to_neighbors(
         &self,
-        sla: &Sla,
-        from: NodeId,
-        accumulated_latency: Time,
+        life: &FunctionLife,
+        bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
-        todo!("Maintance to be done");
-        let mut requests = vec![];
+        let from = &bid_request.node_origin;
+        let accumulated_latency = &bid_request.accumulated_latency;
+        // Only the Clouds talk with all their neighbors, the others just
+        // pass the sla up to their parent
+        let targets = if self.is_cloud {
+            life.node_situation.get_neighbors()
+        } else {
+            life.node_situation.get_parent_id().into_iter().collect()
+        };
 
-        if std::env::var("IS_CLOUD").map(|x| x == "is_cloud").unwrap_or(false)
+        let mut requests = vec![];
+        for neighbor in targets.into_iter().filter(|neighbor| neighbor != from)
         {
-            for neighbor in self.node_situation.get_neighbors() {
-                if neighbor == from {
-                    continue;
-                }
+            let Some(latency) =
+                life.neighbor_monitor.get_latency_to(&neighbor).await
+            else {
+                warn!("Cannot get Latency of {}", neighbor);
+                continue;
+            };
+            let Some(NodeDescription { advertised_bandwidth, .. }) =
+                life.node_situation.get_fog_node_neighbor(&neighbor)
+            else {
+                warn!("Cannot get neighbor bandwidth of {}", neighbor);
+                continue;
+            };
 
-                requests.push((
-                    BidRequest {
-                        sla,
-                        node_origin: self.node_situation.get_my_id(),
-                        accumulated_latency,
-                    },
-                    neighbor,
-                ));
-            }
-        } else if let Some(parent) = self.node_situation.get_parent_id() {
             requests.push((
-                BidRequest {
-                    sla,
-                    node_origin: self.node_situation.get_my_id(),
-                    accumulated_latency,
-                },
-                parent,
+                accumulated_latency.accumulate(latency, advertised_bandwidth),
+                neighbor,
             ));
         }
 
-        let promises = requests.iter().map(|(request, neighbor)| {
-            self.node_query.request_neighbor_bid(request, neighbor.clone())
+        let promises = requests.into_iter().map(|(latency, neighbor)| {
+            life.follow_up(bid_request, neighbor, latency, 1)
         });
 
         Ok(BidProposals {
@@ -58,51 +72,60 @@ impl FunctionLife {
                 .collect(),
         })
     }
+}
 
-    pub async fn bid_on_new_function_and_transmit(
+#[async_trait::async_trait]
+impl PlacementStrategy for CloudOnlyV2Placement {
+    /// Pass the [Sla] up to the Cloud nodes, which bid and also pass it to
+    /// their neighbors, so that the Cloud nodes can compete together
+    async fn bid_on_new_function_and_transmit(
         &self,
-        sla: &Sla,
-        from: NodeId,
-        accumulated_latency: Time,
+        life: &FunctionLife,
+        bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
+        let sla = &bid_request.sla;
+        let from = &bid_request.node_origin;
+        let accumulated_latency = &bid_request.accumulated_latency;
+
+        if !self.is_cloud {
+            debug!("Node is not a Cloud, c.f. IS_CLOUD env var value");
+            return self
+                .follow_up_to_neighbors(life, bid_request)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to transmit the sla coming from {} to my \
+                         parent",
+                        from
+                    )
+                });
+        }
+
         let (result_bid, proposals) = join(
-            self.auction.bid_on(sla.clone(), accumulated_latency),
-            self.follow_up_to_neighbors(
-                sla,
-                from.clone(),
-                accumulated_latency,
-            ),
+            life.auction.bid_on(sla.clone(), accumulated_latency),
+            self.follow_up_to_neighbors(life, bid_request),
         )
         .await;
-        let my_id = self.node_situation.get_my_id();
+        let my_id = life.node_situation.get_my_id();
 
         let mut proposals = proposals.with_context(|| {
             format!(
                 "Failed to bid and transmit on the sla coming from {}",
-                from.clone()
+                from
             )
         })?;
 
         let result_bid = result_bid.context("Failed to bid on the sla")?;
 
-        if std::env::var("IS_CLOUD").map(|x| x == "is_cloud").unwrap_or(false)
-        {
-            if let Some((bid, bid_record)) = result_bid {
-                proposals.bids.push(BidProposal {
-                    node_id: my_id,
-                    id:      bid,
-                    bid:     bid_record.0.bid,
-                });
-            } else {
-                warn!("Bid unsatisfiable, passing on...");
-            }
+        if let Some((bid, bid_record)) = result_bid {
+            proposals.bids.push(BidProposal {
+                node_id:             my_id,
+                id:                  bid,
+                bid:                 bid_record.bid,
+                accumulated_latency: accumulated_latency.median,
+            });
         } else {
-            debug!(
-                "Node is not a Cloud, c.f. IS_CLOUD env var value: {}",
-                std::env::var("IS_CLOUD").unwrap_or(
-                    "<no env var IS_CLOUD have been found.>".to_string()
-                )
-            )
+            warn!("Bid unsatisfiable, passing on...");
         }
 
         Ok(proposals)
@@ -1,23 +1,25 @@
 use super::*;
-use model::domain::sla::Sla;
 use model::dto::node::NodeDescription;
-use model::view::auction::{
-    BidProposal, BidProposals, BidRequest, BidRequestOwned,
-};
+use model::view::auction::{BidProposal, BidProposals};
 use model::NodeId;
 use tracing::debug;
 use uom::fmt::DisplayStyle::Abbreviation;
 
-impl FunctionLife {
+#[derive(Debug)]
+pub struct EdgeFurthestPlacement;
+
+impl EdgeFurthestPlacement {
     /// Follow up the [Sla] to the neighbors, and ignore the path where it
     /// came from.
     async fn follow_up_to_neighbors<'a>(
         &'a self,
-        sla: &'a Sla,
-        from: &NodeId,
-        accumulated_latency: &AccumulatedLatency,
+        life: &'a FunctionLife,
+        bid_request: &'a BidRequestOwned,
     ) -> Result<BidProposals> {
-        let neighbors = self.node_situation.get_neighbors();
+        let sla = &bid_request.sla;
+        let from = &bid_request.node_origin;
+        let accumulated_latency = &bid_request.accumulated_latency;
+        let neighbors = life.node_situation.get_neighbors();
         let mut latencies: Vec<(NodeId, AccumulatedLatency)> =
             Vec::with_capacity(neighbors.len());
 
@@ -28,14 +30,14 @@ impl FunctionLife {
             }
 
             let Some(latency) =
-                self.neighbor_monitor.get_latency_to(&neighbor).await
+                life.neighbor_monitor.get_latency_to(&neighbor).await
             else {
                 warn!("Cannot get Latency of {}", neighbor);
                 continue;
             };
 
             let Some(NodeDescription { advertised_bandwidth, .. }) =
-                self.node_situation.get_fog_node_neighbor(&neighbor)
+                life.node_situation.get_fog_node_neighbor(&neighbor)
             else {
                 warn!("Cannot neighbor bandwidth of {}", neighbor);
                 continue;
@@ -45,7 +47,7 @@ impl FunctionLife {
                 accumulated_latency.accumulate(latency, advertised_bandwidth);
 
             let worse_lat =
-                self.compute_worse_latency(&latency, sla.input_max_size);
+                life.compute_worse_latency(&latency, sla.input_max_size);
 
             if worse_lat > sla.latency_max {
                 trace!(
@@ -70,15 +72,12 @@ impl FunctionLife {
         });
 
         for (neighbor, accumulated_latency) in latencies {
-            let Ok(bid) = self
-                .node_query
-                .request_neighbor_bid(
-                    &BidRequest {
-                        sla,
-                        node_origin: self.node_situation.get_my_id(),
-                        accumulated_latency: accumulated_latency.to_owned(),
-                    },
+            let Ok(bid) = life
+                .follow_up(
+                    bid_request,
                     neighbor.clone(),
+                    accumulated_latency,
+                    1,
                 )
                 .await
             else {
@@ -92,21 +91,24 @@ impl FunctionLife {
         info!("No candidate retained after filtering latencies");
         Ok(BidProposals { bids: vec![] })
     }
+}
 
+#[async_trait::async_trait]
+impl PlacementStrategy for EdgeFurthestPlacement {
     /// Here the operation will be sequential, first looking to place on a
     /// bottom node, or a child at least, and only then to consider
     /// itself as a candidate
-    pub async fn bid_on_new_function_and_transmit(
+    async fn bid_on_new_function_and_transmit(
         &self,
+        life: &FunctionLife,
         bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
         let sla = &bid_request.sla;
-        let from = &bid_request.node_origin;
         let accumulated_latency = &bid_request.accumulated_latency;
 
         trace!("Transmitting bid to other nodes...");
         let mut follow_up = self
-            .follow_up_to_neighbors(sla, from, accumulated_latency)
+            .follow_up_to_neighbors(life, bid_request)
             .await
             .context("Failed to follow up sla to my neighbors")?;
         let bid = follow_up.bids.pop();
@@ -117,13 +119,14 @@ impl FunctionLife {
             }
             None => {
                 if let Ok(Some((id, record))) =
-                    self.auction.bid_on(sla.clone(), accumulated_latency).await
+                    life.auction.bid_on(sla.clone(), accumulated_latency).await
                 {
                     info!("no bids are coming from any neighbors, bidded.");
                     vec![BidProposal {
-                        node_id: self.node_situation.get_my_id(),
+                        node_id: life.node_situation.get_my_id(),
                         id,
                         bid: record.bid,
+                        accumulated_latency: accumulated_latency.median,
                     }]
                 } else {
                     info!(
@@ -1,30 +1,27 @@
 use super::*;
 use anyhow::{anyhow, ensure};
-use model::domain::sla::Sla;
-use model::view::auction::{
-    BidProposal, BidProposals, BidRequest, BidRequestOwned,
-};
+use model::view::auction::{BidProposal, BidProposals};
 
-impl FunctionLife {
+#[derive(Debug)]
+pub struct EdgeWardPlacement;
+
+impl EdgeWardPlacement {
     /// Follow up the [Sla] to the neighbors, and ignore the path where it
     /// came from.
     async fn follow_up_to_parent<'a>(
         &'a self,
-        sla: &'a Sla,
-        accumulated_latency: AccumulatedLatency,
+        life: &'a FunctionLife,
+        bid_request: &'a BidRequestOwned,
     ) -> Result<BidProposals> {
-        let Some(parent) = self.node_situation.get_parent_id() else {
+        let Some(parent) = life.node_situation.get_parent_id() else {
             return Ok(BidProposals { bids: vec![] });
         };
-        let bid = self
-            .node_query
-            .request_neighbor_bid(
-                &BidRequest {
-                    sla,
-                    node_origin: self.node_situation.get_my_id(),
-                    accumulated_latency: accumulated_latency.to_owned(),
-                },
+        let bid = life
+            .follow_up(
+                bid_request,
                 parent,
+                bid_request.accumulated_latency.clone(),
+                1,
             )
             .await
             .context("Failed to request a bid from my parent")?;
@@ -36,29 +33,34 @@ impl FunctionLife {
 
         Ok(bid)
     }
+}
 
+#[async_trait::async_trait]
+impl PlacementStrategy for EdgeWardPlacement {
     /// Here the operation will be sequential, first looking to place on a
     /// bottom node, or a child at least, and only then to consider
     /// itself as a candidate
-    pub async fn bid_on_new_function_and_transmit(
+    async fn bid_on_new_function_and_transmit(
         &self,
+        life: &FunctionLife,
         bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
         let sla = &bid_request.sla;
         let accumulated_latency = &bid_request.accumulated_latency;
 
         let bid = if let Ok(Some((id, record))) =
-            self.auction.bid_on(sla.clone(), accumulated_latency).await
+            life.auction.bid_on(sla.clone(), accumulated_latency).await
         {
             BidProposal {
-                node_id: self.node_situation.get_my_id(),
+                node_id: life.node_situation.get_my_id(),
                 id,
                 bid: record.bid,
+                accumulated_latency: accumulated_latency.median,
             }
         } else {
             trace!("Transmitting bid to other node...");
             let mut follow_up = self
-                .follow_up_to_parent(sla, accumulated_latency.clone())
+                .follow_up_to_parent(life, bid_request)
                 .await
                 .context("Failed to follow up sla to my parent")?;
             follow_up.bids.pop().ok_or(anyhow!(
@@ -1,47 +1,48 @@
 use super::*;
-use anyhow::{anyhow, bail, ensure, Context, Result};
-use model::domain::sla::Sla;
-use model::view::auction::{BidProposal, BidProposals, BidRequest};
-use model::NodeId;
-use uom::si::f64::Time;
+use anyhow::{anyhow, bail, ensure};
+use model::dto::node::NodeDescription;
+use model::view::auction::{BidProposal, BidProposals};
 
-impl FunctionLife {
-    /// Follow up the [Sla] to the neighbors, and ignore the path where it
-    /// came from.
+#[derive(Debug)]
+pub struct EdgeWardV2Placement;
+
+impl EdgeWardV2Placement {
+    /// Follow up the [Sla] to the parent, if the latency allows it
     async fn follow_up_to_parent<'a>(
         &'a self,
-        sla: &'a Sla,
-        accumulated_latency: Time,
+        life: &'a FunctionLife,
+        bid_request: &'a BidRequestOwned,
     ) -> Result<BidProposals> {
-        let Some(parent) = self.node_situation.get_parent_id() else {
+        let sla = &bid_request.sla;
+        let accumulated_latency = &bid_request.accumulated_latency;
+        let Some(parent) = life.node_situation.get_parent_id() else {
             return Ok(BidProposals { bids: vec![] });
         };
 
-        let latency_outbound = self
+        let latency = life
             .neighbor_monitor
             .get_latency_to(&parent)
             .await
-            .map(|x| x.median)
             .ok_or_else(|| anyhow!("Cannot get Latency of {}", parent))?;
+        let NodeDescription { advertised_bandwidth, .. } = life
+            .node_situation
+            .get_fog_node_neighbor(&parent)
+            .ok_or_else(|| anyhow!("Cannot get bandwidth of {}", parent))?;
+
+        let accumulated_latency =
+            accumulated_latency.accumulate(latency, advertised_bandwidth);
 
-        if latency_outbound + accumulated_latency > sla.latency_max {
+        if life.compute_worse_latency(&accumulated_latency, sla.input_max_size)
+            > sla.latency_max
+        {
             bail!(
                 "Accumulated latency + latency to parent is expected to be \
                  over the sla's max latency."
             )
         };
 
-        let bid = self
-            .node_query
-            .request_neighbor_bid(
-                &BidRequest {
-                    sla,
-                    node_origin: self.node_situation.get_my_id(),
-                    accumulated_latency: accumulated_latency.to_owned()
-                        + latency_outbound,
-                },
-                parent,
-            )
+        let bid = life
+            .follow_up(bid_request, parent, accumulated_latency, 1)
             .await
             .context("Failed to request a bid from my parent")?;
 
@@ -52,29 +53,34 @@ impl FunctionLife {
 
         Ok(bid)
     }
+}
 
-    /// Here the operation will be sequential, first looking to place on a
-    /// bottom node, or a child at least, and only then to consider
-    /// itself as a candidate
-    pub async fn bid_on_new_function_and_transmit(
+#[async_trait::async_trait]
+impl PlacementStrategy for EdgeWardV2Placement {
+    /// Here the operation will be sequential, first looking to place on
+    /// itself, and only then to pass the sla to its parent if the latency
+    /// is expected to be respected
+    async fn bid_on_new_function_and_transmit(
         &self,
-        sla: &Sla,
-        _from: NodeId,
-        accumulated_latency: Time,
+        life: &FunctionLife,
+        bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
-        todo!("Maintance to be done");
+        let sla = &bid_request.sla;
+        let accumulated_latency = &bid_request.accumulated_latency;
+
         let bid = if let Ok(Some((id, record))) =
-            self.auction.bid_on(sla.clone(), accumulated_latency).await
+            life.auction.bid_on(sla.clone(), accumulated_latency).await
         {
             BidProposal {
-                node_id: self.node_situation.get_my_id(),
+                node_id: life.node_situation.get_my_id(),
                 id,
-                bid: record.0.bid,
+                bid: record.bid,
+                accumulated_latency: accumulated_latency.median,
             }
         } else {
             trace!("Transmitting bid to other node...");
             let mut follow_up = self
-                .follow_up_to_parent(sla, accumulated_latency)
+                .follow_up_to_parent(life, bid_request)
                 .await
                 .context("Failed to follow up sla to my parent")?;
             follow_up.bids.pop().ok_or(anyhow!(
@@ -1,42 +1,43 @@
 use super::*;
 use anyhow::anyhow;
-use model::domain::sla::Sla;
-use model::view::auction::{
-    BidProposal, BidProposals, BidRequest, BidRequestOwned,
-};
+use model::dto::node::NodeDescription;
+use model::view::auction::{BidProposal, BidProposals};
 use tracing::debug;
 
-impl FunctionLife {
-    /// Follow up the [Sla] to the neighbors, and ignore the path where it
-    /// came from.
+#[derive(Debug)]
+pub struct EdgeWardV3Placement;
+
+impl EdgeWardV3Placement {
+    /// Follow up the [Sla] to the parent, if the latency allows it
     async fn follow_up_to_parent<'a>(
         &'a self,
-        sla: &'a Sla,
-        accumulated_latency: &AccumulatedLatency,
+        life: &'a FunctionLife,
+        bid_request: &'a BidRequestOwned,
     ) -> Result<BidProposals> {
-        let Some(parent) = self.node_situation.get_parent_id() else {
+        let sla = &bid_request.sla;
+        let accumulated_latency = &bid_request.accumulated_latency;
+        let Some(parent) = life.node_situation.get_parent_id() else {
             return Ok(BidProposals { bids: vec![] });
         };
 
-        let latency = self
+        let latency = life
             .neighbor_monitor
             .get_latency_to(&parent)
             .await
             .ok_or_else(|| anyhow!("Cannot get Latency of {}", parent))?;
+        let NodeDescription { advertised_bandwidth, .. } = life
+            .node_situation
+            .get_fog_node_neighbor(&parent)
+            .ok_or_else(|| anyhow!("Cannot get bandwidth of {}", parent))?;
 
-        let accumulated_latency = accumulated_latency.accumulate(latency);
+        let accumulated_latency =
+            accumulated_latency.accumulate(latency, advertised_bandwidth);
 
-        if accumulated_latency.median < sla.latency_max {
-            let bid = self
-                .node_query
-                .request_neighbor_bid(
-                    &BidRequest {
-                        sla,
-                        node_origin: self.node_situation.get_my_id(),
-                        accumulated_latency,
-                    },
-                    parent,
-                )
+        if life.compute_worse_latency(&accumulated_latency, sla.input_max_size)
+            < sla.latency_max
+        {
+            let bid = life
+                .follow_up(bid_request, parent, accumulated_latency, 1)
                 .await
                 .context("Failed to request a bid from my parent")?;
             if !bid.bids.is_empty() {
@@ -46,21 +47,24 @@ impl FunctionLife {
 
         Ok(BidProposals { bids: vec![] })
     }
+}
 
-    /// Here the operation will be sequential, first looking to place on a
-    /// bottom node, or a child at least, and only then to consider
-    /// itself as a candidate
-    pub async fn bid_on_new_function_and_transmit(
+#[async_trait::async_trait]
+impl PlacementStrategy for EdgeWardV3Placement {
+    /// Here the operation will be sequential, first looking to place on the
+    /// furthest node up the tree that respects the latency, and only then to
+    /// consider itself as a candidate
+    async fn bid_on_new_function_and_transmit(
         &self,
+        life: &FunctionLife,
         bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
-        todo!("Maintance to be done");
         let sla = &bid_request.sla;
         let accumulated_latency = &bid_request.accumulated_latency;
 
         trace!("Transmitting bid to other node...");
         let mut follow_up = self
-            .follow_up_to_parent(sla, accumulated_latency)
+            .follow_up_to_parent(life, bid_request)
             .await
             .context("Failed to follow up sla to my parent")?;
         let bid = follow_up.bids.pop();
@@ -70,16 +74,15 @@ impl FunctionLife {
                 vec![bid]
             }
             None => {
-                if let Ok(Some((id, record))) = self
-                    .auction
-                    .bid_on(sla.clone(), &accumulated_latency)
-                    .await
+                if let Ok(Some((id, record))) =
+                    life.auction.bid_on(sla.clone(), accumulated_latency).await
                 {
                     info!("no bids are coming from above, bidded.");
                     vec![BidProposal {
-                        node_id: self.node_situation.get_my_id(),
+                        node_id: life.node_situation.get_my_id(),
                         id,
                         bid: record.bid,
+                        accumulated_latency: accumulated_latency.median,
                     }]
                 } else {
                     info!(
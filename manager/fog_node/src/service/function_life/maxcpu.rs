@@ -1,35 +1,44 @@
 use super::*;
 use futures::future::{join, try_join_all};
-use model::domain::sla::Sla;
-use model::view::auction::{
-    BidProposal, BidProposals, BidRequest, BidRequestOwned,
-};
+use model::dto::node::NodeDescription;
+use model::view::auction::{BidProposal, BidProposals};
 use model::NodeId;
 use tracing::debug;
 use uom::fmt::DisplayStyle::Abbreviation;
 
-impl FunctionLife {
+#[derive(Debug)]
+pub struct MaxCpuPlacement;
+
+impl MaxCpuPlacement {
     async fn follow_up_to_single_neighbor(
         &self,
+        life: &FunctionLife,
         neighbor: &NodeId,
-        sla: &Sla,
-        from: &NodeId,
-        accumulated_latency: &AccumulatedLatency,
+        bid_request: &BidRequestOwned,
     ) -> Result<Option<BidProposals>> {
-        if neighbor == from {
+        let sla = &bid_request.sla;
+        let accumulated_latency = &bid_request.accumulated_latency;
+        if neighbor == &bid_request.node_origin {
             return Ok(None);
         }
         let Some(latency) =
-            self.neighbor_monitor.get_latency_to(neighbor).await
+            life.neighbor_monitor.get_latency_to(neighbor).await
         else {
             warn!("Cannot get Latency of {}", neighbor);
             return Ok(None);
         };
 
+        let Some(NodeDescription { advertised_bandwidth, .. }) =
+            life.node_situation.get_fog_node_neighbor(neighbor)
+        else {
+            warn!("Cannot neighbor bandwidth of {}", neighbor);
+            return Ok(None);
+        };
+
         let accumulated_latency_to_next_node =
-            accumulated_latency.accumulate(latency);
+            accumulated_latency.accumulate(latency, advertised_bandwidth);
 
-        let worse_lat = self.compute_worse_latency(
+        let worse_lat = life.compute_worse_latency(
             &accumulated_latency_to_next_node,
             sla.input_max_size,
         );
@@ -52,15 +61,13 @@ impl FunctionLife {
             );
             return Ok(None);
         }
-        let request = BidRequest {
-            sla,
-            node_origin: self.node_situation.get_my_id(),
-            accumulated_latency: accumulated_latency_to_next_node,
-        };
-
-        let bid = self
-            .node_query
-            .request_neighbor_bid(&request, neighbor.clone())
+        let bid = life
+            .follow_up(
+                bid_request,
+                neighbor.clone(),
+                accumulated_latency_to_next_node,
+                1,
+            )
             .await?;
         Ok(Some(bid))
     }
@@ -69,18 +76,12 @@ impl FunctionLife {
     /// came from.
     async fn follow_up_to_neighbors(
         &self,
-        sla: &Sla,
-        from: NodeId,
-        accumulated_latency: &AccumulatedLatency,
+        life: &FunctionLife,
+        bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
-        let neighbors = self.node_situation.get_neighbors();
+        let neighbors = life.node_situation.get_neighbors();
         let promises = neighbors.iter().map(|neighbor| {
-            self.follow_up_to_single_neighbor(
-                neighbor,
-                sla,
-                &from,
-                accumulated_latency,
-            )
+            self.follow_up_to_single_neighbor(life, neighbor, bid_request)
         });
 
         Ok(BidProposals {
@@ -93,9 +94,13 @@ impl FunctionLife {
                 .collect(),
         })
     }
+}
 
-    pub async fn bid_on_new_function_and_transmit(
+#[async_trait::async_trait]
+impl PlacementStrategy for MaxCpuPlacement {
+    async fn bid_on_new_function_and_transmit(
         &self,
+        life: &FunctionLife,
         bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
         let sla = &bid_request.sla;
@@ -103,15 +108,11 @@ impl FunctionLife {
         let accumulated_latency = &bid_request.accumulated_latency;
 
         let (result_bid, proposals) = join(
-            self.auction.bid_on(sla.clone(), accumulated_latency),
-            self.follow_up_to_neighbors(
-                sla,
-                from.clone(),
-                accumulated_latency,
-            ),
+            life.auction.bid_on(sla.clone(), accumulated_latency),
+            self.follow_up_to_neighbors(life, bid_request),
         )
         .await;
-        let my_id = self.node_situation.get_my_id();
+        let my_id = life.node_situation.get_my_id();
 
         let mut proposals = proposals.with_context(|| {
             format!(
@@ -124,9 +125,10 @@ impl FunctionLife {
 
         if let Some((bid, bid_record)) = result_bid {
             proposals.bids.push(BidProposal {
-                node_id: my_id,
-                id:      bid,
-                bid:     bid_record.bid,
+                node_id:             my_id,
+                id:                  bid,
+                bid:                 bid_record.bid,
+                accumulated_latency: accumulated_latency.median,
             });
         } else {
             warn!("Bid unsatisfiable, passing on...");
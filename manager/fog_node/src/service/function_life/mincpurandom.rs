@@ -1,37 +1,38 @@
 use super::*;
 use futures::future::try_join_all;
-use model::domain::sla::Sla;
 use model::dto::node::NodeDescription;
-use model::view::auction::{
-    BidProposal, BidProposals, BidRequest, BidRequestOwned,
-};
+use model::view::auction::{BidProposal, BidProposals};
 use model::NodeId;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use tracing::debug;
 use uom::fmt::DisplayStyle::Abbreviation;
 
-impl FunctionLife {
+#[derive(Debug)]
+pub struct MinCpuRandomPlacement;
+
+impl MinCpuRandomPlacement {
     async fn follow_up_to_single_neighbor(
         &self,
+        life: &FunctionLife,
         neighbor: &NodeId,
-        sla: &Sla,
-        from: &NodeId,
-        accumulated_latency: &AccumulatedLatency,
+        bid_request: &BidRequestOwned,
         nb_propositions_required: usize,
     ) -> Result<Option<BidProposals>> {
-        if neighbor == from {
+        let sla = &bid_request.sla;
+        let accumulated_latency = &bid_request.accumulated_latency;
+        if neighbor == &bid_request.node_origin {
             return Ok(None);
         }
         let Some(latency) =
-            self.neighbor_monitor.get_latency_to(neighbor).await
+            life.neighbor_monitor.get_latency_to(neighbor).await
         else {
             warn!("Cannot get Latency of {}", neighbor);
             return Ok(None);
         };
 
         let Some(NodeDescription { advertised_bandwidth, .. }) =
-            self.node_situation.get_fog_node_neighbor(neighbor)
+            life.node_situation.get_fog_node_neighbor(neighbor)
         else {
             warn!("Cannot neighbor bandwidth of {}", neighbor);
             return Ok(None);
@@ -40,7 +41,7 @@ impl FunctionLife {
         let accumulated_latency_to_next_node =
             accumulated_latency.accumulate(latency, advertised_bandwidth);
 
-        let worse_lat = self.compute_worse_latency(
+        let worse_lat = life.compute_worse_latency(
             &accumulated_latency_to_next_node,
             sla.input_max_size,
         );
@@ -62,16 +63,13 @@ impl FunctionLife {
             );
             return Ok(None);
         }
-        let request = BidRequest {
-            sla,
-            node_origin: self.node_situation.get_my_id(),
-            accumulated_latency: accumulated_latency_to_next_node,
-            nb_propositions_required,
-        };
-
-        let bid = self
-            .node_query
-            .request_neighbor_bid(&request, neighbor.clone())
+        let bid = life
+            .follow_up(
+                bid_request,
+                neighbor.clone(),
+                accumulated_latency_to_next_node,
+                nb_propositions_required,
+            )
             .await?;
         Ok(Some(bid))
     }
@@ -80,12 +78,11 @@ impl FunctionLife {
     /// came from.
     async fn follow_up_to_neighbors(
         &self,
-        sla: &Sla,
-        from: NodeId,
-        accumulated_latency: &AccumulatedLatency,
+        life: &FunctionLife,
+        bid_request: &BidRequestOwned,
         number_neighbors_to_follow_to: usize,
     ) -> Result<BidProposals> {
-        let mut neighbors = self.node_situation.get_neighbors();
+        let mut neighbors = life.node_situation.get_neighbors();
         neighbors.shuffle(&mut thread_rng());
 
         let nb_propositions_required =
@@ -101,10 +98,9 @@ impl FunctionLife {
             .take(number_neighbors_to_follow_to)
             .map(|neighbor| {
                 self.follow_up_to_single_neighbor(
+                    life,
                     neighbor,
-                    sla,
-                    &from,
-                    accumulated_latency,
+                    bid_request,
                     nb_propositions_required,
                 )
             });
@@ -119,9 +115,13 @@ impl FunctionLife {
                 .collect(),
         })
     }
+}
 
-    pub async fn bid_on_new_function_and_transmit(
+#[async_trait::async_trait]
+impl PlacementStrategy for MinCpuRandomPlacement {
+    async fn bid_on_new_function_and_transmit(
         &self,
+        life: &FunctionLife,
         bid_request: &BidRequestOwned,
     ) -> Result<BidProposals> {
         let sla = &bid_request.sla;
@@ -134,29 +134,31 @@ impl FunctionLife {
             return Ok(BidProposals { bids: vec![] });
         }
 
-        let my_id = self.node_situation.get_my_id();
-        let my_proposal =
-            match self.auction.bid_on(sla.clone(), &accumulated_latency).await
-            {
-                Ok(Some((bid_id, bid_record))) => Some(BidProposal {
-                    node_id: my_id,
-                    id:      bid_id,
-                    bid:     bid_record.bid,
-                }),
-                _ => {
-                    warn!("Bid unsatisfiable, passing on...");
-                    None
-                }
-            };
+        let my_id = life.node_situation.get_my_id();
+        let my_proposal = match life
+            .auction
+            .bid_on(sla.clone(), accumulated_latency)
+            .await
+        {
+            Ok(Some((bid_id, bid_record))) => Some(BidProposal {
+                node_id:             my_id,
+                id:                  bid_id,
+                bid:                 bid_record.bid,
+                accumulated_latency: accumulated_latency.median,
+            }),
+            _ => {
+                warn!("Bid unsatisfiable, passing on...");
+                None
+            }
+        };
 
         if my_proposal.is_some() {
             nb_propositions_required -= 1;
         }
 
         let proposals = self.follow_up_to_neighbors(
-            sla,
-            from.clone(),
-            accumulated_latency,
+            life,
+            bid_request,
             nb_propositions_required,
         );
         let mut proposals = proposals.await.with_context(|| {
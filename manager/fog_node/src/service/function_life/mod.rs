@@ -1,82 +1,170 @@
 use std::sync::Arc;
 
+use crate::monitoring::NeighborBidTimeout;
 use crate::repository::cron::{Cron, Task, UnprovisionFunction};
 use crate::repository::faas::FunctionTimeout;
 use crate::repository::function_tracking::FunctionTracking;
 use crate::service::auction::Auction;
 use crate::service::neighbor_monitor::NeighborMonitor;
-use crate::{NodeQuery, NodeSituation, FUNCTION_LIVE_TIMEOUT_MSECS};
-use anyhow::{Context, Result};
+use crate::{
+    NodeQuery, NodeSituation, BID_TIMEOUT_LATENCY_FACTOR,
+    BID_TIMEOUT_MIN_MSECS, FUNCTION_LIVE_TIMEOUT_MSECS,
+};
+use anyhow::{bail, ensure, Context, Result};
 use backoff::exponential::{ExponentialBackoff, ExponentialBackoffBuilder};
 use backoff::SystemClock;
+use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
 use helper::env_load;
-use model::view::auction::AccumulatedLatency;
-use model::SlaId;
+use helper::http_error::{CodeContext, ErrorCode};
+use helper::monitoring::MetricsExporter;
+use helper::uom_helper::cpu_ratio::millicpu;
+use model::domain::sla::Sla;
+use model::dto::node::PlacementStrategyKind;
+use model::view::auction::{
+    AccumulatedLatency, BidProposals, BidRequestOwned,
+};
+use model::view::function::FunctionStatus;
+use model::view::node::CapacitySummary;
+use model::view::sla::SlaExtension;
+use model::{BidRequestId, NodeId, SlaId};
 use num_traits::ToPrimitive;
-use tracing::{error, info, instrument, trace, warn};
+use nutype::nutype;
+use std::fmt::Debug;
+use std::time::Instant;
+use tracing::{debug, error, info, instrument, trace, warn};
 use uom::si::f64::Time;
 use uom::si::information::byte;
-use uom::si::information_rate::byte_per_second;
 use uom::si::ratio::ratio;
-use uom::si::rational64::Information;
+use uom::si::rational64::{Information, Ratio};
 use uom::si::time::{millisecond, second};
 
 pub struct FunctionLife {
-    function: Arc<Function>,
-    auction: Arc<Auction>,
-    node_situation: Arc<NodeSituation>,
-    #[allow(dead_code)]
-    neighbor_monitor: Arc<NeighborMonitor>,
-    node_query: Arc<NodeQuery>,
-    #[allow(dead_code)]
-    function_tracking: Arc<FunctionTracking>,
-    cron: Arc<Cron>,
+    function:              Arc<Function>,
+    auction:               Arc<Auction>,
+    node_situation:        Arc<NodeSituation>,
+    neighbor_monitor:      Arc<NeighborMonitor>,
+    node_query:            Arc<NodeQuery>,
+    function_tracking:     Arc<FunctionTracking>,
+    cron:                  Arc<Cron>,
     function_live_timeout: Arc<std::time::Duration>,
+    placement:             Box<dyn PlacementStrategy>,
+    /// The calls for bids already answered, with when they arrived
+    seen_bid_requests:     DashMap<BidRequestId, Instant>,
+    metrics:               Arc<MetricsExporter>,
+    bid_timeout_factor:    f64,
+    bid_timeout_min:       std::time::Duration,
+    /// What the subtrees of the children can host, with when it arrived
+    child_capacities:      DashMap<NodeId, (CapacitySummary, Instant)>,
 }
 
-#[cfg(feature = "auction")]
-mod auction_placement;
+/// Share of the latency budget left to the sla that a neighbor gets to
+/// answer a call for bids
+#[nutype(derive(Clone, Debug), validate(finite, greater = 0.0))]
+pub struct BidTimeoutLatencyFactor(f64);
 
-#[cfg(feature = "maxcpu")]
-mod maxcpu;
+/// Least time a neighbor gets to answer a call for bids, in milliseconds
+#[nutype(derive(Clone, Debug), validate(greater = 0))]
+pub struct BidTimeoutMin(u64);
 
-#[cfg(feature = "mincpurandom")]
-mod mincpurandom;
+/// How long a call for bids is remembered to drop its copies; longer than
+/// any auction lasts
+const SEEN_BID_REQUESTS_TTL: std::time::Duration =
+    std::time::Duration::from_secs(300);
 
-// #[cfg(feature = "cloud_only")]
-// mod cloud_only_placement;
-// #[cfg(feature = "cloud_only")]
-// pub use cloud_only_placement::*;
+/// Age after which the capacity summary of a child is ignored, a few times
+/// the period they are pushed at
+const CAPACITY_SUMMARY_TTL: std::time::Duration =
+    std::time::Duration::from_secs(60);
 
-// #[cfg(feature = "cloud_only_v2")]
-// mod cloud_only_placement_v2;
-// #[cfg(feature = "cloud_only_v2")]
-// pub use cloud_only_placement_v2::*;
+/// Placement algorithm answering the bid requests: it may bid on the [Sla]
+/// itself and forward the request to its neighbors, as it sees fit.
+#[async_trait::async_trait]
+pub trait PlacementStrategy: Debug + Send + Sync {
+    async fn bid_on_new_function_and_transmit(
+        &self,
+        function_life: &FunctionLife,
+        bid_request: &BidRequestOwned,
+    ) -> Result<BidProposals>;
+}
 
-#[cfg(feature = "edge_first")]
+mod auction_placement;
+mod cloud_only_placement;
+mod cloud_only_placement_v2;
 mod edge_first_placement;
-
-#[cfg(feature = "edge_furthest")]
 mod edge_furthest_placement;
-
-#[cfg(feature = "edge_ward")]
 mod edge_ward_placement;
-
-#[cfg(feature = "edge_ward_v2")]
 mod edge_ward_placement_v2;
-
-#[cfg(feature = "edge_ward_v3")]
 mod edge_ward_placement_v3;
-use super::function::Function;
+mod maxcpu;
+mod mincpurandom;
+
+pub use auction_placement::AuctionPlacement;
+pub use cloud_only_placement::CloudOnlyPlacement;
+pub use cloud_only_placement_v2::CloudOnlyV2Placement;
+pub use edge_first_placement::EdgeFirstPlacement;
+pub use edge_furthest_placement::EdgeFurthestPlacement;
+pub use edge_ward_placement::EdgeWardPlacement;
+pub use edge_ward_placement_v2::EdgeWardV2Placement;
+pub use edge_ward_placement_v3::EdgeWardV3Placement;
+pub use maxcpu::MaxCpuPlacement;
+pub use mincpurandom::MinCpuRandomPlacement;
+
+use super::function::{satisfiability_check, Function};
 #[allow(dead_code)]
 const DEFAULT_MTU: f64 = 1500.0;
 // MSS
-const DEFAULT_TCP_MSS: f64 = 1460.0; // In terms of number of MSS
 #[allow(dead_code)]
+const DEFAULT_TCP_MSS: f64 = 1460.0; // In terms of number of MSS
 #[allow(dead_code)]
 const TCP_TIMEOUT_SEC: f64 = 0.020;
 
+/// Build the placement algorithm described in the node configuration
+pub fn placement_from_kind(
+    kind: PlacementStrategyKind,
+    node_situation: &NodeSituation,
+) -> Box<dyn PlacementStrategy> {
+    match kind {
+        PlacementStrategyKind::Auction => Box::new(AuctionPlacement),
+        PlacementStrategyKind::EdgeFirst => Box::new(EdgeFirstPlacement),
+        PlacementStrategyKind::EdgeFurthest => Box::new(EdgeFurthestPlacement),
+        PlacementStrategyKind::EdgeWard => Box::new(EdgeWardPlacement),
+        PlacementStrategyKind::MaxCpu => Box::new(MaxCpuPlacement),
+        PlacementStrategyKind::MinCpuRandom => Box::new(MinCpuRandomPlacement),
+        PlacementStrategyKind::CloudOnly => Box::new(CloudOnlyPlacement),
+        PlacementStrategyKind::CloudOnlyV2 => {
+            Box::new(CloudOnlyV2Placement::new(node_situation.is_market()))
+        }
+        PlacementStrategyKind::EdgeWardV2 => Box::new(EdgeWardV2Placement),
+        PlacementStrategyKind::EdgeWardV3 => Box::new(EdgeWardV3Placement),
+    }
+}
+
+/// Wait for the provisioned function to turn live, finishing it when it
+/// does not in time
+async fn wait_until_live(
+    function: &Function,
+    backoff: ExponentialBackoff<SystemClock>,
+    id: SlaId,
+) -> Result<()> {
+    let res = backoff::future::retry(backoff, || async {
+        trace!("Checking if function is alive");
+        function.check_and_set_function_is_live(id.clone()).await?;
+        Ok(())
+    })
+    .await;
+
+    if res.is_err() {
+        warn!("Failed to check that the function is alive");
+        let function = function.lock().await?;
+        function.finish_function(id).await?;
+    }
+    res
+}
+
 impl FunctionLife {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         function: Arc<Function>,
         auction: Arc<Auction>,
@@ -85,47 +173,11 @@ impl FunctionLife {
         node_query: Arc<NodeQuery>,
         function_tracking: Arc<FunctionTracking>,
         cron: Arc<Cron>,
+        metrics: Arc<MetricsExporter>,
     ) -> Result<Self> {
-        #[cfg(feature = "edge_first")]
-        {
-            info!("Using edge-first placement");
-        }
-        #[cfg(feature = "edge_furthest")]
-        {
-            info!("Using edge furthest placement");
-        }
-        #[cfg(feature = "cloud_only")]
-        {
-            info!("Using cloud-only placement");
-        }
-        #[cfg(feature = "cloud_only_v2")]
-        {
-            info!("Using cloud-only v2 placement");
-        }
-        #[cfg(feature = "edge_ward")]
-        {
-            info!("Using edge-ward placement");
-        }
-        #[cfg(feature = "edge_ward_v2")]
-        {
-            info!("Using edge-ward v2 placement");
-        }
-        #[cfg(feature = "edge_ward_v3")]
-        {
-            info!("Using edge-ward v3 placement");
-        }
-        #[cfg(feature = "auction")]
-        {
-            info!("Using auction placement");
-        }
-        #[cfg(feature = "maxcpu")]
-        {
-            info!("Using maxcpu placement");
-        }
-        #[cfg(feature = "mincpurandom")]
-        {
-            info!("Using mincpurandom placement");
-        }
+        let placement_kind = node_situation.get_placement_strategy();
+        info!("Using {} placement", placement_kind);
+        let placement = placement_from_kind(placement_kind, &node_situation);
 
         let function_live_timeout =
             env_load!(FunctionTimeout, FUNCTION_LIVE_TIMEOUT_MSECS, u64);
@@ -133,6 +185,15 @@ impl FunctionLife {
             Arc::new(std::time::Duration::from_millis(
                 function_live_timeout.into_inner(),
             ));
+        let bid_timeout_factor = env_load!(
+            BidTimeoutLatencyFactor,
+            BID_TIMEOUT_LATENCY_FACTOR,
+            f64
+        )
+        .into_inner();
+        let bid_timeout_min = std::time::Duration::from_millis(
+            env_load!(BidTimeoutMin, BID_TIMEOUT_MIN_MSECS, u64).into_inner(),
+        );
         Ok(Self {
             function,
             auction,
@@ -142,17 +203,445 @@ impl FunctionLife {
             function_tracking,
             cron,
             function_live_timeout,
+            placement,
+            seen_bid_requests: DashMap::new(),
+            metrics,
+            bid_timeout_factor,
+            bid_timeout_min,
+            child_capacities: DashMap::new(),
+        })
+    }
+
+    /// Bid on the [Sla] and/or forward it to the neighbors, following the
+    /// configured [PlacementStrategy]. The copies of a request that already
+    /// reached this node by another path get no bid.
+    pub async fn bid_on_new_function_and_transmit(
+        &self,
+        bid_request: &BidRequestOwned,
+    ) -> Result<BidProposals> {
+        if !self.first_time_seen(bid_request) {
+            debug!(
+                "Dropping the copy of bid request {} for sla {}",
+                bid_request.request_id, bid_request.sla.id
+            );
+            return Ok(BidProposals { bids: vec![] });
+        }
+        self.placement
+            .bid_on_new_function_and_transmit(self, bid_request)
+            .await
+    }
+
+    /// Record the request, and tell whether it is the first time this node
+    /// sees it
+    fn first_time_seen(&self, bid_request: &BidRequestOwned) -> bool {
+        let now = Instant::now();
+        self.seen_bid_requests
+            .retain(|_, seen_at| now - *seen_at < SEEN_BID_REQUESTS_TTL);
+        if bid_request.visited.contains(&self.node_situation.get_my_id()) {
+            return false;
+        }
+        match self.seen_bid_requests.entry(bid_request.request_id.clone()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+
+    /// Time a neighbor gets to answer a call for bids: a share of the
+    /// latency budget the sla has left once there, so that the nodes deeper
+    /// in the fog give up before their parents do
+    pub(crate) fn bid_timeout(
+        &self,
+        sla: &Sla,
+        accumulated_latency: &AccumulatedLatency,
+    ) -> std::time::Duration {
+        let remaining = (sla.latency_max - accumulated_latency.median)
+            .get::<second>()
+            .max(0.0);
+        let timeout = std::time::Duration::try_from_secs_f64(
+            remaining * self.bid_timeout_factor,
+        )
+        .unwrap_or(std::time::Duration::MAX);
+        timeout.max(self.bid_timeout_min)
+    }
+
+    /// Keep what the subtree of a child can host, until it sends a newer
+    /// summary
+    pub fn update_child_capacity(
+        &self,
+        summary: CapacitySummary,
+    ) -> Result<()> {
+        trace!("Updating the capacity of the subtree of {}", summary.node_id);
+        if !self.node_situation.is_child(&summary.node_id) {
+            bail!(ErrorCode::Conflict.error(format!(
+                "{} is not a child of this node",
+                summary.node_id
+            )));
+        }
+        self.child_capacities
+            .insert(summary.node_id.clone(), (summary, Instant::now()));
+        Ok(())
+    }
+
+    /// The summaries of the current children that are recent enough
+    fn get_child_capacities(&self) -> Vec<CapacitySummary> {
+        let now = Instant::now();
+        self.child_capacities.retain(|id, (_, received_at)| {
+            now - *received_at < CAPACITY_SUMMARY_TTL
+                && self.node_situation.is_child(id)
+        });
+        self.child_capacities
+            .iter()
+            .map(|entry| entry.value().0.clone())
+            .collect()
+    }
+
+    /// What this node and its subtree can host at best, from the free
+    /// resources of this node and the summaries of its children
+    pub async fn get_capacity_summary(&self) -> Result<CapacitySummary> {
+        let (mut max_free_memory, mut max_free_cpu) = self
+            .auction
+            .get_max_free_resources()
+            .await
+            .context("Failed to get the free resources of this node")?;
+        let no_latency = Time::new::<second>(0.0);
+        let has_free_resources = |memory: &Information, cpu: &Ratio| {
+            *memory > Information::new::<byte>(num_rational::Ratio::new(0, 1))
+                && *cpu
+                    > Ratio::new::<millicpu>(num_rational::Ratio::new(0, 1))
+        };
+        let mut min_latency =
+            if has_free_resources(&max_free_memory, &max_free_cpu) {
+                Some(no_latency)
+            } else {
+                None
+            };
+
+        for child in self.get_child_capacities() {
+            if child.max_free_memory > max_free_memory {
+                max_free_memory = child.max_free_memory;
+            }
+            if child.max_free_cpu > max_free_cpu {
+                max_free_cpu = child.max_free_cpu;
+            }
+            if !has_free_resources(&child.max_free_memory, &child.max_free_cpu)
+            {
+                continue;
+            }
+            // An unknown latency may be as low as nothing
+            let latency_to_child = self
+                .neighbor_monitor
+                .get_latency_to(&child.node_id)
+                .await
+                .map_or(no_latency, |latency| latency.median);
+            let latency = latency_to_child + child.min_latency;
+            if min_latency.map_or(true, |min| latency < min) {
+                min_latency = Some(latency);
+            }
+        }
+
+        Ok(CapacitySummary {
+            node_id: self.node_situation.get_my_id(),
+            max_free_cpu,
+            max_free_memory,
+            min_latency: min_latency.unwrap_or(no_latency),
         })
     }
 
+    /// Tell the parent what this node and its subtree can host; the node
+    /// connected to the market has no parent to tell
+    pub async fn push_capacity_summary(&self) -> Result<()> {
+        if self.node_situation.is_market() {
+            return Ok(());
+        }
+        let summary = self.get_capacity_summary().await?;
+        self.node_query.capacity_to_parent(&summary).await
+    }
+
+    /// Whether the summary of the subtree of the neighbor tells it cannot
+    /// host the sla, be it by lack of resources or by being too far away.
+    /// Without a recent summary, the subtree is asked anyway.
+    fn cannot_host(
+        &self,
+        sla: &Sla,
+        neighbor: &NodeId,
+        accumulated_latency: &AccumulatedLatency,
+    ) -> bool {
+        let Some(summary) = self
+            .child_capacities
+            .get(neighbor)
+            .filter(|entry| entry.value().1.elapsed() < CAPACITY_SUMMARY_TTL)
+            .map(|entry| entry.value().0.clone())
+        else {
+            return false;
+        };
+        let zero_memory =
+            Information::new::<byte>(num_rational::Ratio::new(0, 1));
+        let zero_cpu = Ratio::new::<millicpu>(num_rational::Ratio::new(0, 1));
+        !satisfiability_check(
+            &zero_memory,
+            &zero_cpu,
+            &summary.max_free_memory,
+            &summary.max_free_cpu,
+            sla,
+        ) || accumulated_latency.median + summary.min_latency > sla.latency_max
+    }
+
+    /// Forward the request to a neighbor. Nothing is asked to the nodes the
+    /// request already went through, nor once its hop budget is spent, nor
+    /// to the children whose subtree cannot host the sla. A neighbor
+    /// answering late is left out, along with its subtree.
+    pub async fn follow_up(
+        &self,
+        bid_request: &BidRequestOwned,
+        neighbor: NodeId,
+        accumulated_latency: AccumulatedLatency,
+        nb_propositions_required: usize,
+    ) -> Result<BidProposals> {
+        if bid_request.visited.contains(&neighbor) {
+            trace!("Skipping {}, the request already went through", neighbor);
+            return Ok(BidProposals { bids: vec![] });
+        }
+        if self.cannot_host(&bid_request.sla, &neighbor, &accumulated_latency)
+        {
+            debug!(
+                "Skipping {}, its subtree cannot host sla {}",
+                neighbor, bid_request.sla.id
+            );
+            return Ok(BidProposals { bids: vec![] });
+        }
+        let timeout = self.bid_timeout(&bid_request.sla, &accumulated_latency);
+        let Some(request) = bid_request.follow_up(
+            self.node_situation.get_my_id(),
+            accumulated_latency,
+            nb_propositions_required,
+        ) else {
+            debug!(
+                "Not forwarding bid request {} to {}, no hop left",
+                bid_request.request_id, neighbor
+            );
+            return Ok(BidProposals { bids: vec![] });
+        };
+        match tokio::time::timeout(
+            timeout,
+            self.node_query.request_neighbor_bid(&request, neighbor.clone()),
+        )
+        .await
+        {
+            Ok(proposals) => proposals,
+            Err(_) => {
+                warn!(
+                    "{} did not answer the bid request for sla {} within \
+                     {:?}, leaving its subtree out",
+                    neighbor, bid_request.sla.id, timeout
+                );
+                if let Err(err) = self
+                    .metrics
+                    .observe(NeighborBidTimeout {
+                        n:         1,
+                        timeout:   timeout.as_secs_f64(),
+                        neighbor:  neighbor.to_string(),
+                        sla_id:    bid_request.sla.id.to_string(),
+                        timestamp: Utc::now(),
+                    })
+                    .await
+                {
+                    warn!("Failed to save metrics: {:?}", err);
+                }
+                Ok(BidProposals { bids: vec![] })
+            }
+        }
+    }
+
     #[instrument(level = "trace", skip(self))]
     pub async fn pay_function(&self, id: SlaId) -> Result<()> {
         let function = self.function.lock().await?;
-        let paid = function.pay_function(id.clone()).await?; // Function is now in the system
-                                                             //drop(function);
+        let paid_at = Utc::now();
+        // Schedule the end before the payment is saved, so that a paid
+        // function is never left running without one
+        let unprovision = match self.function_tracking.get_proposed(&id) {
+            Some(proposal) => {
+                self.schedule_unprovision(
+                    id.clone(),
+                    proposal.node.clone(),
+                    paid_at,
+                    proposal.sla.duration,
+                )
+                .await
+                .with_code(ErrorCode::BadRequest, || {
+                    format!("Cannot schedule the end of the function {}", id)
+                })?;
+                Some(Task::UnprovisionFunction(UnprovisionFunction {
+                    sla:  id.clone(),
+                    node: proposal.node,
+                }))
+            }
+            // Paying fails below with the reason
+            None => None,
+        };
+        // Function is now in the system
+        if let Err(err) = function.pay_function(id.clone(), paid_at).await {
+            if let Some(unprovision) = unprovision {
+                self.cron.cancel(&unprovision).await;
+            }
+            return Err(err);
+        }
+        drop(function);
+        self.cron.cancel(&Task::ExpireProposal(id.clone())).await;
+        self.cron.cancel(&Task::ForgetProposal(id)).await;
+        Ok(())
+    }
+
+    /// Another bid won the auction on the [Sla]
+    #[instrument(level = "trace", skip(self))]
+    pub async fn reject_function(&self, id: SlaId) -> Result<()> {
+        self.auction.reject(&id).await
+    }
+
+    /// Stop the function before the end of its duration, and drop the job
+    /// that would have stopped it
+    #[instrument(level = "trace", skip(self))]
+    pub async fn terminate_function(&self, id: SlaId) -> Result<()> {
+        let function = self.function.lock().await?;
+        ensure!(
+            self.function_tracking.get_finishable(&id).is_some(),
+            ErrorCode::Conflict.error(format!(
+                "Function {} is not running, cannot terminate it",
+                id
+            ))
+        );
+        // The job still stops the function should finishing it fail now
+        function.finish_function(id.clone()).await?;
+        if self.cron.cancel_sla(&id).await == 0 {
+            warn!("No pending job was found to unprovision {}", id);
+        }
+        Ok(())
+    }
+
+    /// Keep the function running for longer than its sla's duration, the
+    /// extension is priced by the node's valuation
+    #[instrument(level = "trace", skip(self))]
+    pub async fn extend_function(
+        &self,
+        id: SlaId,
+        duration: Time,
+    ) -> Result<SlaExtension> {
+        let _function = self.function.lock().await?;
+        let running = self
+            .function_tracking
+            .get_finishable(&id)
+            .with_code(ErrorCode::Conflict, || {
+                format!("Function {} is not running, cannot extend it", id)
+            })?
+            .to_finished();
+        let task = Task::UnprovisionFunction(UnprovisionFunction {
+            sla:  id.clone(),
+            node: running.node.clone(),
+        });
+        let deadline =
+            self.cron.get_deadline(&task).await.with_context(|| {
+                format!("No pending job was found to unprovision {}", id)
+            })?;
+
+        let extension = Sla { duration, ..running.sla };
+        let priced = self
+            .auction
+            .price_extension(&extension, &running.node)
+            .await
+            .context("Failed to price the extension")?;
+
+        let extended = duration
+            .get::<millisecond>()
+            .ceil()
+            .to_i64()
+            .and_then(chrono::Duration::try_milliseconds)
+            .and_then(|duration| deadline.checked_add_signed(duration))
+            .with_code(ErrorCode::BadRequest, || {
+                format!("Cannot extend {} by {:?}", id, duration)
+            })?;
+        self.cron
+            .reschedule(&task, extended)
+            .await
+            .context("Failed to move the end of the function")?;
+        // The stored sla must keep matching the end of the function
+        let sla = match self.function_tracking.extend(&id, duration) {
+            Ok(sla) => sla,
+            Err(err) => {
+                if let Err(err) = self.cron.reschedule(&task, deadline).await {
+                    warn!("Failed to move the end of {} back: {:?}", id, err);
+                }
+                return Err(
+                    err.context("Failed to save the extended duration")
+                );
+            }
+        };
+
+        Ok(SlaExtension { sla, price: priced.price })
+    }
 
+    /// Where the function of the sla is in its lifecycle
+    pub fn get_function_status(&self, id: &SlaId) -> Result<FunctionStatus> {
+        self.function_tracking
+            .get_status(id, Utc::now())
+            .with_code(ErrorCode::NotFound, || {
+                format!("No record of the function {}", id)
+            })
+    }
+
+    /// Resume the proposals and the functions recovered from the disk after a
+    /// restart: their resources are accounted for again and their expiry or
+    /// end is rescheduled
+    pub async fn recover(&self) -> Result<()> {
+        self.auction
+            .recover_proposals()
+            .await
+            .context("Failed to recover the proposals")?;
+
+        let function = self.function.lock().await?;
+        let recovered = function.recover_functions().await;
+        drop(function);
+
+        for paid in recovered {
+            info!("Recovered paid function {}", paid.sla.id);
+            // The restart interrupted the check that it turns live
+            if self.function_tracking.get_provisioned(&paid.sla.id).is_some() {
+                let function = self.function.clone();
+                let backoff = self.get_backoff();
+                let id = paid.sla.id.clone();
+                tokio::spawn(async move {
+                    if let Err(err) =
+                        wait_until_live(&function, backoff, id.clone()).await
+                    {
+                        warn!(
+                            "Recovered function {} did not turn live: {:?}",
+                            id, err
+                        );
+                    }
+                });
+            }
+            self.schedule_unprovision(
+                paid.sla.id,
+                paid.node,
+                paid.paid_at,
+                paid.sla.duration,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Unprovision the function once its duration is elapsed since its
+    /// payment
+    async fn schedule_unprovision(
+        &self,
+        id: SlaId,
+        node: String,
+        paid_at: DateTime<Utc>,
+        duration: Time,
+    ) -> Result<()> {
         let function = self.function.clone();
-        let node = paid.node;
         let task = Task::UnprovisionFunction(UnprovisionFunction {
             sla: id.clone(),
             node,
@@ -160,7 +649,7 @@ impl FunctionLife {
         let id2 = id.clone();
 
         self.cron
-            .add_oneshot(paid.sla.duration, task, move || {
+            .add_oneshot(paid_at, duration, task, move || {
                 let id = id.clone();
                 let function = function.clone();
                 Box::pin(async move {
@@ -194,7 +683,7 @@ impl FunctionLife {
                         panic!(
                             "Failed to finish function when unprovisioning \
                              function: {}",
-                            err.to_string()
+                            err
                         );
                     }
                 })
@@ -218,25 +707,12 @@ impl FunctionLife {
     #[instrument(level = "trace", skip(self))]
     pub async fn provision_function(&self, id: SlaId) -> Result<()> {
         self.prov(id.clone()).await?;
-
-        let res = backoff::future::retry(self.get_backoff(), || async {
-            trace!("Checking if function is alive");
-            self.function.check_and_set_function_is_live(id.clone()).await?;
-            Ok(())
-        })
-        .await;
-
-        if res.is_err() {
-            warn!("Failed to check that the function is alive");
-            let function = self.function.lock().await?;
-            function.finish_function(id).await?;
-        }
-        res
+        wait_until_live(&self.function, self.get_backoff(), id).await
     }
 
     fn get_backoff(&self) -> ExponentialBackoff<SystemClock> {
         let backoff = ExponentialBackoffBuilder::default()
-            .with_max_elapsed_time(Some((*self.function_live_timeout).clone()))
+            .with_max_elapsed_time(Some(*self.function_live_timeout))
             .build();
 
         backoff
@@ -261,7 +737,7 @@ impl FunctionLife {
             data_size,
             accumulated_latency_to_next_node.bandwidth
         );
-        return worse;
+        worse
     }
 }
 
@@ -346,6 +822,222 @@ fn get_tcp_latency(
 
     ret
 }
+#[cfg(feature = "offline")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::k8s::OFFLINE_NODE_K8S;
+    use crate::service::fixtures::{
+        get_auction_impl, get_auction_impl_tracked, market_connected, sla,
+        Instance,
+    };
+    use helper::uom_helper::cpu_ratio::cpu;
+    use model::dto::node::BidValuationConfig;
+    use model::view::function::FunctionState;
+    use std::time::Duration;
+    use uom::si::information::megabyte;
+
+    /// The status of the function follows it through its lifecycle
+    #[tokio::test]
+    async fn test_function_status() {
+        let Instance { auction, function_life, .. } =
+            get_auction_impl(market_connected()).await;
+        let sla = Sla { duration: Time::new::<second>(60.0), ..sla() };
+        let id = sla.id.clone();
+        function_life
+            .get_function_status(&id)
+            .expect_err("The function is not known yet");
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let status = function_life.get_function_status(&id).unwrap();
+        assert_eq!(status.state, FunctionState::Proposed);
+        assert_eq!(status.node.as_deref(), Some(OFFLINE_NODE_K8S));
+        assert!(status.paid_at.is_none() && status.remaining.is_none());
+
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+        let status = function_life.get_function_status(&id).unwrap();
+        assert_eq!(status.state, FunctionState::Paid);
+        let paid_at = status.paid_at.expect("No payment time");
+        assert_eq!(
+            status.ends_at,
+            Some(paid_at + chrono::Duration::seconds(60))
+        );
+
+        function_life
+            .provision_function(id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+        let status = function_life.get_function_status(&id).unwrap();
+        assert!(matches!(
+            status.state,
+            FunctionState::Provisioned | FunctionState::Live
+        ));
+        assert!(status.opened_port.is_some());
+        assert!(status.function_name.is_some());
+        let remaining = status.remaining.expect("No remaining time");
+        assert!(remaining > Time::new::<second>(0.0));
+        assert!(remaining <= Time::new::<second>(60.0));
+
+        function_life
+            .terminate_function(id.clone())
+            .await
+            .expect("Terminating the function failed");
+        let status = function_life.get_function_status(&id).unwrap();
+        assert_eq!(status.state, FunctionState::Finished);
+        assert!(status.ends_at.is_none());
+    }
+
+    /// The function is extended while running: it is priced and finishes
+    /// later than its initial duration
+    #[tokio::test]
+    async fn test_extension() {
+        let Instance {
+            auction,
+            function_life,
+            function_tracking,
+            resource_tracking,
+            ..
+        } = get_auction_impl(market_connected()).await;
+        let sla = Sla { duration: Time::new::<second>(3.0), ..sla() };
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+        function_life
+            .provision_function(id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+
+        let ends = || async {
+            function_life
+                .cron
+                .get_pending()
+                .await
+                .into_iter()
+                .find(|(task, _)| {
+                    matches!(task, Task::UnprovisionFunction(_))
+                        && *task.sla() == id
+                })
+                .map(|(_, deadline)| deadline)
+                .expect("The end of the function is not scheduled")
+        };
+        let initial_end = ends().await;
+        // The clock is paused from now on: sleeping only advances it, once
+        // every job due in the meantime ran
+        tokio::time::pause();
+
+        let extension = function_life
+            .extend_function(id.clone(), Time::new::<second>(3.0))
+            .await
+            .expect("Extending the function failed");
+        assert!(extension.price > 0.0);
+        assert_eq!(extension.sla.duration, Time::new::<second>(6.0));
+        assert_eq!(
+            function_tracking.get_finishable_sla(&id).unwrap().duration,
+            Time::new::<second>(6.0)
+        );
+        let extended_end = ends().await;
+        assert_eq!(extended_end - initial_end, chrono::Duration::seconds(3));
+
+        let margin = chrono::Duration::milliseconds(500);
+        let initial_remaining = initial_end - Utc::now() + margin;
+        tokio::time::sleep(initial_remaining.to_std().unwrap_or_default())
+            .await;
+        assert!(
+            function_tracking.get_finished(&id).is_none(),
+            "The function ended at its initial duration"
+        );
+
+        tokio::time::sleep((extended_end - initial_end).to_std().unwrap())
+            .await;
+        function_tracking.get_finished(&id).expect("Function is not finished");
+        let (ram, cc) =
+            resource_tracking.get_used(OFFLINE_NODE_K8S).await.unwrap();
+        assert_eq!(
+            ram,
+            Information::new::<megabyte>(num_rational::Ratio::new(0, 1))
+        );
+        assert_eq!(cc, Ratio::new::<cpu>(num_rational::Ratio::new(0, 1)));
+        function_life
+            .extend_function(id, Time::new::<second>(3.0))
+            .await
+            .expect_err("The function is already finished");
+    }
+
+    /// The node restarts before the provisioned function turned live: the
+    /// liveness check must resume after the recovery
+    #[tokio::test]
+    async fn test_recovery_resumes_liveness() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let Instance { auction, function_life, function, .. } =
+            get_auction_impl_tracked(
+                market_connected(),
+                PlacementStrategyKind::default(),
+                BidValuationConfig::default(),
+                Arc::new(FunctionTracking::new(&db).unwrap()),
+            )
+            .await;
+
+        let sla = sla();
+
+        let acc = AccumulatedLatency::default();
+        auction
+            .bid_on(sla.clone(), &acc)
+            .await
+            .expect("Bidding failed")
+            .expect("Bid refused");
+        let id = sla.id.clone();
+        function_life
+            .pay_function(id.clone())
+            .await
+            .expect("Function paid failed");
+        // Provisioned, without waiting for it to turn live
+        function
+            .lock()
+            .await
+            .unwrap()
+            .provision_function(id.clone())
+            .await
+            .expect("Provisioning paid function failed");
+
+        // Restart the node on the same database
+        let Instance { function_life, function_tracking, .. } =
+            get_auction_impl_tracked(
+                market_connected(),
+                PlacementStrategyKind::default(),
+                BidValuationConfig::default(),
+                Arc::new(FunctionTracking::new(&db).unwrap()),
+            )
+            .await;
+        assert!(function_tracking.get_provisioned(&id).is_some());
+
+        function_life.recover().await.expect("Recovery failed");
+        // Longer than the offline backend takes to set the function online
+        tokio::time::sleep(Duration::from_secs(3)).await;
+
+        let status = function_tracking
+            .get_status(&id, Utc::now())
+            .expect("The function is not tracked");
+        assert_eq!(status.state, FunctionState::Live);
+    }
+}
+
 //#[cfg(test)]
 //mod tests {
 //    use crate::service::function_life::get_tcp_latency;
@@ -1,4 +1,9 @@
+pub(crate) mod admin;
 pub(crate) mod auction;
+pub(crate) mod bid_valuation;
+#[cfg(feature = "offline")]
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub(crate) mod function;
 pub(crate) mod function_life;
 pub(crate) mod neighbor_monitor;
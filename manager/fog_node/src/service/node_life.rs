@@ -1,25 +1,231 @@
-use crate::{NodeQuery, NodeSituation};
+use crate::service::auction::Auction;
+use crate::service::neighbor_monitor::NeighborMonitor;
+use crate::{NodeQuery, NodeSituation, HEARTBEAT_INTERVAL_MSECS};
 use anyhow::{bail, Context, Result};
-use model::dto::node::NodeDescription;
-use model::view::node::RegisterNode;
+use helper::env_load;
+use helper::http_error::ErrorCode;
+use model::dto::node::{NodeCategory, NodeDescription};
+use model::view::node::{
+    DeregisterNode, MoveNode, MoveNodeRequest, NodeCapacity, RegisterNode,
+};
 use model::{FogNodeFaaSPortExternal, FogNodeHTTPPort};
+use nutype::nutype;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tracing::trace;
+use std::time::Duration;
+use tracing::{trace, warn};
 use uom::si::rational64::InformationRate;
 
-#[derive(Debug)]
+/// Period of the heartbeats sent to the market, in milliseconds
+#[nutype(derive(Clone, Debug), validate(greater = 0))]
+pub struct HeartbeatInterval(u64);
+
 pub struct NodeLife {
-    node_situation: Arc<NodeSituation>,
-    node_query:     Arc<NodeQuery>,
+    node_situation:     Arc<NodeSituation>,
+    node_query:         Arc<NodeQuery>,
+    auction:            Arc<Auction>,
+    neighbor_monitor:   Arc<NeighborMonitor>,
+    heartbeat_interval: Duration,
+    /// Set once the node left the network
+    left:               AtomicBool,
 }
 
 impl NodeLife {
     pub fn new(
         node_situation: Arc<NodeSituation>,
         node_query: Arc<NodeQuery>,
-    ) -> Self {
-        Self { node_situation, node_query }
+        auction: Arc<Auction>,
+        neighbor_monitor: Arc<NeighborMonitor>,
+    ) -> Result<Self> {
+        let heartbeat_interval =
+            env_load!(HeartbeatInterval, HEARTBEAT_INTERVAL_MSECS, u64);
+        let heartbeat_interval =
+            Duration::from_millis(heartbeat_interval.into_inner());
+        Ok(Self {
+            node_situation,
+            node_query,
+            auction,
+            neighbor_monitor,
+            heartbeat_interval,
+            left: AtomicBool::new(false),
+        })
+    }
+
+    pub fn get_heartbeat_interval(&self) -> Duration {
+        self.heartbeat_interval
+    }
+
+    /// Keep the child known to this node and forward its heartbeat to the
+    /// market
+    pub async fn relay_heartbeat(&self, register: RegisterNode) -> Result<()> {
+        trace!("Relaying heartbeat");
+        self.ensure_not_left()?;
+        self.register_child_locally(&register)?;
+        self.node_query
+            .heartbeat_to_parent(&register)
+            .await
+            .context("Failed to forward the heartbeat to my parent")
+    }
+
+    fn ensure_not_left(&self) -> Result<()> {
+        if self.left.load(Ordering::Relaxed) {
+            bail!(ErrorCode::Conflict.error("This node left the network"));
+        }
+        Ok(())
+    }
+
+    /// Leave the network, the parent and the market forget this node. The
+    /// children are moved under the parent first, so that they keep
+    /// reaching the market.
+    pub async fn leave(&self) -> Result<()> {
+        trace!("Leaving the network");
+        self.ensure_not_left()?;
+        self.rehome_children().await?;
+        let deregister = DeregisterNode {
+            node_id: self.node_situation.get_my_id(),
+            parent:  self.node_situation.get_parent_id(),
+        };
+        self.node_query.deregister_to_parent(&deregister).await?;
+        self.left.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Move the children under the parent of this node. A child that failed
+    /// to move is still a child, leaving again retries it.
+    async fn rehome_children(&self) -> Result<()> {
+        let children = self.node_situation.get_children();
+        if children.is_empty() {
+            return Ok(());
+        }
+        let parent = self
+            .node_situation
+            .get_parent_id()
+            .zip(self.node_situation.get_parent_node_address());
+        let Some((parent_id, (parent_node_ip, parent_node_port_http))) =
+            parent
+        else {
+            bail!(ErrorCode::Conflict.error(
+                "The node connected to the market cannot leave while other \
+                 nodes are attached to it"
+            ));
+        };
+        for (child, description) in children {
+            let request = MoveNodeRequest {
+                parent_id: parent_id.clone(),
+                parent_node_ip,
+                parent_node_port_http: parent_node_port_http.clone(),
+                #[cfg(feature = "offline")]
+                parent_latency: description.latency
+                    + self.node_situation.get_my_offline_latency(),
+            };
+            self.node_query
+                .move_child(&description, &request)
+                .await
+                .with_context(|| {
+                    format!("Failed to move my child {} to my parent", child)
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Forget the child if this node is its parent and forward its
+    /// departure to the market
+    pub async fn relay_deregistration(
+        &self,
+        deregister: DeregisterNode,
+    ) -> Result<()> {
+        trace!("Relaying deregistration of {}", deregister.node_id);
+        self.node_query
+            .deregister_to_parent(&deregister)
+            .await
+            .context("Failed to forward the deregistration to my parent")?;
+        if deregister.parent == Some(self.node_situation.get_my_id()) {
+            self.node_situation.unregister(&deregister.node_id);
+        }
+        Ok(())
+    }
+
+    /// Re-attach this node, along with its subtree, under another parent.
+    /// The new parent is told first, nothing changes if it fails to
+    /// register this node. The old parent is told then.
+    pub async fn move_to(&self, request: MoveNodeRequest) -> Result<()> {
+        trace!("Moving under {}", request.parent_id);
+        self.ensure_not_left()?;
+        let from = self.node_situation.get_parent_id().ok_or_else(|| {
+            ErrorCode::Conflict
+                .error("The node connected to the market cannot move")
+        })?;
+        if request.parent_id == self.node_situation.get_my_id() {
+            bail!(
+                ErrorCode::BadRequest.error("A node cannot be its own parent")
+            );
+        }
+        let move_node = MoveNode {
+            node_id: self.node_situation.get_my_id(),
+            from,
+            to: request.parent_id.clone(),
+            ip: self.node_situation.get_my_public_ip(),
+            port_http: self.node_situation.get_my_public_port_http(),
+            advertised_bandwidth: self
+                .node_situation
+                .get_my_advertised_bandwidth(),
+            #[cfg(feature = "offline")]
+            offline_latency: request.parent_latency,
+        };
+
+        self.node_query
+            .move_to_new_parent(
+                &move_node,
+                request.parent_node_ip,
+                &request.parent_node_port_http,
+            )
+            .await
+            .context("Failed to tell my new parent about the move")?;
+        // The new parent relayed the move to the market, the old one only
+        // has to forget this node
+        if let Err(err) = self.node_query.move_to_parent(&move_node).await {
+            warn!("Failed to tell my old parent about the move: {:?}", err);
+        }
+        self.node_situation.set_situation(NodeCategory::NodeConnected {
+            parent_id: request.parent_id,
+            parent_node_ip: request.parent_node_ip,
+            parent_node_port_http: request.parent_node_port_http,
+            #[cfg(feature = "offline")]
+            parent_latency: request.parent_latency,
+        });
+        Ok(())
+    }
+
+    /// Update the children if the move concerns this node and forward it to
+    /// the market, which moves the node only once
+    pub async fn relay_move(&self, move_node: MoveNode) -> Result<()> {
+        trace!("Relaying move of {}", move_node.node_id);
+        let me = self.node_situation.get_my_id();
+        if move_node.node_id == me {
+            bail!(ErrorCode::Conflict
+                .error(format!("Node {} cannot move in its own subtree", me)));
+        }
+        self.node_query
+            .move_to_parent(&move_node)
+            .await
+            .context("Failed to forward the move to my parent")?;
+        if move_node.from == me {
+            self.node_situation.unregister(&move_node.node_id);
+        }
+        if move_node.to == me {
+            self.node_situation.register(
+                move_node.node_id,
+                NodeDescription {
+                    ip: move_node.ip,
+                    port_http: move_node.port_http,
+                    advertised_bandwidth: move_node.advertised_bandwidth,
+                    #[cfg(feature = "offline")]
+                    latency: move_node.offline_latency,
+                },
+            );
+        }
+        Ok(())
     }
 
     pub async fn register_child_node(
@@ -27,7 +233,17 @@ impl NodeLife {
         register: RegisterNode,
     ) -> Result<()> {
         trace!("Registering child node");
-        match &register {
+        self.register_child_locally(&register)?;
+        self.node_query
+            .register_to_parent(register)
+            .await
+            .context("Failed to register a child node to my parent")?;
+        Ok(())
+    }
+
+    /// Record the node as a child if this node is its parent
+    fn register_child_locally(&self, register: &RegisterNode) -> Result<()> {
+        match register {
             RegisterNode::Node {
                 node_id,
                 parent,
@@ -58,22 +274,39 @@ impl NodeLife {
                 );
             }
         }
-
-        self.node_query
-            .register_to_parent(register)
-            .await
-            .context("Failed to register a child node to my parent")?;
         Ok(())
     }
 
-    pub async fn init_registration(
+    /// What this node can still host, reported to the market for the
+    /// placements it computes itself
+    async fn my_capacity(&self) -> Option<NodeCapacity> {
+        let (max_free_memory, max_free_cpu) =
+            match self.auction.get_max_free_resources().await {
+                Ok(free) => free,
+                Err(err) => {
+                    warn!("Failed to get my free resources: {:?}", err);
+                    return None;
+                }
+            };
+        let latency_to_parent = match self.node_situation.get_parent_id() {
+            Some(parent) => self
+                .neighbor_monitor
+                .get_latency_to(&parent)
+                .await
+                .map(|latency| latency.median),
+            None => None,
+        };
+        Some(NodeCapacity { max_free_cpu, max_free_memory, latency_to_parent })
+    }
+
+    /// How this node describes itself to its parent
+    async fn my_registration(
         &self,
         ip: IpAddr,
         port_http: FogNodeHTTPPort,
         port_faas: FogNodeFaaSPortExternal,
         advertised_bandwidth: InformationRate,
-    ) -> Result<()> {
-        trace!("Init registration");
+    ) -> Result<RegisterNode> {
         let register = if self.node_situation.is_market() {
             RegisterNode::MarketNode {
                 node_id: self.node_situation.get_my_id(),
@@ -82,6 +315,7 @@ impl NodeLife {
                 port_faas,
                 tags: self.node_situation.get_my_tags(),
                 advertised_bandwidth,
+                capacity: self.my_capacity().await,
             }
         } else {
             RegisterNode::Node {
@@ -97,9 +331,42 @@ impl NodeLife {
                 advertised_bandwidth,
                 #[cfg(feature = "offline")]
                 offline_latency: self.node_situation.get_my_offline_latency(),
+                capacity: self.my_capacity().await,
             }
         };
+        Ok(register)
+    }
+
+    pub async fn init_registration(
+        &self,
+        ip: IpAddr,
+        port_http: FogNodeHTTPPort,
+        port_faas: FogNodeFaaSPortExternal,
+        advertised_bandwidth: InformationRate,
+    ) -> Result<()> {
+        trace!("Init registration");
+        let register = self
+            .my_registration(ip, port_http, port_faas, advertised_bandwidth)
+            .await?;
         self.node_query.register_to_parent(register).await?;
         Ok(())
     }
+
+    /// Tell the market this node is still alive
+    pub async fn send_heartbeat(
+        &self,
+        ip: IpAddr,
+        port_http: FogNodeHTTPPort,
+        port_faas: FogNodeFaaSPortExternal,
+        advertised_bandwidth: InformationRate,
+    ) -> Result<()> {
+        trace!("Sending heartbeat");
+        if self.left.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let register = self
+            .my_registration(ip, port_http, port_faas, advertised_bandwidth)
+            .await?;
+        self.node_query.heartbeat_to_parent(&register).await
+    }
 }
@@ -0,0 +1,243 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use tracing::error;
+
+/// Class of a failure, for the clients to react programmatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request is malformed or describes something impossible
+    BadRequest,
+    /// The designated resource is unknown
+    NotFound,
+    /// The resource is not in a state allowing the operation
+    Conflict,
+    /// The resource was only available for a limited time
+    Expired,
+    /// Not enough resources are left to satisfy the request
+    Unsatisfiable,
+    /// Another node of the fog failed to answer properly
+    UpstreamFailure,
+    /// Anything else
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::Expired => StatusCode::GONE,
+            ErrorCode::Unsatisfiable => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::UpstreamFailure => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Create an error of this class
+    pub fn error<C>(self, context: C) -> anyhow::Error
+    where
+        C: Display,
+    {
+        anyhow::Error::new(Coded {
+            code:    self,
+            context: context.to_string(),
+        })
+    }
+}
+
+/// Body of all the error responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorBody {
+    pub code:    ErrorCode,
+    /// Human readable chain of causes
+    pub message: String,
+    /// Machine readable details, their schema depends on the code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail:  Option<serde_json::Value>,
+}
+
+/// Error message tagged with its class, the outermost one wins
+#[derive(Debug)]
+struct Coded {
+    code:    ErrorCode,
+    context: String,
+}
+
+impl Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.context)
+    }
+}
+
+impl std::error::Error for Coded {}
+
+/// Like [anyhow::Context], also classifying the error
+pub trait CodeContext<T, E> {
+    fn code<C>(self, code: ErrorCode, context: C) -> anyhow::Result<T>
+    where
+        C: Display + Send + Sync + 'static;
+
+    fn with_code<C, F>(self, code: ErrorCode, f: F) -> anyhow::Result<T>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+impl<T, E, R> CodeContext<T, E> for R
+where
+    R: anyhow::Context<T, E>,
+{
+    fn code<C>(self, code: ErrorCode, context: C) -> anyhow::Result<T>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.with_context(|| Coded { code, context: context.to_string() })
+    }
+
+    fn with_code<C, F>(self, code: ErrorCode, f: F) -> anyhow::Result<T>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.with_context(|| Coded { code, context: f().to_string() })
+    }
+}
+
+/// Error returned by the HTTP handlers, answered as an [ErrorBody]
+#[derive(Debug)]
+pub struct HttpError {
+    code:   ErrorCode,
+    err:    anyhow::Error,
+    detail: Option<serde_json::Value>,
+}
+
+impl HttpError {
+    pub fn new<E>(code: ErrorCode, err: E) -> Self
+    where
+        E: Into<anyhow::Error>,
+    {
+        let err = err.into();
+        error!("{}", err);
+        HttpError { code, err, detail: None }
+    }
+
+    pub fn with_detail<D>(mut self, detail: &D) -> Self
+    where
+        D: Serialize,
+    {
+        self.detail = serde_json::to_value(detail).ok();
+        self
+    }
+
+    pub fn code(&self) -> ErrorCode { self.code }
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.err)
+    }
+}
+
+impl ResponseError for HttpError {
+    fn status_code(&self) -> StatusCode { self.code.status() }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            code:    self.code,
+            message: format!("{:#}", self.err),
+            detail:  self.detail.clone(),
+        })
+    }
+}
+
+impl From<anyhow::Error> for HttpError {
+    fn from(err: anyhow::Error) -> HttpError {
+        let code = err
+            .downcast_ref::<Coded>()
+            .map(|coded| coded.code)
+            .unwrap_or(ErrorCode::Internal);
+        HttpError::new(code, err)
+    }
+}
+
+/// Error handler of the extractors (`JsonConfig`, `PathConfig`,
+/// `QueryConfig`...), so that malformed requests are answered as an
+/// [ErrorBody] too
+pub fn bad_request<E>(err: E, _req: &HttpRequest) -> actix_web::Error
+where
+    E: Into<anyhow::Error>,
+{
+    HttpError::new(ErrorCode::BadRequest, err).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App};
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_classification() {
+        let err: anyhow::Error = Err::<(), _>(anyhow!("root"))
+            .code(ErrorCode::NotFound, "not there")
+            .context("outer")
+            .unwrap_err();
+        let err = HttpError::from(err);
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+
+        let err = HttpError::from(anyhow!("unclassified"));
+        assert_eq!(err.code(), ErrorCode::Internal);
+
+        let err = HttpError::from(
+            Err::<(), _>(ErrorCode::Conflict.error("inner"))
+                .code(ErrorCode::UpstreamFailure, "outermost")
+                .unwrap_err(),
+        );
+        assert_eq!(err.code(), ErrorCode::UpstreamFailure);
+    }
+
+    #[test]
+    fn test_body_schema() {
+        let body = ErrorBody {
+            code:    ErrorCode::UpstreamFailure,
+            message: "toto".to_string(),
+            detail:  None,
+        };
+        assert_eq!(
+            serde_json::to_string(&body).unwrap(),
+            r#"{"code":"UPSTREAM_FAILURE","message":"toto"}"#
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_request() {
+        async fn echo(body: web::Json<ErrorBody>) -> web::Json<ErrorBody> {
+            body
+        }
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(
+                    web::JsonConfig::default().error_handler(bad_request),
+                )
+                .route("/echo", web::post().to(echo)),
+        )
+        .await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/echo")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{not json")
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: ErrorBody = actix_web::test::read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::BadRequest);
+        assert!(body.detail.is_none());
+    }
+}
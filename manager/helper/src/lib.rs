@@ -1,4 +1,3 @@
-#![feature(type_alias_impl_trait)]
 #![allow(incomplete_features)]
 
 #[macro_use]
@@ -8,6 +7,7 @@ pub mod chrono;
 pub mod env;
 pub mod err;
 pub mod from_disk;
+pub mod http_error;
 pub mod monitoring;
 pub mod pool;
 pub mod reqwest_helper;
@@ -7,12 +7,7 @@ pub struct Pool<T: Default> {
 impl<T: Default> Pool<T> {
     pub fn new(size: usize) -> Self { Self { pool: ArrayQueue::new(size) } }
 
-    pub fn get(&self) -> T {
-        match self.pool.pop() {
-            Some(buffer) => buffer,
-            None => T::default(),
-        }
-    }
+    pub fn get(&self) -> T { self.pool.pop().unwrap_or_default() }
 
     pub fn put(&self, buffer: T) { self.pool.force_push(buffer); }
 }
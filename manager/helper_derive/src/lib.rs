@@ -1,4 +1,4 @@
-#[allow(refining_impl_trait)]
+#![allow(refining_impl_trait)]
 use helper::monitoring::InfluxName;
 use proc_macro::TokenStream;
 use quote::quote;
@@ -67,7 +67,7 @@ pub fn influx_observation(
         )
         .into_inner();
     item_struct_to_db.ident = syn::Ident::new(
-        &format!("{}Exported", name.to_string()),
+        &format!("{}Exported", name),
         item_struct_to_db.ident.span(),
     );
     item_struct_to_db.vis =
@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
 use model::view::auction::AcceptedBid;
-use model::view::node::{GetFogNodes, RegisterNode};
-use model::view::sla::PutSla;
+use model::view::function::GetFunction;
+use model::view::node::{DeregisterNode, GetFogNodes, MoveNode, RegisterNode};
+use model::view::sla::{ExtendSla, PutSla};
 use model::{NodeId, SlaId};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -16,7 +17,11 @@ pub async fn start_auction(
     trace!("put sla: {:?} for {:?}", payload.sla.id, payload.target_node);
 
     auction_service
-        .start_auction(payload.target_node, payload.sla)
+        .start_auction(
+            payload.target_node,
+            payload.sla,
+            payload.auction_mechanism,
+        )
         .await
         .context("Failed the auctioning and provisionning process")
 }
@@ -34,6 +39,49 @@ pub async fn provision_function(
         .context("Failed the auctioning and provisionning process")
 }
 
+/// Stop a provisioned function before the end of its SLA
+#[instrument(level = "trace", skip(auction_service))]
+pub async fn terminate_function(
+    id: SlaId,
+    auction_service: &Arc<crate::service::auction::Auction>,
+) -> Result<()> {
+    trace!("terminate sla id: {}", id);
+
+    auction_service
+        .terminate(id)
+        .await
+        .context("Failed to terminate the function")
+}
+
+/// Get the auctioned function and where it is in its lifecycle
+#[instrument(level = "trace", skip(auction_service))]
+pub async fn get_function(
+    id: SlaId,
+    auction_service: &Arc<crate::service::auction::Auction>,
+) -> Result<GetFunction> {
+    trace!("get sla id: {}", id);
+
+    auction_service
+        .get_function(id)
+        .await
+        .context("Failed to get the function")
+}
+
+/// Keep a provisioned function running for longer than its SLA
+#[instrument(level = "trace", skip(auction_service))]
+pub async fn extend_function(
+    id: SlaId,
+    extension: ExtendSla,
+    auction_service: &Arc<crate::service::auction::Auction>,
+) -> Result<AcceptedBid> {
+    trace!("extend sla id: {}", id);
+
+    auction_service
+        .extend(id, extension)
+        .await
+        .context("Failed to extend the function")
+}
+
 /// Register a new node in the network
 pub async fn register_node(
     payload: RegisterNode,
@@ -43,6 +91,33 @@ pub async fn register_node(
     fog_net.register_node(payload).await.context("Failed to register node")
 }
 
+/// Refresh the liveness of a node
+pub async fn heartbeat(
+    payload: RegisterNode,
+    fog_net: &Arc<crate::service::fog_node_network::FogNodeNetwork>,
+) -> Result<()> {
+    trace!("heartbeat: {:?}", payload);
+    fog_net.heartbeat(payload).await.context("Failed to process heartbeat")
+}
+
+/// Remove a node leaving the network
+pub async fn deregister_node(
+    payload: DeregisterNode,
+    fog_net: &Arc<crate::service::fog_node_network::FogNodeNetwork>,
+) -> Result<()> {
+    trace!("deregistering node: {:?}", payload);
+    fog_net.deregister_node(payload).await.context("Failed to deregister node")
+}
+
+/// Re-attach a node under another parent
+pub async fn move_node(
+    payload: MoveNode,
+    fog_net: &Arc<crate::service::fog_node_network::FogNodeNetwork>,
+) -> Result<()> {
+    trace!("moving node: {:?}", payload);
+    fog_net.move_node(payload).await.context("Failed to move node")
+}
+
 /// Get all the provisioned functions from the database
 pub async fn get_functions(
     faas_service: &Arc<crate::service::faas::FogNodeFaaS>,
@@ -4,42 +4,22 @@ use actix_web::web::{self, Data, Json};
 use actix_web::HttpResponse;
 use anyhow::Context;
 use chrono::Utc;
+use helper::http_error::HttpError;
 use helper::monitoring::MetricsExporter;
-use model::view::node::RegisterNode;
-use model::view::sla::{PutSla, PutSlaRequest};
+use model::view::node::{DeregisterNode, MoveNode, RegisterNode};
+use model::view::sla::{ExtendSla, PutSla, PutSlaRequest};
 use model::SlaId;
 use serde::Deserialize;
 use tracing::error;
 
-#[derive(Debug)]
-pub struct AnyhowErrorWrapper {
-    err: anyhow::Error,
-}
-
-impl std::fmt::Display for AnyhowErrorWrapper {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        //error!("{:?}", self.err);
-        write!(f, "{:?}", self.err)
-    }
-}
-
-impl actix_web::error::ResponseError for AnyhowErrorWrapper {}
-
-impl From<anyhow::Error> for AnyhowErrorWrapper {
-    fn from(err: anyhow::Error) -> AnyhowErrorWrapper {
-        error!("{}", err);
-        AnyhowErrorWrapper { err }
-    }
-}
-
 /// Register a SLA and starts the auctioning process, as well as establishing
 /// the routing once the auction is completed
 pub async fn put_function(
     payload: Json<PutSlaRequest>,
     auction_service: Data<crate::service::auction::Auction>,
     metrics: Data<MetricsExporter>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
-    let payload: PutSla = payload.0.into();
+) -> Result<HttpResponse, HttpError> {
+    let payload: PutSla = payload.0.try_into()?;
     let res =
         controller::start_auction(payload.clone(), &auction_service).await;
     match res {
@@ -77,34 +57,98 @@ pub struct PostProvisionParams {
 pub async fn post_provision_function(
     params: web::Path<PostProvisionParams>,
     auction_service: Data<crate::service::auction::Auction>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     controller::provision_function(params.id.clone(), &auction_service)
         .await?;
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Stop the function before the end of its SLA
+pub async fn delete_function(
+    params: web::Path<PostProvisionParams>,
+    auction_service: Data<crate::service::auction::Auction>,
+) -> Result<HttpResponse, HttpError> {
+    controller::terminate_function(params.id.clone(), &auction_service)
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Keep the function running for longer than the duration of its SLA
+pub async fn post_extend_function(
+    params: web::Path<PostProvisionParams>,
+    payload: Json<ExtendSla>,
+    auction_service: Data<crate::service::auction::Auction>,
+) -> Result<HttpResponse, HttpError> {
+    payload.validate()?;
+    let res = controller::extend_function(
+        params.id.clone(),
+        payload.0,
+        &auction_service,
+    )
+    .await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
 /// Register a new node in the network
 pub async fn post_register_node(
     payload: Json<RegisterNode>,
     node_net: Data<crate::service::fog_node_network::FogNodeNetwork>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     controller::register_node(payload.0, &node_net).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Remove a node leaving the network
+pub async fn post_deregister_node(
+    payload: Json<DeregisterNode>,
+    node_net: Data<crate::service::fog_node_network::FogNodeNetwork>,
+) -> Result<HttpResponse, HttpError> {
+    controller::deregister_node(payload.0, &node_net).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Re-attach a node under another parent
+pub async fn post_move_node(
+    payload: Json<MoveNode>,
+    node_net: Data<crate::service::fog_node_network::FogNodeNetwork>,
+) -> Result<HttpResponse, HttpError> {
+    controller::move_node(payload.0, &node_net).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Keep a registered node alive in the network
+pub async fn post_heartbeat(
+    payload: Json<RegisterNode>,
+    node_net: Data<crate::service::fog_node_network::FogNodeNetwork>,
+) -> Result<HttpResponse, HttpError> {
+    controller::heartbeat(payload.0, &node_net).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
 /// Get all the successfull transactions (function provisioned) done by the
 /// market since its boot.
 pub async fn get_functions(
     faas_service: Data<crate::service::faas::FogNodeFaaS>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     let res = controller::get_functions(&faas_service).await;
     Ok(HttpResponse::Ok().json(res))
 }
 
+/// Get the function auctioned for the SLA, and its state on the fog node
+/// hosting it
+pub async fn get_function(
+    params: web::Path<PostProvisionParams>,
+    auction_service: Data<crate::service::auction::Auction>,
+) -> Result<HttpResponse, HttpError> {
+    let res =
+        controller::get_function(params.id.clone(), &auction_service).await;
+    Ok(HttpResponse::Ok().json(res?))
+}
+
 /// Get all the connected nodes that have registered here
 pub async fn get_fog(
     fog_node_network: Data<crate::service::fog_node_network::FogNodeNetwork>,
-) -> Result<HttpResponse, AnyhowErrorWrapper> {
+) -> Result<HttpResponse, HttpError> {
     let res = controller::get_fog(&fog_node_network).await?;
     Ok(HttpResponse::Ok().json(res))
 }
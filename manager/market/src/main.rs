@@ -1,6 +1,5 @@
-#![feature(async_closure)]
-#![feature(future_join)]
 use actix_web::web::Data;
+use helper::http_error::bad_request;
 use helper::monitoring::{
     InfluxAddress, InfluxBucket, InfluxOrg, InfluxToken, InstanceName,
     MetricsExporter,
@@ -16,6 +15,7 @@ use crate::handler::*;
 use actix_web::{middleware, web, App, HttpServer};
 use actix_web_opentelemetry::RequestTracing;
 use anyhow::Context;
+use model::domain::auction::AuctionMechanismKind;
 use opentelemetry::global;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
 use reqwest_middleware::ClientBuilder;
@@ -23,7 +23,7 @@ use reqwest_tracing::TracingMiddleware;
 use std::env::var;
 use std::sync::Arc;
 use tracing::subscriber::set_global_default;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
 use tracing_actix_web::TracingLogger;
 use tracing_forest::ForestLayer;
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
@@ -40,6 +40,12 @@ env_var!(INFLUX_TOKEN);
 env_var!(INFLUX_ORG);
 env_var!(INFLUX_BUCKET);
 env_var!(INSTANCE_NAME);
+env_var!(AUCTION_MAX_FALLBACKS);
+env_var!(BID_HOP_BUDGET);
+env_var!(NODE_SUSPECT_TIMEOUT_MSECS);
+env_var!(NODE_EVICTION_TIMEOUT_MSECS);
+env_var!(MARKET_PLACEMENT);
+env_var!(AUCTION_MECHANISM);
 
 /// Compose multiple layers into a `tracing`'s subscriber.
 pub fn init_subscriber(name: String, env_filter: String) {
@@ -69,9 +75,7 @@ pub fn init_subscriber(name: String, env_filter: String) {
     //  let telemetry = tracing_opentelemetry::layer().with_tracer(tracer);
 
     //let reg = reg.with(telemetry);
-    if std::env::var("ENABLE_COLLECTOR").unwrap_or("".to_string())
-        == "true".to_string()
-    {
+    if std::env::var("ENABLE_COLLECTOR").unwrap_or("".to_string()) == "true" {
         let collector_ip = std::env::var("COLLECTOR_IP")
             .unwrap_or_else(|_| "localhost".to_string());
         let collector_port = std::env::var("COLLECTOR_PORT")
@@ -136,7 +140,7 @@ async fn main() -> anyhow::Result<()> {
 
     let fog_node = Arc::new(repository::fog_node::FogNode::new());
     let fog_node_network_service = Arc::new(
-        service::fog_node_network::FogNodeNetwork::new(fog_node.clone()),
+        service::fog_node_network::FogNodeNetwork::new(fog_node.clone())?,
     );
 
     let fog_node_communication = Arc::new(
@@ -145,7 +149,15 @@ async fn main() -> anyhow::Result<()> {
             http_client,
         ),
     );
-    let auction_process = Arc::new(crate::repository::auction::Auction::new());
+    let auction_mechanism = match var(AUCTION_MECHANISM) {
+        Ok(mechanism) => mechanism.parse().with_context(|| {
+            format!("{} was not formatted right", AUCTION_MECHANISM)
+        })?,
+        Err(_) => AuctionMechanismKind::default(),
+    };
+    info!("Using the {} auction mechanism", auction_mechanism);
+    let auction_process =
+        Arc::new(crate::repository::auction::Auction::new(auction_mechanism));
     let bid_tracking =
         Arc::new(crate::repository::bid_tracking::BidTracking::new());
 
@@ -161,7 +173,23 @@ async fn main() -> anyhow::Result<()> {
         faas_service.clone(),
         metrics.clone(),
         bid_tracking.clone(),
-    ));
+    )?);
+
+    let evicting_network = fog_node_network_service.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            match evicting_network.evict_silent_nodes().await {
+                Ok(evicted) if !evicted.is_empty() => {
+                    warn!("Evicted silent nodes: {:?}", evicted);
+                }
+                Ok(_) => {}
+                Err(err) => error!("Failed to evict silent nodes: {:?}", err),
+            }
+        }
+    });
 
     info!("Starting HHTP server on 0.0.0.0:{}", my_port_http);
 
@@ -176,7 +204,10 @@ async fn main() -> anyhow::Result<()> {
         let app =
             app.wrap(TracingLogger::default()).wrap(RequestTracing::new());
 
-        app.app_data(Data::clone(&faas_service))
+        app.app_data(web::JsonConfig::default().error_handler(bad_request))
+            .app_data(web::PathConfig::default().error_handler(bad_request))
+            .app_data(web::QueryConfig::default().error_handler(bad_request))
+            .app_data(Data::clone(&faas_service))
             .app_data(Data::clone(&auction_service))
             .app_data(Data::clone(&fog_node_network_service))
             .app_data(Data::clone(&metrics))
@@ -187,7 +218,16 @@ async fn main() -> anyhow::Result<()> {
                         "/function/{id}",
                         web::post().to(post_provision_function),
                     )
+                    .route("/function/{id}", web::get().to(get_function))
+                    .route("/function/{id}", web::delete().to(delete_function))
+                    .route(
+                        "/function/{id}/extend",
+                        web::post().to(post_extend_function),
+                    )
                     .route("/register", web::post().to(post_register_node))
+                    .route("/deregister", web::post().to(post_deregister_node))
+                    .route("/reparent", web::post().to(post_move_node))
+                    .route("/heartbeat", web::post().to(post_heartbeat))
                     .route("/functions", web::get().to(get_functions))
                     .route("/fog", web::get().to(get_fog))
                     .route("/health", web::get().to(health)),
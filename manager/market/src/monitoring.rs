@@ -14,6 +14,44 @@ struct FunctionDeploymentDuration {
     bid_id:                  String,
     #[influxdb(tag)]
     sla_id:                  String,
+    /// Number of chosen bids that failed before this one
+    #[influxdb(field)]
+    fallbacks:               u64,
+}
+
+/// A chosen bid failed, the market falls back on the next best one
+#[influx_observation]
+struct AuctionFallback {
+    /// Number of fallbacks so far for the sla, this one included
+    #[influxdb(field)]
+    value:         u64,
+    #[influxdb(tag)]
+    function_name: String,
+    #[influxdb(tag)]
+    sla_id:        String,
+    #[influxdb(tag)]
+    bid_id:        String,
+    #[influxdb(tag)]
+    node_id:       String,
+    /// Either payment or provisioning
+    #[influxdb(tag)]
+    phase:         String,
+}
+
+/// Price paid to extend the duration of a function.
+#[influx_observation]
+struct FunctionExtension {
+    #[influxdb(field)]
+    value:         f64,
+    /// Added duration, in milliseconds
+    #[influxdb(field)]
+    extension:     f64,
+    #[influxdb(tag)]
+    function_name: String,
+    #[influxdb(tag)]
+    sla_id:        String,
+    #[influxdb(tag)]
+    bid_id:        String,
 }
 
 /// Number of provisioned functions.d
@@ -1,40 +1,252 @@
+use model::domain::auction::AuctionMechanismKind;
 use model::dto::function::ChosenBid;
 use model::view::auction::BidProposal;
+use std::fmt::Debug;
+use uom::si::time::millisecond;
 
-pub struct Auction;
+/// Rule choosing the winner among the bids and the price it is paid
+pub trait AuctionMechanism: Debug + Send + Sync {
+    /// None when no bid can win
+    fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid>;
+}
 
-impl Auction {
-    pub fn new() -> Self { Self {} }
+/// The bids sorted by the key, ascending. The bids whose key is not a
+/// finite number are left out.
+fn sorted_by_key(
+    bids: &[BidProposal],
+    key: impl Fn(&BidProposal) -> f64,
+) -> Vec<(&BidProposal, f64)> {
+    let mut bids: Vec<_> = bids
+        .iter()
+        .map(|bid| (bid, key(bid)))
+        .filter(|(_, key)| key.is_finite())
+        .collect();
+    bids.sort_unstable_by(|(_, a), (_, b)| a.total_cmp(b));
+    bids
 }
 
-impl Auction {
-    #[cfg(feature = "random")]
-    pub fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
-        use rand::Rng;
+#[derive(Debug)]
+pub struct FirstPrice;
+
+impl AuctionMechanism for FirstPrice {
+    fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
+        let (first, _) =
+            sorted_by_key(bids, |bid| bid.bid).first().cloned()?;
+        Some(ChosenBid { price: first.bid, bid: first.clone() })
+    }
+}
+
+#[derive(Debug)]
+pub struct SecondPrice;
+
+impl AuctionMechanism for SecondPrice {
+    fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
+        let bids = sorted_by_key(bids, |bid| bid.bid);
+        let (first, _) = bids.first().cloned()?;
+        let price = bids.get(1).map_or(first.bid, |(second, _)| second.bid);
+        Some(ChosenBid { price, bid: first.clone() })
+    }
+}
+
+#[derive(Debug)]
+pub struct RandomWinner;
+
+impl AuctionMechanism for RandomWinner {
+    fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
+        use rand::seq::SliceRandom;
+
+        let bids = sorted_by_key(bids, |bid| bid.bid);
+        let (chosen, _) = bids.choose(&mut rand::thread_rng()).cloned()?;
+        Some(ChosenBid { price: chosen.bid, bid: chosen.clone() })
+    }
+}
 
-        let bids = bids.iter().collect::<Vec<_>>();
-        if bids.len() == 0 {
-            return None;
+#[derive(Debug)]
+pub struct ReservePrice {
+    pub reserve: f64,
+}
+
+impl AuctionMechanism for ReservePrice {
+    fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
+        let bids: Vec<_> = sorted_by_key(bids, |bid| bid.bid)
+            .into_iter()
+            .filter(|(bid, _)| bid.bid <= self.reserve)
+            .collect();
+        let (first, _) = bids.first().cloned()?;
+        let price = bids.get(1).map_or(self.reserve, |(second, _)| second.bid);
+        Some(ChosenBid { price, bid: first.clone() })
+    }
+}
+
+#[derive(Debug)]
+pub struct WeightedScore {
+    pub latency_weight: f64,
+}
+
+impl WeightedScore {
+    fn latency_cost(&self, bid: &BidProposal) -> f64 {
+        self.latency_weight * bid.accumulated_latency.get::<millisecond>()
+    }
+}
+
+impl AuctionMechanism for WeightedScore {
+    fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
+        let bids = sorted_by_key(bids, |bid| bid.bid + self.latency_cost(bid));
+        let (first, _) = bids.first().cloned()?;
+        // Never less than the bid itself
+        let price = bids.get(1).map_or(first.bid, |(_, second_score)| {
+            (second_score - self.latency_cost(first)).max(first.bid)
+        });
+        Some(ChosenBid { price, bid: first.clone() })
+    }
+}
+
+/// Build the mechanism described by the configuration
+pub fn mechanism_from_kind(
+    kind: AuctionMechanismKind,
+) -> Box<dyn AuctionMechanism> {
+    match kind {
+        AuctionMechanismKind::FirstPrice => Box::new(FirstPrice),
+        AuctionMechanismKind::SecondPrice => Box::new(SecondPrice),
+        AuctionMechanismKind::Random => Box::new(RandomWinner),
+        AuctionMechanismKind::ReservePrice { reserve } => {
+            Box::new(ReservePrice { reserve })
+        }
+        AuctionMechanismKind::WeightedScore { latency_weight } => {
+            Box::new(WeightedScore { latency_weight })
         }
-        let random_index = rand::thread_rng().gen_range(0..bids.len());
-        let chosen = bids.get(random_index).cloned().cloned().unwrap();
-        Some(ChosenBid { price: chosen.bid, bid: chosen })
-    }
-
-    #[cfg(not(feature = "random"))]
-    pub fn auction(&self, bids: &[BidProposal]) -> Option<ChosenBid> {
-        let mut bids = bids.iter().collect::<Vec<_>>();
-        bids.sort_unstable_by(|a, b| a.bid.partial_cmp(&b.bid).unwrap()); // Sort asc
-        let first = bids.get(0).cloned().cloned();
-        let second = bids.get(1);
-        match (first, second) {
-            (Some(first), Some(second)) => {
-                Some(ChosenBid { price: second.bid, bid: first })
-            }
-            (Some(first), None) => {
-                Some(ChosenBid { price: first.bid, bid: first })
-            }
-            _ => None,
+    }
+}
+
+pub struct Auction {
+    /// Used for the slas that do not choose their own
+    default_mechanism: AuctionMechanismKind,
+}
+
+impl Auction {
+    pub fn new(default_mechanism: AuctionMechanismKind) -> Self {
+        Self { default_mechanism }
+    }
+
+    pub fn auction(
+        &self,
+        bids: &[BidProposal],
+        mechanism: Option<AuctionMechanismKind>,
+    ) -> Option<ChosenBid> {
+        mechanism_from_kind(mechanism.unwrap_or(self.default_mechanism))
+            .auction(bids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::{BidId, NodeId};
+    use uom::si::f64::Time;
+    use uuid::Uuid;
+
+    fn bid(bid: f64, latency_ms: f64) -> BidProposal {
+        BidProposal {
+            node_id: NodeId::from(Uuid::new_v4()),
+            id: BidId::from(Uuid::new_v4()),
+            bid,
+            accumulated_latency: Time::new::<millisecond>(latency_ms),
         }
     }
+
+    /// The winning bid, by its id, and the price paid
+    fn outcome(
+        mechanism: &dyn AuctionMechanism,
+        bids: &[BidProposal],
+    ) -> Option<(BidId, f64)> {
+        mechanism.auction(bids).map(|chosen| (chosen.bid.id, chosen.price))
+    }
+
+    #[test]
+    fn test_first_price() {
+        let bids = [bid(3.0, 0.0), bid(1.0, 0.0), bid(2.0, 0.0)];
+        assert_eq!(
+            outcome(&FirstPrice, &bids),
+            Some((bids[1].id.clone(), 1.0))
+        );
+        assert_eq!(outcome(&FirstPrice, &[]), None);
+    }
+
+    #[test]
+    fn test_second_price() {
+        let bids = [bid(3.0, 0.0), bid(1.0, 0.0), bid(2.0, 0.0)];
+        assert_eq!(
+            outcome(&SecondPrice, &bids),
+            Some((bids[1].id.clone(), 2.0))
+        );
+        // Alone, the winner is paid its own bid
+        assert_eq!(
+            outcome(&SecondPrice, &bids[..1]),
+            Some((bids[0].id.clone(), 3.0))
+        );
+        assert_eq!(outcome(&SecondPrice, &[]), None);
+    }
+
+    #[test]
+    fn test_reserve_price() {
+        let reserve = ReservePrice { reserve: 2.5 };
+        let bids = [bid(3.0, 0.0), bid(1.0, 0.0), bid(2.0, 0.0)];
+        assert_eq!(outcome(&reserve, &bids), Some((bids[1].id.clone(), 2.0)));
+
+        // The bids above the reserve do not set the price
+        let bids = [bid(3.0, 0.0), bid(1.0, 0.0)];
+        assert_eq!(outcome(&reserve, &bids), Some((bids[1].id.clone(), 2.5)));
+
+        assert_eq!(outcome(&reserve, &[bid(3.0, 0.0)]), None);
+    }
+
+    #[test]
+    fn test_weighted_score() {
+        let weighted = WeightedScore { latency_weight: 0.5 };
+        // Scores of 6, 3 and 5
+        let bids = [bid(1.0, 10.0), bid(3.0, 0.0), bid(2.0, 6.0)];
+        assert_eq!(outcome(&weighted, &bids), Some((bids[1].id.clone(), 5.0)));
+
+        // The winner is paid up to the second score, minus its own latency
+        let bids = [bid(1.0, 2.0), bid(3.0, 0.0)];
+        assert_eq!(outcome(&weighted, &bids), Some((bids[0].id.clone(), 2.0)));
+
+        // Without weight, it is a second price auction
+        let plain = WeightedScore { latency_weight: 0.0 };
+        let bids = [bid(1.0, 10.0), bid(3.0, 0.0)];
+        assert_eq!(outcome(&plain, &bids), Some((bids[0].id.clone(), 3.0)));
+    }
+
+    #[test]
+    fn test_non_finite_bids_left_out() {
+        let bids = [
+            bid(f64::NAN, 0.0),
+            bid(2.0, 0.0),
+            bid(f64::INFINITY, 0.0),
+            bid(1.0, 0.0),
+            bid(f64::NEG_INFINITY, 0.0),
+        ];
+        let sorted: Vec<_> = sorted_by_key(&bids, |bid| bid.bid)
+            .into_iter()
+            .map(|(bid, key)| (bid.id.clone(), key))
+            .collect();
+        assert_eq!(
+            sorted,
+            vec![(bids[3].id.clone(), 1.0), (bids[1].id.clone(), 2.0)]
+        );
+
+        assert_eq!(
+            outcome(&FirstPrice, &bids),
+            Some((bids[3].id.clone(), 1.0))
+        );
+        assert_eq!(
+            outcome(&SecondPrice, &bids),
+            Some((bids[3].id.clone(), 2.0))
+        );
+        let nan = [bid(f64::NAN, 0.0), bid(f64::NAN, 1.0)];
+        assert_eq!(outcome(&SecondPrice, &nan), None);
+        // A score that is not a number does not win either
+        let weighted = WeightedScore { latency_weight: f64::NAN };
+        assert_eq!(outcome(&weighted, &bids), None);
+    }
 }
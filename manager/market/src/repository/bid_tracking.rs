@@ -1,22 +1,57 @@
 use dashmap::DashMap;
+use model::domain::auction::AuctionMechanismKind;
+use model::view::auction::{AcceptedBid, BidProposal};
 use model::{NodeId, SlaId};
 
+/// How the winner is chosen among the candidates
+#[derive(Debug, Clone, Copy)]
+pub enum Selection {
+    /// By the auction mechanism, the one chosen by the sla over the one of
+    /// the market
+    Auction(Option<AuctionMechanismKind>),
+    /// The candidates are ranked by the global placement of the market,
+    /// the first one is paid its own bid
+    Ranked,
+}
+
+/// The auction won for a sla and the bids to fall back on, should the
+/// winner fail
+#[derive(Debug, Clone)]
+pub struct TrackedAuction {
+    pub accepted:   AcceptedBid,
+    /// Bids that were not tried yet
+    pub candidates: Vec<BidProposal>,
+    /// Number of winners that failed so far
+    pub fallbacks:  u64,
+    pub selection:  Selection,
+}
+
+impl TrackedAuction {
+    pub fn node(&self) -> &NodeId { &self.accepted.chosen.bid.node_id }
+
+    /// The nodes hosting an instance of the function
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeId> {
+        self.accepted.instances().map(|instance| &instance.bid.node_id)
+    }
+}
+
 #[derive(Debug)]
 pub struct BidTracking {
-    node_association: dashmap::DashMap<SlaId, NodeId>,
+    auctions: dashmap::DashMap<SlaId, TrackedAuction>,
 }
 
 impl BidTracking {
-    pub fn new() -> Self { Self { node_association: DashMap::new() } }
+    pub fn new() -> Self { Self { auctions: DashMap::new() } }
+
+    pub fn save(&self, id: SlaId, auction: TrackedAuction) {
+        self.auctions.insert(id, auction);
+    }
 
-    pub fn save(&self, id: SlaId, node: NodeId) {
-        self.node_association.insert(id, node);
+    pub fn get(&self, id: &SlaId) -> Option<TrackedAuction> {
+        self.auctions.get(id).map(|auction| auction.clone())
     }
 
-    pub fn get(&self, id: &SlaId) -> Option<NodeId> {
-        match self.node_association.get(id) {
-            Some(node) => Some(node.clone()),
-            None => None,
-        }
+    pub fn remove(&self, id: &SlaId) -> Option<TrackedAuction> {
+        self.auctions.remove(id).map(|(_, auction)| auction)
     }
 }
@@ -1,6 +1,9 @@
 use anyhow::{anyhow, ensure, Result};
-use model::dto::node::{Node, NodeRecord};
+use chrono::{DateTime, Duration, Utc};
+use helper::http_error::ErrorCode;
+use model::dto::node::{Liveness, Node, NodeRecord};
 use model::view::auction::AcceptedBid;
+use model::view::node::NodeCapacity;
 use model::{FogNodeFaaSPortExternal, FogNodeHTTPPort, NodeId};
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -16,31 +19,34 @@ impl FogNode {
     pub fn new() -> Self { Self { nodes: RwLock::new(HashMap::new()) } }
 
     async fn check_tree(&self) -> Result<()> {
-        let mut roots = self
-            .nodes
-            .read()
-            .await
+        Self::check_nodes(&*self.nodes.read().await)
+    }
+
+    /// Check the nodes form a single tree, callers holding the write lock
+    /// check it before letting anyone else see their changes
+    fn check_nodes(nodes: &HashMap<NodeId, Node<NodeRecord>>) -> Result<()> {
+        let mut roots = nodes
             .iter()
             .filter(|(_id, node)| node.parent.is_none())
             .map(|(id, _node)| id.clone())
             .collect::<Vec<_>>();
 
+        if nodes.is_empty() {
+            return Ok(());
+        }
         ensure!(!roots.is_empty(), "The tree doesn't have a root");
         ensure!(roots.len() == 1, "The tree have more than a single root");
 
         let root = roots.pop().unwrap();
         let mut stack = vec![root];
+        let mut reached = 0;
         while let Some(id) = stack.pop() {
-            let node_children = self
-                .nodes
-                .read()
-                .await
-                .get(&id)
-                .map(|node| node.children.clone())
-                .unwrap();
+            reached += 1;
+            let node_children =
+                nodes.get(&id).map(|node| node.children.clone()).unwrap();
             for child in node_children.iter() {
                 ensure!(
-                    self.nodes.read().await.contains_key(child),
+                    nodes.contains_key(child),
                     "Node {} child's doesn't exists: {}",
                     id,
                     child
@@ -48,9 +54,7 @@ impl FogNode {
             }
 
             for child in node_children.iter() {
-                if let Some(parent) =
-                    &self.nodes.read().await.get(child).unwrap().parent
-                {
+                if let Some(parent) = &nodes.get(child).unwrap().parent {
                     ensure!(
                         *parent == id,
                         "Node {} parent's doesn't exists: {}",
@@ -63,9 +67,39 @@ impl FogNode {
             stack.extend(node_children.iter().cloned());
         }
 
+        ensure!(
+            reached == nodes.len(),
+            "Some nodes of the tree are not reachable from the root"
+        );
+
         Ok(())
     }
 
+    /// Detach the node from its parent and remove it along with its whole
+    /// subtree. Returns the removed nodes.
+    fn remove_subtree(
+        nodes: &mut HashMap<NodeId, Node<NodeRecord>>,
+        id: &NodeId,
+    ) -> Vec<NodeId> {
+        let Some(node) = nodes.get(id) else {
+            return vec![];
+        };
+        if let Some(parent) = node.parent.clone() {
+            if let Some(parent) = nodes.get_mut(&parent) {
+                parent.children.retain(|child| child != id);
+            }
+        }
+        let mut removed = Vec::new();
+        let mut stack = vec![id.clone()];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = nodes.remove(&id) {
+                stack.extend(node.children);
+                removed.push(id);
+            }
+        }
+        removed
+    }
+
     // async fn print_tree(&self) {
     //     let to_print =
     //         serde_json::to_string_pretty(&*self.nodes.read().await).
@@ -82,6 +116,126 @@ impl FogNode {
         }
     }
 
+    /// Refresh the liveness of a node and what it reported, returns false if
+    /// it is unknown
+    pub async fn touch(
+        &self,
+        id: &NodeId,
+        now: DateTime<Utc>,
+        capacity: Option<NodeCapacity>,
+    ) -> bool {
+        match self.nodes.write().await.get_mut(id) {
+            Some(node) => {
+                node.data.last_seen = now;
+                node.data.liveness = Liveness::Alive;
+                node.data.capacity = capacity;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mark the nodes silent for longer than `suspect` as suspected and
+    /// remove the ones silent for longer than `evict`, along with their
+    /// whole subtree. The root is only ever suspected: the market reaches
+    /// the network through it. Returns the removed nodes.
+    pub async fn evict_silent(
+        &self,
+        now: DateTime<Utc>,
+        suspect: Duration,
+        evict: Duration,
+    ) -> Result<Vec<NodeId>> {
+        let mut nodes = self.nodes.write().await;
+        let mut silent = Vec::new();
+        for (id, node) in nodes.iter_mut() {
+            let silence = now - node.data.last_seen;
+            if silence > evict && node.parent.is_some() {
+                silent.push(id.clone());
+            } else if silence > suspect {
+                node.data.liveness = Liveness::Suspected;
+            }
+        }
+
+        // Already removed ones were in the subtree of an ancestor
+        let evicted = silent
+            .iter()
+            .flat_map(|id| Self::remove_subtree(&mut nodes, id))
+            .collect();
+        drop(nodes);
+
+        self.check_tree().await?;
+        Ok(evicted)
+    }
+
+    /// Remove a node leaving the network, its subtree can no longer reach
+    /// the market and goes away with it. Returns the removed nodes.
+    pub async fn remove(&self, id: &NodeId) -> Result<Vec<NodeId>> {
+        let mut nodes = self.nodes.write().await;
+        nodes.get(id).ok_or_else(|| {
+            ErrorCode::NotFound.error(format!("Node {} doesn't exist", id))
+        })?;
+        let removed = Self::remove_subtree(&mut nodes, id);
+        drop(nodes);
+
+        self.check_tree().await?;
+        Ok(removed)
+    }
+
+    /// Re-attach a node and its subtree under another parent, which cannot
+    /// be in that subtree. Nothing changes if the node is already attached
+    /// there, or if the tree is not consistent once it moved.
+    pub async fn reparent(&self, id: &NodeId, to: &NodeId) -> Result<()> {
+        let mut nodes = self.nodes.write().await;
+        nodes.get(to).ok_or_else(|| {
+            ErrorCode::NotFound
+                .error(format!("The new parent {} doesn't exist", to))
+        })?;
+        let node = nodes.get(id).ok_or_else(|| {
+            ErrorCode::NotFound.error(format!("Node {} doesn't exist", id))
+        })?;
+        let Some(from) = node.parent.clone() else {
+            return Err(ErrorCode::Conflict
+                .error(format!("Node {} is the root, it cannot move", id)));
+        };
+        if &from == to {
+            return Ok(());
+        }
+        let mut ancestor = Some(to);
+        while let Some(current) = ancestor {
+            if current == id {
+                return Err(ErrorCode::Conflict.error(format!(
+                    "Cannot move node {} under {}, which is in its subtree",
+                    id, to
+                )));
+            }
+            ancestor =
+                nodes.get(current).and_then(|node| node.parent.as_ref());
+        }
+
+        let pos = nodes.get_mut(&from).and_then(|parent| {
+            let pos = parent.children.iter().position(|child| child == id);
+            parent.children.retain(|child| child != id);
+            pos
+        });
+        nodes.get_mut(to).unwrap().children.push(id.clone());
+        nodes.get_mut(id).unwrap().parent = Some(to.clone());
+
+        // Still under the lock, nobody sees the move unless it holds
+        let result = Self::check_nodes(&nodes);
+        if result.is_err() {
+            if let Some(parent) = nodes.get_mut(to) {
+                parent.children.retain(|child| child != id);
+            }
+            if let (Some(parent), Some(pos)) = (nodes.get_mut(&from), pos) {
+                parent.children.insert(pos, id.clone());
+            }
+            if let Some(node) = nodes.get_mut(id) {
+                node.parent = Some(from);
+            }
+        }
+        result
+    }
+
     pub async fn append_new_child(
         &self,
         parent: &NodeId,
@@ -96,11 +250,10 @@ impl FogNode {
             .await
             .get_mut(parent)
             .ok_or_else(|| {
-                anyhow!(
+                ErrorCode::NotFound.error(format!(
                     "The parent of {} (which is supposedly {}) doesn't exist",
-                    child,
-                    parent
-                )
+                    child, parent
+                ))
             })?
             .children
             .push(child.clone());
@@ -184,6 +337,11 @@ impl FogNode {
         res
     }
 
+    /// A copy of the whole tree, as it is now
+    pub async fn get_tree(&self) -> HashMap<NodeId, Node<NodeRecord>> {
+        self.nodes.read().await.clone()
+    }
+
     pub async fn get_records(&self) -> HashMap<NodeId, Vec<AcceptedBid>> {
         let mut records: HashMap<NodeId, Vec<AcceptedBid>> = HashMap::new();
         for (node, data) in &*self.nodes.read().await {
@@ -205,3 +363,214 @@ impl FogNode {
             .collect();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helper::http_error::HttpError;
+    use uuid::Uuid;
+
+    /// A root with two children, the first one having a child of its own
+    struct Tree {
+        fog_node:   FogNode,
+        root:       NodeId,
+        child:      NodeId,
+        grandchild: NodeId,
+        sibling:    NodeId,
+    }
+
+    async fn get_tree(now: DateTime<Utc>) -> Tree {
+        let fog_node = FogNode::new();
+        let ip = "127.0.0.1".parse().unwrap();
+        let [root, child, grandchild, sibling] =
+            [(); 4].map(|_| NodeId::from(Uuid::new_v4()));
+        fog_node
+            .append_root(root.clone(), ip, 0.into(), 0.into(), &[])
+            .await
+            .unwrap();
+        for (parent, node) in
+            [(&root, &child), (&child, &grandchild), (&root, &sibling)]
+        {
+            fog_node
+                .append_new_child(
+                    parent,
+                    node.clone(),
+                    ip,
+                    0.into(),
+                    0.into(),
+                    &[],
+                )
+                .await
+                .unwrap();
+        }
+        for node in [&root, &child, &grandchild, &sibling] {
+            assert!(fog_node.touch(node, now, None).await);
+        }
+        Tree { fog_node, root, child, grandchild, sibling }
+    }
+
+    fn timeouts() -> (Duration, Duration) {
+        (Duration::seconds(10), Duration::seconds(20))
+    }
+
+    async fn liveness(fog_node: &FogNode, id: &NodeId) -> Liveness {
+        fog_node.get(id).await.expect("Node was removed").data.liveness
+    }
+
+    #[tokio::test]
+    async fn test_evict_thresholds() {
+        let now = Utc::now();
+        let Tree { fog_node, root, child, grandchild, sibling } =
+            get_tree(now).await;
+        let (suspect, evict) = timeouts();
+        fog_node.touch(&child, now - Duration::seconds(15), None).await;
+        fog_node.touch(&sibling, now - Duration::seconds(25), None).await;
+
+        let evicted =
+            fog_node.evict_silent(now, suspect, evict).await.unwrap();
+
+        assert_eq!(evicted, vec![sibling.clone()]);
+        assert!(fog_node.get(&sibling).await.is_none());
+        assert_eq!(liveness(&fog_node, &child).await, Liveness::Suspected);
+        assert_eq!(liveness(&fog_node, &grandchild).await, Liveness::Alive);
+        assert_eq!(liveness(&fog_node, &root).await, Liveness::Alive);
+        assert!(!fog_node
+            .get(&root)
+            .await
+            .unwrap()
+            .children
+            .contains(&sibling));
+        fog_node.check_tree().await.unwrap();
+
+        // A heartbeat clears the suspicion
+        assert!(fog_node.touch(&child, now, None).await);
+        assert_eq!(liveness(&fog_node, &child).await, Liveness::Alive);
+        assert!(!fog_node.touch(&sibling, now, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_evict_subtree() {
+        let now = Utc::now();
+        let Tree { fog_node, root, child, grandchild, sibling } =
+            get_tree(now).await;
+        let (suspect, evict) = timeouts();
+        fog_node.touch(&child, now - Duration::seconds(25), None).await;
+
+        let mut evicted =
+            fog_node.evict_silent(now, suspect, evict).await.unwrap();
+        evicted.sort_by_key(|id| id.to_string());
+        let mut expected = vec![child.clone(), grandchild.clone()];
+        expected.sort_by_key(|id| id.to_string());
+
+        // The fresh grandchild cannot reach the market anymore
+        assert_eq!(evicted, expected);
+        assert!(fog_node.get(&grandchild).await.is_none());
+        assert_eq!(fog_node.get(&root).await.unwrap().children, vec![sibling]);
+        assert_eq!(fog_node.get_nodes().await.len(), 2);
+        fog_node.check_tree().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_leave() {
+        let Tree { fog_node, root, child, grandchild, sibling } =
+            get_tree(Utc::now()).await;
+
+        let mut removed = fog_node.remove(&child).await.unwrap();
+        removed.sort_by_key(|id| id.to_string());
+        let mut expected = vec![child.clone(), grandchild.clone()];
+        expected.sort_by_key(|id| id.to_string());
+
+        assert_eq!(removed, expected);
+        assert_eq!(fog_node.get(&root).await.unwrap().children, vec![sibling]);
+        fog_node.check_tree().await.unwrap();
+        let err = fog_node.remove(&child).await.unwrap_err();
+        assert_eq!(HttpError::from(err).code(), ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_move() {
+        let Tree { fog_node, root, child, grandchild, sibling } =
+            get_tree(Utc::now()).await;
+
+        fog_node.reparent(&grandchild, &sibling).await.unwrap();
+
+        let moved = fog_node.get(&grandchild).await.unwrap();
+        assert_eq!(moved.parent, Some(sibling.clone()));
+        assert!(fog_node.get(&child).await.unwrap().children.is_empty());
+        assert_eq!(
+            fog_node.get(&sibling).await.unwrap().children,
+            vec![grandchild.clone()]
+        );
+        fog_node.check_tree().await.unwrap();
+
+        // Moving under the current parent changes nothing
+        fog_node.reparent(&grandchild, &sibling).await.unwrap();
+        assert_eq!(fog_node.get(&sibling).await.unwrap().children.len(), 1);
+        let err = fog_node.reparent(&root, &child).await.unwrap_err();
+        assert_eq!(HttpError::from(err).code(), ErrorCode::Conflict);
+    }
+
+    #[tokio::test]
+    async fn test_move_into_own_subtree() {
+        let Tree { fog_node, root, child, grandchild, .. } =
+            get_tree(Utc::now()).await;
+        let before = fog_node.get_tree().await;
+
+        for to in [&grandchild, &child] {
+            let err = fog_node.reparent(&child, to).await.unwrap_err();
+            assert_eq!(HttpError::from(err).code(), ErrorCode::Conflict);
+        }
+
+        // Nothing changed, not even for a while
+        let after = fog_node.get_tree().await;
+        for (id, node) in before.iter() {
+            assert_eq!(after[id].parent, node.parent);
+            assert_eq!(after[id].children, node.children);
+        }
+        assert_eq!(after[&child].parent, Some(root));
+        fog_node.check_tree().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_move_in_inconsistent_tree() {
+        let Tree { fog_node, child, grandchild, sibling, .. } =
+            get_tree(Utc::now()).await;
+        // A node that cannot be reached from the root
+        let mut orphan = fog_node.get(&sibling).await.unwrap();
+        orphan.parent = Some(NodeId::from(Uuid::new_v4()));
+        orphan.children = vec![];
+        fog_node
+            .nodes
+            .write()
+            .await
+            .insert(NodeId::from(Uuid::new_v4()), orphan);
+        fog_node.check_tree().await.unwrap_err();
+
+        fog_node.reparent(&grandchild, &sibling).await.unwrap_err();
+
+        // The move was undone
+        let moved = fog_node.get(&grandchild).await.unwrap();
+        assert_eq!(moved.parent, Some(child.clone()));
+        assert_eq!(
+            fog_node.get(&child).await.unwrap().children,
+            vec![grandchild]
+        );
+        assert!(fog_node.get(&sibling).await.unwrap().children.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_root_never_evicted() {
+        let now = Utc::now();
+        let Tree { fog_node, root, .. } = get_tree(now).await;
+        let (suspect, evict) = timeouts();
+        fog_node.touch(&root, now - Duration::seconds(60), None).await;
+
+        let evicted =
+            fog_node.evict_silent(now, suspect, evict).await.unwrap();
+
+        assert!(evicted.is_empty());
+        assert_eq!(liveness(&fog_node, &root).await, Liveness::Suspected);
+        assert_eq!(fog_node.get_nodes().await.len(), 4);
+        fog_node.check_tree().await.unwrap();
+    }
+}
@@ -3,7 +3,9 @@ use anyhow::{bail, Context, Result};
 use model::domain::sla::Sla;
 use model::dto::node::NodeRecord;
 use model::view::auction::{AccumulatedLatency, BidProposals, BidRequest};
-use model::{NodeId, SlaId};
+use model::view::function::FunctionStatus;
+use model::view::sla::{ExtendSla, SlaExtension};
+use model::{BidRequestId, NodeId, SlaId};
 use std::fmt::Debug;
 use std::sync::Arc;
 use tracing::instrument;
@@ -21,9 +23,10 @@ impl NodeCommunication {
         Self { network, client }
     }
 
-    /// Sends to <ip:port from nodeId>/api/<route>
-    async fn send(
+    /// Requests <ip:port from nodeId>/api/<route> with the method
+    async fn request(
         &self,
+        method: reqwest::Method,
         to: &NodeId,
         route: &str,
     ) -> Result<reqwest_middleware::RequestBuilder> {
@@ -33,7 +36,18 @@ impl NodeCommunication {
             bail!("Failed to find a record correspod to the node {}", to);
         };
 
-        Ok(self.client.post(format!("http://{ip}:{port_http}/api/{route}")))
+        Ok(self
+            .client
+            .request(method, format!("http://{ip}:{port_http}/api/{route}")))
+    }
+
+    /// Sends to <ip:port from nodeId>/api/<route>
+    async fn send(
+        &self,
+        to: &NodeId,
+        route: &str,
+    ) -> Result<reqwest_middleware::RequestBuilder> {
+        self.request(reqwest::Method::POST, to, route).await
     }
 
     #[instrument(level = "trace", skip(self))]
@@ -41,6 +55,8 @@ impl NodeCommunication {
         &self,
         to: NodeId,
         sla: &'_ Sla,
+        request_id: BidRequestId,
+        hop_budget: u32,
     ) -> Result<BidProposals> {
         let response = self
             .send(&to, "bid")
@@ -52,8 +68,10 @@ impl NodeCommunication {
                 sla,
                 node_origin: to.clone(),
                 accumulated_latency: AccumulatedLatency::default(),
-                #[cfg(feature = "mincpurandom")]
                 nb_propositions_required: 2,
+                request_id,
+                hop_budget,
+                visited: vec![],
             })
             .send()
             .await
@@ -109,6 +127,129 @@ impl NodeCommunication {
         Ok(())
     }
 
+    /// Tell the node its bid lost, so it can release what it held for it
+    #[instrument(level = "trace", skip(self))]
+    pub async fn reject_offer(&self, to: NodeId, id: &SlaId) -> Result<()> {
+        let resp = self
+            .send(&to, &format!("reject/{}", id))
+            .await
+            .with_context(|| {
+                format!("Failed to obtained the url to contact {}", to)
+            })?
+            .send()
+            .await
+            .with_context(|| {
+                format!("Failed to send a rejection to {}", to)
+            })?;
+        resp.error_for_status().with_context(|| {
+            format!(
+                "Failed to send a rejection to {}, the response errored",
+                to
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Stop the function before the end of its duration
+    #[instrument(level = "trace", skip(self))]
+    pub async fn terminate_function(
+        &self,
+        to: NodeId,
+        id: &SlaId,
+    ) -> Result<()> {
+        let resp = self
+            .request(reqwest::Method::DELETE, &to, &format!("function/{}", id))
+            .await
+            .with_context(|| {
+                format!("Failed to obtained the url to contact {}", to)
+            })?
+            .send()
+            .await
+            .with_context(|| {
+                format!("Failed to send a termination to {}", to)
+            })?;
+        resp.error_for_status().with_context(|| {
+            format!(
+                "Failed to send a termination to {}, the response errored",
+                to
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Keep the function running for longer, returns the priced extension
+    #[instrument(level = "trace", skip(self))]
+    pub async fn extend_function(
+        &self,
+        to: NodeId,
+        id: &SlaId,
+        extension: &ExtendSla,
+    ) -> Result<SlaExtension> {
+        let resp = self
+            .send(&to, &format!("function/{}/extend", id))
+            .await
+            .with_context(|| {
+                format!("Failed to obtained the url to contact {}", to)
+            })?
+            .json(extension)
+            .send()
+            .await
+            .with_context(|| {
+                format!("Failed to send an extension to {}", to)
+            })?;
+        let resp = resp.error_for_status().with_context(|| {
+            format!(
+                "Failed to send an extension to {}, the response errored",
+                to
+            )
+        })?;
+
+        helper::reqwest_helper::deserialize_response(resp).await.with_context(
+            || {
+                format!(
+                    "Failed to deserialize the response when trying to \
+                     extend                      {} on node {}",
+                    id, to
+                )
+            },
+        )
+    }
+
+    /// Where the function is in its lifecycle on the node
+    #[instrument(level = "trace", skip(self))]
+    pub async fn get_function_status(
+        &self,
+        to: NodeId,
+        id: &SlaId,
+    ) -> Result<FunctionStatus> {
+        let resp = self
+            .request(reqwest::Method::GET, &to, &format!("function/{}", id))
+            .await
+            .with_context(|| {
+                format!("Failed to obtained the url to contact {}", to)
+            })?
+            .send()
+            .await
+            .with_context(|| {
+                format!("Failed to ask {} for the status of {}", to, id)
+            })?;
+        let resp = resp.error_for_status().with_context(|| {
+            format!(
+                "Failed to ask {} for the status of {}, the response errored",
+                to, id
+            )
+        })?;
+
+        helper::reqwest_helper::deserialize_response(resp).await.with_context(
+            || {
+                format!(
+                    "Failed to deserialize the status of {} from node {}",
+                    id, to
+                )
+            },
+        )
+    }
+
     #[instrument(level = "trace", skip(self))]
     pub async fn provision_function(
         &self,
@@ -1,20 +1,46 @@
 use super::faas::FogNodeFaaS;
 use super::fog_node_network::FogNodeNetwork;
-use crate::monitoring::FunctionDeploymentDuration;
+use super::global_placement::{self, GlobalView};
+use crate::monitoring::{
+    AuctionFallback, FunctionDeploymentDuration, FunctionExtension,
+};
 use crate::repository::auction::Auction as AuctionRepository;
-use crate::repository::bid_tracking::BidTracking;
+use crate::repository::bid_tracking::{
+    BidTracking, Selection, TrackedAuction,
+};
 use crate::repository::node_communication::NodeCommunication;
+use crate::{AUCTION_MAX_FALLBACKS, BID_HOP_BUDGET, MARKET_PLACEMENT};
 use anyhow::{anyhow, Context, Result};
 use chrono::Utc;
+use helper::env_load;
+use helper::err::IndividualErrorList;
+use helper::http_error::{CodeContext, ErrorCode};
 use helper::monitoring::MetricsExporter;
-use model::domain::auction::AuctionResult;
+use model::domain::auction::{AuctionMechanismKind, AuctionResult};
 use model::domain::sla::Sla;
 use model::dto::function::ChosenBid;
-use model::dto::node::NodeRecord;
-use model::view::auction::{AcceptedBid, BidProposals, InstanciatedBid};
-use model::{NodeId, SlaId};
+use model::dto::node::{MarketPlacementKind, NodeRecord};
+use model::view::auction::{
+    AcceptedBid, BidProposal, BidProposals, InstanciatedBid,
+};
+use model::view::function::GetFunction;
+use model::view::sla::ExtendSla;
+use model::{BidRequestId, NodeId, SlaId};
+use nutype::nutype;
+use std::collections::HashSet;
 use std::sync::Arc;
-use tracing::trace;
+use tracing::{info, trace, warn};
+use uom::si::time::millisecond;
+use uuid::Uuid;
+
+/// How many times the market falls back on the next best bid when the
+/// chosen node fails
+#[nutype(derive(Clone, Debug), validate(less_or_equal = 64))]
+pub struct MaxFallbacks(usize);
+
+/// How many times a call for bids may be forwarded from node to node
+#[nutype(derive(Clone, Debug), validate(less_or_equal = 1024))]
+pub struct BidHopBudget(u32);
 
 pub struct Auction {
     auction_process:    Arc<AuctionRepository>,
@@ -23,6 +49,9 @@ pub struct Auction {
     faas:               Arc<FogNodeFaaS>,
     metrics:            Arc<MetricsExporter>,
     tracking:           Arc<BidTracking>,
+    max_fallbacks:      MaxFallbacks,
+    hop_budget:         BidHopBudget,
+    placement:          MarketPlacementKind,
 }
 
 impl Auction {
@@ -33,65 +62,511 @@ impl Auction {
         faas: Arc<FogNodeFaaS>,
         metrics: Arc<MetricsExporter>,
         tracking: Arc<BidTracking>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        let max_fallbacks =
+            env_load!(MaxFallbacks, AUCTION_MAX_FALLBACKS, usize);
+        let hop_budget = env_load!(BidHopBudget, BID_HOP_BUDGET, u32);
+        let placement = match std::env::var(MARKET_PLACEMENT) {
+            Ok(placement) => placement.parse().with_context(|| {
+                format!("{} was not formatted right", MARKET_PLACEMENT)
+            })?,
+            Err(_) => MarketPlacementKind::default(),
+        };
+        info!("Using {} placement", placement);
+        Ok(Self {
             auction_process,
             node_communication,
             fog_node_network,
             faas,
             metrics,
             tracking,
-        }
+            max_fallbacks,
+            hop_budget,
+            placement,
+        })
     }
 
+    /// Ask the node for bids, and its neighbors as long as the hop budget
+    /// allows it
     async fn call_for_bids(
         &self,
         to: NodeId,
         sla: &'_ Sla,
+        hop_budget: u32,
     ) -> Result<BidProposals> {
         trace!("call for bids: {:?}", sla.id);
 
         self.node_communication
-            .request_bids_from_node(to.clone(), sla)
+            .request_bids_from_node(
+                to.clone(),
+                sla,
+                BidRequestId::from(Uuid::new_v4()),
+                hop_budget,
+            )
             .await
-            .with_context(|| format!("Failed to get bids from {}", to))
+            .with_code(ErrorCode::UpstreamFailure, || {
+                format!("Failed to get bids from {}", to)
+            })
     }
 
-    async fn do_auction(
+    /// Notify the nodes whose bids lost, in the background, so they can
+    /// release their proposals without waiting for them to expire
+    fn reject_losers(
         &self,
         proposals: &BidProposals,
+        winner: Option<&AcceptedBid>,
+        id: &SlaId,
+    ) {
+        let winners: HashSet<&NodeId> = winner
+            .into_iter()
+            .flat_map(|winner| winner.instances())
+            .map(|instance| &instance.bid.node_id)
+            .collect();
+        let losers: HashSet<NodeId> = proposals
+            .bids
+            .iter()
+            .filter(|bid| !winners.contains(&bid.node_id))
+            .map(|bid| bid.node_id.clone())
+            .collect();
+        if losers.is_empty() {
+            return;
+        }
+
+        let node_communication = self.node_communication.clone();
+        let id = id.clone();
+        tokio::spawn(async move {
+            let rejections = losers.into_iter().map(|node| {
+                let node_communication = node_communication.clone();
+                let id = id.clone();
+                async move {
+                    if let Err(err) = node_communication
+                        .reject_offer(node.clone(), &id)
+                        .await
+                    {
+                        warn!(
+                            "Failed to notify {} that its bid on {} lost: \
+                             {:?}",
+                            node, id, err
+                        );
+                    }
+                }
+            });
+            futures::future::join_all(rejections).await;
+        });
+    }
+
+    /// Give back what the paid instances hold on their nodes, then forget
+    /// them: they will not be provisioned there
+    async fn release_paid(&self, id: &SlaId, paid: &[AcceptedBid]) {
+        let releases =
+            paid.iter().flat_map(|bid| bid.instances()).map(|instance| {
+                let node = instance.bid.node_id.clone();
+                async move {
+                    if let Err(err) = self
+                        .faas
+                        .terminate_function(id.clone(), node.clone())
+                        .await
+                    {
+                        warn!(
+                            "Failed to release {} on {}: {:?}",
+                            id, node, err
+                        );
+                    }
+                }
+            });
+        futures::future::join_all(releases).await;
+        for bid in paid {
+            self.faas.discard_function(bid).await;
+        }
+    }
+
+    /// Choose among the remaining candidates, the price is computed on
+    /// them only. The sla is refused when the price is above its budget;
+    /// the nodes only bid prices on an sla with a budget, so that both
+    /// compare.
+    async fn do_auction(
+        &self,
+        sla: &Sla,
+        candidates: &[BidProposal],
+        selection: Selection,
     ) -> Result<AuctionResult> {
-        trace!("do auction: {:?}", proposals);
-        let auction_result =
-            self.auction_process.auction(&proposals.bids).ok_or_else(
-                || anyhow!("Auction failed, no winners were selected"),
-            )?;
+        trace!("do auction: {:?}", candidates);
+        let auction_result = match selection {
+            Selection::Auction(mechanism) => {
+                self.auction_process.auction(candidates, mechanism)
+            }
+            Selection::Ranked => candidates
+                .first()
+                .map(|bid| ChosenBid { bid: bid.clone(), price: bid.bid }),
+        }
+        .ok_or_else(|| {
+            ErrorCode::Unsatisfiable
+                .error("Auction failed, no winners were selected")
+        })?;
+        if let Some(max_price) =
+            sla.max_price.filter(|max_price| auction_result.price > *max_price)
+        {
+            return Err(ErrorCode::Unsatisfiable.error(format!(
+                "No affordable bid: the price of {} is above the maximum \
+                 price of {}",
+                auction_result.price, max_price
+            )));
+        }
         Ok(AuctionResult { chosen_bid: auction_result })
     }
 
+    /// Record that the chosen bid failed, and tell whether another one may
+    /// still be tried
+    async fn fall_back(
+        &self,
+        sla: &Sla,
+        failed: &BidProposal,
+        phase: &str,
+        fallbacks: &mut u64,
+        candidates: &[BidProposal],
+    ) -> Result<bool> {
+        *fallbacks += 1;
+        self.metrics
+            .observe(AuctionFallback {
+                value:         *fallbacks,
+                function_name: sla.function_live_name.clone(),
+                sla_id:        sla.id.to_string(),
+                bid_id:        failed.id.to_string(),
+                node_id:       failed.node_id.to_string(),
+                phase:         phase.to_string(),
+                timestamp:     Utc::now(),
+            })
+            .await
+            .context("Failed to save metrics")?;
+
+        Ok(*fallbacks <= self.max_fallbacks.clone().into_inner() as u64
+            && !candidates.is_empty())
+    }
+
+    /// Pay the best of the candidates, falling back on the next best one
+    /// while the chosen node fails, up to the configured limit
+    async fn pay_best_candidate(
+        &self,
+        sla: &Sla,
+        proposals: &BidProposals,
+        candidates: &mut Vec<BidProposal>,
+        fallbacks: &mut u64,
+        selection: Selection,
+    ) -> Result<AcceptedBid> {
+        loop {
+            let AuctionResult { chosen_bid } = self
+                .do_auction(sla, candidates, selection)
+                .await
+                .context("Auction failed")?;
+            candidates.retain(|bid| bid.id != chosen_bid.bid.id);
+
+            let failed = chosen_bid.bid.clone();
+            let err = match self
+                .process_paying_details(
+                    proposals.clone(),
+                    chosen_bid,
+                    sla.clone(),
+                )
+                .await
+            {
+                Ok(accepted) => return Ok(accepted),
+                Err(err) => err,
+            };
+
+            warn!(
+                "Failed to pay for the bid of {} on {}: {:?}",
+                failed.node_id, sla.id, err
+            );
+            if !self
+                .fall_back(sla, &failed, "payment", fallbacks, candidates)
+                .await?
+            {
+                return Err(err.context(format!(
+                    "Failed to pay for a bid after {} fallbacks",
+                    fallbacks
+                )));
+            }
+        }
+    }
+
+    /// Pay the best candidate, and when the sla spreads its replicas, the
+    /// next best ones until each replica has its own node
+    async fn pay_candidates(
+        &self,
+        sla: &Sla,
+        proposals: &BidProposals,
+        candidates: &mut Vec<BidProposal>,
+        fallbacks: &mut u64,
+        selection: Selection,
+    ) -> Result<AcceptedBid> {
+        let mut accepted = self
+            .pay_best_candidate(
+                sla, proposals, candidates, fallbacks, selection,
+            )
+            .await?;
+        while sla.spread_replicas
+            && (accepted.instances().count() as u64) < sla.replicas
+        {
+            let hosts: HashSet<NodeId> = accepted
+                .instances()
+                .map(|instance| instance.bid.node_id.clone())
+                .collect();
+            candidates.retain(|bid| !hosts.contains(&bid.node_id));
+            match self
+                .pay_best_candidate(
+                    sla, proposals, candidates, fallbacks, selection,
+                )
+                .await
+            {
+                Ok(replica) => accepted.replicas.push(replica.chosen),
+                Err(err) => {
+                    // Released the same way as an instance failing to
+                    // provision
+                    self.release_paid(&sla.id, &accepted.split()).await;
+                    return Err(err.context(format!(
+                        "Failed to spread the {} replicas of {} over \
+                         distinct nodes",
+                        sla.replicas, sla.id
+                    )));
+                }
+            }
+        }
+        Ok(accepted)
+    }
+
+    /// Provision the instances of the paid function, falling back on the
+    /// next best bid while the chosen node of an instance fails, up to the
+    /// configured limit
     pub async fn provision(&self, id: SlaId) -> Result<()> {
-        let node = self.tracking.get(&id).context(format!(
-            "Failed to retrieve the data (node_id) correlated to the sla id \
-             {}",
-            id
-        ))?;
+        let mut tracked = self.tracking.get(&id).code(
+            ErrorCode::NotFound,
+            format!(
+                "Failed to retrieve the data (node_id) correlated to the sla \
+                 id {}",
+                id
+            ),
+        )?;
 
-        self.faas
-            .provision_paid_function(id, node)
-            .await
-            .context("Failed to provision function")?;
+        let mut index = 0;
+        while let Some(paid) = tracked.accepted.split().get(index).cloned() {
+            let node = paid.chosen.bid.node_id.clone();
+            let err = match self
+                .faas
+                .provision_paid_function(id.clone(), node.clone())
+                .await
+                .code(
+                    ErrorCode::UpstreamFailure,
+                    "Failed to provision function",
+                ) {
+                Ok(()) => {
+                    index += 1;
+                    continue;
+                }
+                Err(err) => err,
+            };
+
+            warn!("Failed to provision {} on {}: {:?}", id, node, err);
+            // The node keeps the resources of a paid function until it ends
+            self.release_paid(&id, std::slice::from_ref(&paid)).await;
+            let TrackedAuction { accepted, candidates, fallbacks, selection } =
+                &mut tracked;
+            let sla = accepted.sla.clone();
+            // The nodes hosting the other instances cannot take this one
+            let hosts: HashSet<NodeId> = accepted
+                .instances()
+                .map(|instance| instance.bid.node_id.clone())
+                .collect();
+            candidates.retain(|bid| !hosts.contains(&bid.node_id));
+            let retry = self
+                .fall_back(
+                    &sla,
+                    &paid.chosen.bid,
+                    "provisioning",
+                    fallbacks,
+                    candidates,
+                )
+                .await?;
+            let res = if retry {
+                self.pay_best_candidate(
+                    &sla,
+                    &accepted.proposals.clone(),
+                    candidates,
+                    fallbacks,
+                    *selection,
+                )
+                .await
+            } else {
+                Err(err.context(format!(
+                    "Failed to provision function after {} fallbacks",
+                    fallbacks
+                )))
+            };
+            match res {
+                Ok(new) => *accepted.instance_mut(index) = new.chosen,
+                Err(err) => {
+                    // Nothing is left of the function without this instance
+                    let others: Vec<AcceptedBid> = tracked
+                        .accepted
+                        .split()
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(other, _)| *other != index)
+                        .map(|(_, bid)| bid)
+                        .collect();
+                    self.release_paid(&id, &others).await;
+                    self.reject_losers(&tracked.accepted.proposals, None, &id);
+                    self.tracking.remove(&id);
+                    return Err(err);
+                }
+            }
+            self.tracking.save(id.clone(), tracked.clone());
+        }
+
+        self.reject_losers(
+            &tracked.accepted.proposals,
+            Some(&tracked.accepted),
+            &id,
+        );
 
         Ok(())
     }
 
+    /// Stop the function before the end of its duration. The instances
+    /// that fail to stop stay tracked, terminating again retries them only
+    pub async fn terminate(&self, id: SlaId) -> Result<()> {
+        let mut tracked =
+            self.tracking.get(&id).with_code(ErrorCode::NotFound, || {
+                format!("No function was auctioned for the sla id {}", id)
+            })?;
+
+        // Every instance is stopped, even when one of them fails to
+        let terminations = tracked.nodes().map(|node| {
+            self.faas.terminate_function(id.clone(), node.clone())
+        });
+        let results = futures::future::join_all(terminations).await;
+        let mut left = Vec::new();
+        let mut errors = Vec::new();
+        for (instance, result) in
+            tracked.accepted.split().into_iter().zip(results)
+        {
+            match result {
+                Ok(()) => self.faas.discard_function(&instance).await,
+                Err(err) => {
+                    errors.push(err.context(format!(
+                        "Failed to terminate the instance on {}",
+                        instance.chosen.bid.node_id
+                    )));
+                    left.push(instance.chosen);
+                }
+            }
+        }
+        if left.is_empty() {
+            self.tracking.remove(&id);
+            return Ok(());
+        }
+
+        // Only the instances still running are left to terminate
+        let count = left.len();
+        let mut left = left.into_iter();
+        tracked.accepted.chosen = left.next().expect("An instance is left");
+        tracked.accepted.replicas = left.collect();
+        self.tracking.save(id.clone(), tracked);
+        Err(ErrorCode::UpstreamFailure.error(format!(
+            "Failed to terminate {} of the instances of {}: {}",
+            count,
+            id,
+            IndividualErrorList::from(errors)
+        )))
+    }
+
+    /// Keep the function running for longer, the extension is priced by
+    /// each node hosting an instance and added to the price of its bid
+    pub async fn extend(
+        &self,
+        id: SlaId,
+        extension: ExtendSla,
+    ) -> Result<AcceptedBid> {
+        let mut tracked =
+            self.tracking.get(&id).with_code(ErrorCode::NotFound, || {
+                format!("No function was auctioned for the sla id {}", id)
+            })?;
+
+        let nodes: Vec<NodeId> = tracked.nodes().cloned().collect();
+        let mut granted_price = 0.0;
+        for (index, node) in nodes.into_iter().enumerate() {
+            let granted = match self
+                .faas
+                .extend_function(id.clone(), node, &extension)
+                .await
+                .code(ErrorCode::UpstreamFailure, "Failed to extend function")
+            {
+                Ok(granted) => granted,
+                Err(err) => {
+                    // Keep the extensions granted by the other instances
+                    self.faas.update_function(tracked.accepted.clone()).await;
+                    self.tracking.save(id, tracked);
+                    return Err(err);
+                }
+            };
+            tracked.accepted.sla = granted.sla;
+            tracked.accepted.instance_mut(index).price += granted.price;
+            granted_price += granted.price;
+        }
+
+        let accepted = tracked.accepted.clone();
+        self.faas.update_function(accepted.clone()).await;
+        self.tracking.save(id, tracked);
+
+        self.metrics
+            .observe(FunctionExtension {
+                value:         granted_price,
+                extension:     extension.duration.get::<millisecond>(),
+                function_name: accepted.sla.function_live_name.clone(),
+                sla_id:        accepted.sla.id.to_string(),
+                bid_id:        accepted.chosen.bid.id.to_string(),
+                timestamp:     Utc::now(),
+            })
+            .await
+            .context("Failed to save metrics")?;
+
+        Ok(accepted)
+    }
+
+    /// The auctioned function, along with its state on the nodes hosting
+    /// its instances
+    pub async fn get_function(&self, id: SlaId) -> Result<GetFunction> {
+        let tracked =
+            self.tracking.get(&id).with_code(ErrorCode::NotFound, || {
+                format!("No function was auctioned for the sla id {}", id)
+            })?;
+
+        let statuses = tracked.nodes().map(|node| {
+            self.faas.get_function_status(id.clone(), node.clone())
+        });
+        let mut statuses = futures::future::join_all(statuses)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .code(
+                ErrorCode::UpstreamFailure,
+                "Failed to get the status of the function",
+            )?;
+        let status = statuses.remove(0);
+
+        Ok(GetFunction {
+            node_id: tracked.node().clone(),
+            bid: tracked.accepted,
+            status,
+            replicas: statuses,
+        })
+    }
+
     async fn process_paying_details(
         &self,
         proposals: BidProposals,
         chosen_bid: ChosenBid,
         sla: Sla,
     ) -> Result<AcceptedBid> {
-        let sla_id = sla.id.clone();
-        let node_id = chosen_bid.bid.node_id.clone();
         let NodeRecord { ip, port_faas, .. } = self
             .fog_node_network
             .get_node(&chosen_bid.bid.node_id)
@@ -109,29 +584,34 @@ impl Auction {
                 ip,
                 port: port_faas,
             },
+            replicas: Vec::new(),
             proposals,
             sla,
         };
 
-        self.faas
-            .pay_for_function(accepted.clone())
-            .await
-            .context("Failed to provision function")?;
-
-        self.tracking.save(sla_id, node_id);
+        self.faas.pay_for_function(accepted.clone()).await.code(
+            ErrorCode::UpstreamFailure,
+            "Failed to provision function",
+        )?;
 
         Ok(accepted)
     }
 
-    pub async fn start_auction(
+    /// Call the fog for bids from the target node and pay the best one.
+    /// Returns the accepted bid, the remaining candidates and the number of
+    /// fallbacks.
+    async fn auction_placement(
         &self,
-        target_node: NodeId,
-        sla: Sla,
-    ) -> Result<AcceptedBid> {
-        let started = Utc::now();
-
+        target_node: &NodeId,
+        sla: &Sla,
+        mechanism: Option<AuctionMechanismKind>,
+    ) -> Result<(AcceptedBid, Vec<BidProposal>, u64)> {
         let proposals = self
-            .call_for_bids(target_node.clone(), &sla)
+            .call_for_bids(
+                target_node.clone(),
+                sla,
+                self.hop_budget.clone().into_inner(),
+            )
             .await
             .with_context(|| {
                 format!(
@@ -141,28 +621,195 @@ impl Auction {
                 )
             })?;
 
-        let AuctionResult { chosen_bid } =
-            self.do_auction(&proposals).await.context("Auction failed")?;
+        self.settle(sla, proposals, Selection::Auction(mechanism)).await
+    }
 
-        let res = self
-            .process_paying_details(proposals, chosen_bid.clone(), sla.clone())
-            .await;
+    /// Pay the best of the proposals, rejecting all of them on failure.
+    /// Returns the accepted bid, the remaining candidates and the number of
+    /// fallbacks.
+    async fn settle(
+        &self,
+        sla: &Sla,
+        proposals: BidProposals,
+        selection: Selection,
+    ) -> Result<(AcceptedBid, Vec<BidProposal>, u64)> {
+        let mut candidates = proposals.bids.clone();
+        let mut fallbacks = 0;
+        match self
+            .pay_candidates(
+                sla,
+                &proposals,
+                &mut candidates,
+                &mut fallbacks,
+                selection,
+            )
+            .await
+        {
+            Ok(accepted) => Ok((accepted, candidates, fallbacks)),
+            Err(err) => {
+                self.reject_losers(&proposals, None, &sla.id);
+                Err(err)
+            }
+        }
+    }
 
-        let accepted = res
-            .context("Failed to provision function after several retries.")?;
+    /// Choose the node from the global view of the fog, then only ask that
+    /// node for its bid, which is paid as is: the auction mechanism plays
+    /// no part. The next nodes of the ranking serve as fallbacks. When the
+    /// sla spreads its replicas, enough nodes of the ranking are asked at
+    /// once and paid in the order of the ranking. Returns the accepted bid,
+    /// the remaining candidates and the number of fallbacks.
+    async fn global_placement(
+        &self,
+        solver: fn(&GlobalView, &NodeId, &Sla) -> Vec<NodeId>,
+        target_node: &NodeId,
+        sla: &Sla,
+    ) -> Result<(AcceptedBid, Vec<BidProposal>, u64)> {
+        let view = self.fog_node_network.get_tree().await;
+        // The solver may search for long, away from the workers
+        let ranked = {
+            let target_node = target_node.clone();
+            let sla = sla.clone();
+            tokio::task::spawn_blocking(move || {
+                solver(&view, &target_node, &sla)
+            })
+            .await
+            .context("Failed to rank the nodes of the global view")?
+        };
+        trace!("{} placement ranked {:?}", self.placement, ranked);
+        let max_fallbacks = self.max_fallbacks.clone().into_inner();
+
+        if sla.spread_replicas {
+            let nodes = ranked
+                .into_iter()
+                .take(sla.replicas as usize + max_fallbacks)
+                .map(|node| async move {
+                    // No hop left, the node does not forward the request
+                    self.call_for_bids(node.clone(), sla, 0).await.map_err(
+                        |err| {
+                            warn!(
+                                "Failed to get the bid of {}: {:?}",
+                                node, err
+                            )
+                        },
+                    )
+                });
+            let bids = futures::future::join_all(nodes)
+                .await
+                .into_iter()
+                .flatten()
+                .flat_map(|proposals| proposals.bids)
+                .collect();
+            return self
+                .settle(sla, BidProposals { bids }, Selection::Ranked)
+                .await;
+        }
+
+        let mut fallbacks = 0;
+        let max_attempts = max_fallbacks + 1;
+        for node in ranked.into_iter().take(max_attempts) {
+            // No hop left, the node does not forward the request
+            let proposals =
+                match self.call_for_bids(node.clone(), sla, 0).await {
+                    Ok(proposals) if !proposals.bids.is_empty() => proposals,
+                    Ok(_) => {
+                        warn!("{} did not bid on {}", node, sla.id);
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!("Failed to get the bid of {}: {:?}", node, err);
+                        continue;
+                    }
+                };
+            let mut candidates = proposals.bids.clone();
+            match self
+                .pay_candidates(
+                    sla,
+                    &proposals,
+                    &mut candidates,
+                    &mut fallbacks,
+                    Selection::Ranked,
+                )
+                .await
+            {
+                Ok(accepted) => return Ok((accepted, candidates, fallbacks)),
+                Err(err) => {
+                    warn!("Failed to place {} on {}: {:?}", sla.id, node, err);
+                    self.reject_losers(&proposals, None, &sla.id);
+                }
+            }
+            if fallbacks > max_fallbacks as u64 {
+                break;
+            }
+        }
+
+        Err(ErrorCode::Unsatisfiable.error(format!(
+            "No node of the global view could host {} from {}",
+            sla.id, target_node
+        )))
+    }
+
+    /// Place the function of the sla, the mechanism of the sla overriding
+    /// the one of the market when the placement is an auction
+    pub async fn start_auction(
+        &self,
+        target_node: NodeId,
+        sla: Sla,
+        mechanism: Option<AuctionMechanismKind>,
+    ) -> Result<AcceptedBid> {
+        let started = Utc::now();
+
+        let ((accepted, candidates, fallbacks), selection) = match self
+            .placement
+        {
+            MarketPlacementKind::Auction => (
+                self.auction_placement(&target_node, &sla, mechanism).await?,
+                Selection::Auction(mechanism),
+            ),
+            MarketPlacementKind::Greedy => (
+                self.global_placement(
+                    global_placement::greedy,
+                    &target_node,
+                    &sla,
+                )
+                .await?,
+                Selection::Ranked,
+            ),
+            MarketPlacementKind::Exact => (
+                self.global_placement(
+                    global_placement::exact,
+                    &target_node,
+                    &sla,
+                )
+                .await?,
+                Selection::Ranked,
+            ),
+        };
+        // The losers are notified once the function is provisioned, they
+        // may still be needed as fallbacks until then
+        self.tracking.save(
+            sla.id.clone(),
+            TrackedAuction {
+                accepted: accepted.clone(),
+                candidates,
+                fallbacks,
+                selection,
+            },
+        );
 
         let finished = Utc::now();
         let duration = finished - started;
 
         self.metrics
             .observe(FunctionDeploymentDuration {
-                value:                   duration.num_milliseconds(),
-                function_name:           sla.function_live_name,
-                bid_id:                  chosen_bid.bid.id.to_string(),
-                sla_id:                  sla.id.to_string(),
+                value: duration.num_milliseconds(),
+                function_name: sla.function_live_name,
+                bid_id: accepted.chosen.bid.id.to_string(),
+                sla_id: sla.id.to_string(),
+                fallbacks,
                 timestamp_auction_start:
                     helper::monitoring::convert_timestamp(started).to_string(),
-                timestamp:               Utc::now(),
+                timestamp: Utc::now(),
             })
             .await
             .context("Failed to save metrics")?;
@@ -170,3 +817,379 @@ impl Auction {
         Ok(accepted)
     }
 }
+
+#[cfg(feature = "offline")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fog_node::FogNode;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use helper::monitoring::{
+        InfluxAddress, InfluxBucket, InfluxOrg, InfluxToken, InstanceName,
+    };
+    use helper::uom_helper::cpu_ratio::millicpu;
+    use model::view::auction::BidProposal;
+    use model::BidId;
+    use reqwest_middleware::ClientBuilder;
+    use std::sync::Mutex;
+    use uom::si::f64::Time;
+    use uom::si::information::megabyte;
+    use uom::si::rational64::{Information, Ratio};
+    use uom::si::time::second;
+
+    /// A fog node answering the market, recording what it is asked
+    #[derive(Debug, Default)]
+    struct FakeNode {
+        fail_accept:    bool,
+        fail_provision: bool,
+        fail_terminate: bool,
+        /// Answered to a call for bids
+        bids:           Vec<BidProposal>,
+        calls:          Mutex<Vec<&'static str>>,
+    }
+
+    impl FakeNode {
+        fn failing_provision() -> Self {
+            Self { fail_provision: true, ..Default::default() }
+        }
+
+        fn failing_accept() -> Self {
+            Self { fail_accept: true, ..Default::default() }
+        }
+
+        fn failing_terminate() -> Self {
+            Self { fail_terminate: true, ..Default::default() }
+        }
+
+        fn answer(&self, call: &'static str, fail: bool) -> HttpResponse {
+            self.calls.lock().unwrap().push(call);
+            if fail {
+                HttpResponse::InternalServerError().finish()
+            } else {
+                HttpResponse::Ok().finish()
+            }
+        }
+
+        fn calls(&self) -> Vec<&'static str> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    struct Market {
+        auction:  Auction,
+        tracking: Arc<BidTracking>,
+        fog_node: Arc<FogNode>,
+        /// The first one is the root, it answers the calls for bids with
+        /// the bids of all the nodes, the first ones being the lowest
+        nodes:    Vec<(NodeId, web::Data<FakeNode>)>,
+    }
+
+    async fn serve(node: FakeNode) -> (u16, web::Data<FakeNode>) {
+        let node = web::Data::new(node);
+        let data = node.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/api/bid",
+                    web::post().to(|node: web::Data<FakeNode>| async move {
+                        HttpResponse::Ok()
+                            .json(BidProposals { bids: node.bids.clone() })
+                    }),
+                )
+                .route(
+                    "/api/accept/{id}",
+                    web::post().to(|node: web::Data<FakeNode>| async move {
+                        node.answer("accept", node.fail_accept)
+                    }),
+                )
+                .route(
+                    "/api/reject/{id}",
+                    web::post().to(|node: web::Data<FakeNode>| async move {
+                        node.answer("reject", false)
+                    }),
+                )
+                .route(
+                    "/api/provision/{id}",
+                    web::post().to(|node: web::Data<FakeNode>| async move {
+                        node.answer("provision", node.fail_provision)
+                    }),
+                )
+                .route(
+                    "/api/function/{id}",
+                    web::delete().to(|node: web::Data<FakeNode>| async move {
+                        node.answer("terminate", node.fail_terminate)
+                    }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let port = server.addrs()[0].port();
+        tokio::spawn(server.run());
+        (port, node)
+    }
+
+    async fn get_market(mut nodes: Vec<FakeNode>) -> Market {
+        let ids: Vec<NodeId> =
+            nodes.iter().map(|_| NodeId::from(Uuid::new_v4())).collect();
+        nodes[0].bids = ids
+            .iter()
+            .enumerate()
+            .map(|(index, node_id)| BidProposal {
+                node_id:             node_id.clone(),
+                id:                  BidId::from(Uuid::new_v4()),
+                bid:                 (index + 1) as f64,
+                accumulated_latency: Time::new::<second>(0.0),
+            })
+            .collect();
+
+        let fog_node = Arc::new(FogNode::new());
+        let mut served = Vec::new();
+        for (node_id, node) in ids.into_iter().zip(nodes) {
+            let (port, node) = serve(node).await;
+            let ip = "127.0.0.1".parse().unwrap();
+            match served.first() {
+                None => fog_node
+                    .append_root(
+                        node_id.clone(),
+                        ip,
+                        port.into(),
+                        port.into(),
+                        &[],
+                    )
+                    .await
+                    .unwrap(),
+                Some((root, _)) => fog_node
+                    .append_new_child(
+                        root,
+                        node_id.clone(),
+                        ip,
+                        port.into(),
+                        port.into(),
+                        &[],
+                    )
+                    .await
+                    .unwrap(),
+            }
+            served.push((node_id, node));
+        }
+
+        let metrics = Arc::new(
+            MetricsExporter::new(
+                InfluxAddress::try_new("127.0.0.1:1234").unwrap(),
+                InfluxOrg::try_new("toto").unwrap(),
+                InfluxToken::try_new("xowyTh1iGcNAZsZeydESOHKvENvcyPaWg8hUe3tO4vPOw_buZVwOdUrqG3gwV314aYd9SWKHcxlykcQY_rwYVQ==").unwrap(),
+                InfluxBucket::try_new("toto").unwrap(),
+                InstanceName::try_new("toto").unwrap(),
+            )
+            .await
+            .unwrap(),
+        );
+        let network = Arc::new(FogNodeNetwork::new(fog_node.clone()).unwrap());
+        let node_communication = Arc::new(NodeCommunication::new(
+            network.clone(),
+            Arc::new(ClientBuilder::new(reqwest::Client::new()).build()),
+        ));
+        let faas = Arc::new(FogNodeFaaS::new(
+            fog_node.clone(),
+            node_communication.clone(),
+        ));
+        let tracking = Arc::new(BidTracking::new());
+        let auction = Auction::new(
+            Arc::new(AuctionRepository::new(AuctionMechanismKind::default())),
+            node_communication,
+            network,
+            faas,
+            metrics,
+            tracking.clone(),
+        )
+        .unwrap();
+
+        Market { auction, tracking, fog_node, nodes: served }
+    }
+
+    fn sla(replicas: u64, spread_replicas: bool) -> Sla {
+        Sla {
+            id: Uuid::new_v4().into(),
+            memory: Information::new::<megabyte>(num_rational::Ratio::new(
+                10, 1,
+            )),
+            cpu: Ratio::new::<millicpu>(num_rational::Ratio::new(10, 1)),
+            latency_max: Time::new::<second>(1.0),
+            duration: Time::new::<second>(5.0),
+            replicas,
+            function_image: "toto".to_string(),
+            function_live_name: "toto".to_string(),
+            data_flow: vec![],
+            env_vars: vec![],
+            env_process: None,
+            input_max_size: Information::new::<megabyte>(
+                num_rational::Ratio::new(1, 1),
+            ),
+            max_price: None,
+            spread_replicas,
+        }
+    }
+
+    /// Whether the market still records a bid paid to the node
+    async fn holds_bid(fog_node: &FogNode, node: &NodeId) -> bool {
+        !fog_node.get(node).await.unwrap().data.accepted_bids.is_empty()
+    }
+
+    #[tokio::test]
+    async fn test_ranked_selection_pays_the_first_bid() {
+        let Market { auction, nodes, .. } =
+            get_market(vec![FakeNode::default(), FakeNode::default()]).await;
+        let mut ranked = nodes[0].1.bids.clone();
+        ranked.reverse();
+        let sla = sla(1, false);
+
+        // The ranking of the global placement wins over the lowest bid
+        let AuctionResult { chosen_bid } = auction
+            .do_auction(&sla, &ranked, Selection::Ranked)
+            .await
+            .expect("Selection failed");
+        assert_eq!(chosen_bid.bid.id, ranked[0].id);
+        assert_eq!(chosen_bid.price, ranked[0].bid);
+
+        let AuctionResult { chosen_bid } = auction
+            .do_auction(&sla, &ranked, Selection::Auction(None))
+            .await
+            .expect("Auction failed");
+        assert_eq!(chosen_bid.bid.id, ranked[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_provision_fallback() {
+        let Market { auction, tracking, fog_node, nodes } = get_market(vec![
+            FakeNode::failing_provision(),
+            FakeNode::default(),
+            FakeNode::default(),
+        ])
+        .await;
+        let (failing, runner_up, last) = (&nodes[0], &nodes[1], &nodes[2]);
+        let sla = sla(1, false);
+
+        let accepted = auction
+            .start_auction(failing.0.clone(), sla.clone(), None)
+            .await
+            .expect("Auction failed");
+        assert_eq!(accepted.chosen.bid.node_id, failing.0);
+
+        auction.provision(sla.id.clone()).await.expect("Provisioning failed");
+
+        // The failing node was released before paying the runner-up
+        assert_eq!(
+            failing.1.calls()[..3],
+            ["accept", "provision", "terminate"]
+        );
+        assert!(!holds_bid(&fog_node, &failing.0).await);
+        assert_eq!(runner_up.1.calls()[..2], ["accept", "provision"]);
+        assert!(holds_bid(&fog_node, &runner_up.0).await);
+        assert!(!runner_up.1.calls().contains(&"terminate"));
+        assert!(!last.1.calls().contains(&"accept"));
+        let tracked = tracking.get(&sla.id).expect("Not tracked anymore");
+        assert_eq!(tracked.node(), &runner_up.0);
+        assert_eq!(tracked.fallbacks, 1);
+    }
+
+    #[tokio::test]
+    async fn test_provision_fallbacks_exhausted() {
+        let Market { auction, tracking, fog_node, nodes } = get_market(vec![
+            FakeNode::failing_provision(),
+            FakeNode::failing_provision(),
+            FakeNode::default(),
+        ])
+        .await;
+        let sla = sla(1, false);
+
+        auction
+            .start_auction(nodes[0].0.clone(), sla.clone(), None)
+            .await
+            .expect("Auction failed");
+        auction
+            .provision(sla.id.clone())
+            .await
+            .expect_err("Provisioned beyond the fallback limit");
+
+        // Both tried nodes were released, the last one was never paid
+        for (node_id, node) in &nodes[..2] {
+            assert_eq!(
+                node.calls()[..3],
+                ["accept", "provision", "terminate"]
+            );
+            assert!(!holds_bid(&fog_node, node_id).await);
+        }
+        assert!(!nodes[2].1.calls().contains(&"accept"));
+        assert!(tracking.get(&sla.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_spread_partial_failure() {
+        let Market { auction, tracking, fog_node, nodes } = get_market(vec![
+            FakeNode::default(),
+            FakeNode::failing_accept(),
+            FakeNode::failing_accept(),
+        ])
+        .await;
+        let sla = sla(2, true);
+
+        auction
+            .start_auction(nodes[0].0.clone(), sla.clone(), None)
+            .await
+            .expect_err("Spread over nodes that cannot be paid");
+
+        // The replica paid before the others failed was released
+        let paid = &nodes[0];
+        assert_eq!(paid.1.calls()[..2], ["accept", "terminate"]);
+        assert!(!holds_bid(&fog_node, &paid.0).await);
+        for (node_id, node) in &nodes[1..] {
+            assert_eq!(node.calls()[..1], ["accept"]);
+            assert!(!node.calls().contains(&"terminate"));
+            assert!(!holds_bid(&fog_node, node_id).await);
+        }
+        assert!(tracking.get(&sla.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_terminate_partial_failure() {
+        let Market { auction, tracking, fog_node, nodes } = get_market(vec![
+            FakeNode::default(),
+            FakeNode::failing_terminate(),
+        ])
+        .await;
+        let sla = sla(2, true);
+        auction
+            .start_auction(nodes[0].0.clone(), sla.clone(), None)
+            .await
+            .expect("Auction failed");
+        auction.provision(sla.id.clone()).await.expect("Provisioning failed");
+
+        auction
+            .terminate(sla.id.clone())
+            .await
+            .expect_err("An instance failed to terminate");
+
+        // Only the instance still running is left to terminate
+        let (stopped, running) = (&nodes[0], &nodes[1]);
+        assert!(!holds_bid(&fog_node, &stopped.0).await);
+        assert!(holds_bid(&fog_node, &running.0).await);
+        let tracked = tracking.get(&sla.id).expect("Not tracked anymore");
+        assert_eq!(tracked.nodes().collect::<Vec<_>>(), vec![&running.0]);
+
+        auction
+            .terminate(sla.id.clone())
+            .await
+            .expect_err("The instance failed to terminate again");
+        assert_eq!(
+            stopped
+                .1
+                .calls()
+                .iter()
+                .filter(|call| **call == "terminate")
+                .count(),
+            1
+        );
+    }
+}
@@ -3,6 +3,8 @@ use crate::repository::node_communication::NodeCommunication;
 use anyhow::{anyhow, Result};
 use model::dto::node::NodeRecord;
 use model::view::auction::AcceptedBid;
+use model::view::function::FunctionStatus;
+use model::view::sla::{ExtendSla, SlaExtension};
 use model::{NodeId, SlaId};
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -47,6 +49,66 @@ impl FogNodeFaaS {
         Ok(())
     }
 
+    pub async fn terminate_function(
+        &self,
+        sla: SlaId,
+        node: NodeId,
+    ) -> Result<()> {
+        trace!("Terminating function...");
+
+        self.node_communication.terminate_function(node, &sla).await?;
+        Ok(())
+    }
+
+    pub async fn extend_function(
+        &self,
+        sla: SlaId,
+        node: NodeId,
+        extension: &ExtendSla,
+    ) -> Result<SlaExtension> {
+        trace!("Extending function...");
+
+        self.node_communication.extend_function(node, &sla, extension).await
+    }
+
+    pub async fn get_function_status(
+        &self,
+        sla: SlaId,
+        node: NodeId,
+    ) -> Result<FunctionStatus> {
+        self.node_communication.get_function_status(node, &sla).await
+    }
+
+    /// Replace the records of a paid bid on the nodes hosting its
+    /// instances, e.g. once its sla got extended
+    pub async fn update_function(&self, bid: AcceptedBid) {
+        for bid in bid.split() {
+            let node = bid.chosen.bid.node_id.clone();
+            let Some(mut record) =
+                self.fog_node.get(&node).await.map(|x| x.data)
+            else {
+                continue;
+            };
+            record.accepted_bids.insert(bid.chosen.bid.id.clone(), bid);
+            self.fog_node.update(&node, record).await;
+        }
+    }
+
+    /// Forget about the instances of a paid bid that could not be
+    /// provisioned
+    pub async fn discard_function(&self, bid: &AcceptedBid) {
+        for instance in bid.instances() {
+            let node = &instance.bid.node_id;
+            let Some(mut record) =
+                self.fog_node.get(node).await.map(|x| x.data)
+            else {
+                continue;
+            };
+            record.accepted_bids.remove(&instance.bid.id);
+            self.fog_node.update(node, record).await;
+        }
+    }
+
     pub async fn provision_paid_function(
         &self,
         sla: SlaId,
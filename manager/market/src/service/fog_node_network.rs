@@ -1,18 +1,96 @@
 use crate::repository::fog_node::FogNode;
+use crate::{NODE_EVICTION_TIMEOUT_MSECS, NODE_SUSPECT_TIMEOUT_MSECS};
 use anyhow::{Context, Result};
-use model::dto::node::NodeRecord;
-use model::view::node::RegisterNode;
+use chrono::{Duration, Utc};
+use helper::env_load;
+use model::dto::node::{Node, NodeRecord};
+use model::view::node::{DeregisterNode, MoveNode, RegisterNode};
 use model::NodeId;
+use nutype::nutype;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Silence after which a node is marked as suspected, in milliseconds
+#[nutype(derive(Clone, Debug), validate(greater = 0))]
+pub struct NodeSuspectTimeout(i64);
+
+/// Silence after which a node and its subtree are removed, in milliseconds
+#[nutype(derive(Clone, Debug), validate(greater = 0))]
+pub struct NodeEvictionTimeout(i64);
 
 #[derive(Debug)]
 pub struct FogNodeNetwork {
-    fog_node: Arc<FogNode>,
+    fog_node:         Arc<FogNode>,
+    suspect_timeout:  NodeSuspectTimeout,
+    eviction_timeout: NodeEvictionTimeout,
 }
 
 impl FogNodeNetwork {
-    pub fn new(fog_node: Arc<FogNode>) -> Self { Self { fog_node } }
+    pub fn new(fog_node: Arc<FogNode>) -> Result<Self> {
+        let suspect_timeout =
+            env_load!(NodeSuspectTimeout, NODE_SUSPECT_TIMEOUT_MSECS, i64);
+        let eviction_timeout =
+            env_load!(NodeEvictionTimeout, NODE_EVICTION_TIMEOUT_MSECS, i64);
+        Ok(Self { fog_node, suspect_timeout, eviction_timeout })
+    }
+
+    /// Refresh the liveness of the node, registering it again if it was
+    /// evicted in the meantime
+    pub async fn heartbeat(&self, node: RegisterNode) -> Result<()> {
+        let (node_id, capacity) = match &node {
+            RegisterNode::MarketNode { node_id, capacity, .. } => {
+                (node_id, capacity)
+            }
+            RegisterNode::Node { node_id, capacity, .. } => {
+                (node_id, capacity)
+            }
+        };
+        if self.fog_node.touch(node_id, Utc::now(), capacity.clone()).await {
+            return Ok(());
+        }
+        warn!("Unknown node {} sent a heartbeat, registering it", node_id);
+        self.register_node(node).await
+    }
+
+    /// Remove a node leaving the network, along with its subtree
+    pub async fn deregister_node(&self, node: DeregisterNode) -> Result<()> {
+        let removed =
+            self.fog_node.remove(&node.node_id).await.with_context(|| {
+                format!("Failed to deregister node {}", node.node_id)
+            })?;
+        info!("Node {} left, removed {:?}", node.node_id, removed);
+        Ok(())
+    }
+
+    /// Re-attach a node under its new parent, the move is reported by both
+    /// its old and new parents
+    pub async fn move_node(&self, node: MoveNode) -> Result<()> {
+        self.fog_node.reparent(&node.node_id, &node.to).await.with_context(
+            || {
+                format!(
+                    "Failed to move node {} from {} to {}",
+                    node.node_id, node.from, node.to
+                )
+            },
+        )
+    }
+
+    /// Remove the nodes that stopped sending heartbeats, with their subtree
+    pub async fn evict_silent_nodes(&self) -> Result<Vec<NodeId>> {
+        self.fog_node
+            .evict_silent(
+                Utc::now(),
+                Duration::milliseconds(
+                    self.suspect_timeout.clone().into_inner(),
+                ),
+                Duration::milliseconds(
+                    self.eviction_timeout.clone().into_inner(),
+                ),
+            )
+            .await
+    }
 
     pub async fn register_node(&self, node: RegisterNode) -> Result<()> {
         match node {
@@ -22,6 +100,7 @@ impl FogNodeNetwork {
                 port_http,
                 port_faas,
                 tags,
+                capacity,
                 ..
             } => {
                 self.fog_node
@@ -36,6 +115,7 @@ impl FogNodeNetwork {
                     .with_context(|| {
                         format!("Failed to register market node {}", node_id)
                     })?;
+                self.fog_node.touch(&node_id, Utc::now(), capacity).await;
             }
             RegisterNode::Node {
                 node_id,
@@ -44,6 +124,7 @@ impl FogNodeNetwork {
                 ip,
                 port_http,
                 port_faas,
+                capacity,
                 ..
             } => {
                 self.fog_node
@@ -63,6 +144,7 @@ impl FogNodeNetwork {
                             node_id, parent
                         )
                     })?;
+                self.fog_node.touch(&node_id, Utc::now(), capacity).await;
             }
         }
 
@@ -73,6 +155,11 @@ impl FogNodeNetwork {
         self.fog_node.get_nodes().await
     }
 
+    /// The tree of the nodes with what they last reported
+    pub async fn get_tree(&self) -> HashMap<NodeId, Node<NodeRecord>> {
+        self.fog_node.get_tree().await
+    }
+
     pub async fn get_node(&self, node: &NodeId) -> Option<NodeRecord> {
         self.fog_node.get(node).await.map(|x| x.data)
     }
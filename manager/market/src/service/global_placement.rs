@@ -0,0 +1,396 @@
+use model::domain::sla::Sla;
+use model::dto::node::{Liveness, Node, NodeRecord};
+use model::NodeId;
+use std::collections::HashMap;
+use tracing::warn;
+use uom::si::f64::Time;
+use uom::si::rational64::Ratio;
+use uom::si::time::second;
+
+/// The tree of the fog nodes, with what they last reported
+pub type GlobalView = HashMap<NodeId, Node<NodeRecord>>;
+
+/// Most sets of nodes the exact placement tries, above that it falls back
+/// on the greedy placement
+const MAX_EXACT_PLACEMENTS: u128 = 100_000;
+
+/// Whether the node is alive and reported enough free resources for the sla
+fn can_host(node: &Node<NodeRecord>, sla: &Sla) -> bool {
+    node.data.liveness == Liveness::Alive
+        && node
+            .data
+            .capacity
+            .as_ref()
+            .is_some_and(|capacity| capacity.can_host(sla))
+}
+
+/// Latency reported by the node to reach its parent
+fn latency_to_parent(node: &Node<NodeRecord>) -> Option<Time> {
+    node.data.capacity.as_ref().and_then(|capacity| capacity.latency_to_parent)
+}
+
+/// Latency from the target node to every node of the tree, following the
+/// links of the tree. The nodes behind a link of unknown latency are left
+/// out.
+fn latencies_from(
+    view: &GlobalView,
+    target: &NodeId,
+) -> HashMap<NodeId, Time> {
+    let mut latencies = HashMap::new();
+    if !view.contains_key(target) {
+        return latencies;
+    }
+    latencies.insert(target.clone(), Time::new::<second>(0.0));
+    let mut stack = vec![target.clone()];
+    while let Some(id) = stack.pop() {
+        let node = &view[&id];
+        let latency = latencies[&id];
+        let parent = node.parent.as_ref().zip(latency_to_parent(node));
+        let children = node.children.iter().filter_map(|child| {
+            view.get(child)
+                .and_then(latency_to_parent)
+                .map(|link| (child, link))
+        });
+        for (next, link) in parent.into_iter().chain(children) {
+            if latencies.contains_key(next) || !view.contains_key(next) {
+                continue;
+            }
+            latencies.insert(next.clone(), latency + link);
+            stack.push(next.clone());
+        }
+    }
+    latencies
+}
+
+/// Climb from the target node towards the root and keep the nodes able to
+/// host the sla within its latency budget, closest first
+pub fn greedy(view: &GlobalView, target: &NodeId, sla: &Sla) -> Vec<NodeId> {
+    let mut ranked = Vec::new();
+    let mut current = target.clone();
+    let mut latency = Time::new::<second>(0.0);
+    while let Some(node) = view.get(&current) {
+        if latency > sla.latency_max {
+            break;
+        }
+        if can_host(node, sla) {
+            ranked.push(current.clone());
+        }
+        let (Some(parent), Some(link)) =
+            (node.parent.clone(), latency_to_parent(node))
+        else {
+            break;
+        };
+        latency += link;
+        current = parent;
+    }
+    ranked
+}
+
+/// Cost of placing the replicas of a sla on a set of nodes, the lower the
+/// better: the total latency from the target node to the chosen nodes, then
+/// the least free cpu left on one of them once they host the replicas
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlacementCost {
+    total_latency:  f64,
+    least_cpu_left: Ratio,
+}
+
+impl PlacementCost {
+    fn of(view: &GlobalView, sla: &Sla, chosen: &[&(NodeId, Time)]) -> Self {
+        let total_latency =
+            chosen.iter().map(|(_, latency)| latency.get::<second>()).sum();
+        let least_cpu_left = chosen
+            .iter()
+            .filter_map(|(id, _)| view[id].data.capacity.as_ref())
+            .map(|capacity| capacity.max_free_cpu - sla.cpu_per_node())
+            .min()
+            .expect("A placement has at least a node");
+        Self { total_latency, least_cpu_left }
+    }
+
+    fn is_better_than(&self, other: &Self) -> bool {
+        self.total_latency
+            .total_cmp(&other.total_latency)
+            .then_with(|| other.least_cpu_left.cmp(&self.least_cpu_left))
+            .is_lt()
+    }
+}
+
+/// Try every combination of `left` more nodes among the candidates from
+/// the index `from`, keeping the best placement found so far
+fn search<'a>(
+    view: &GlobalView,
+    sla: &Sla,
+    candidates: &'a [(NodeId, Time)],
+    from: usize,
+    left: usize,
+    chosen: &mut Vec<&'a (NodeId, Time)>,
+    best: &mut Option<(PlacementCost, Vec<NodeId>)>,
+) {
+    if left == 0 {
+        let cost = PlacementCost::of(view, sla, chosen);
+        let better = match best {
+            Some((best, _)) => cost.is_better_than(best),
+            None => true,
+        };
+        if better {
+            let nodes = chosen.iter().map(|(id, _)| id.clone()).collect();
+            *best = Some((cost, nodes));
+        }
+        return;
+    }
+    for index in from..=candidates.len().saturating_sub(left) {
+        chosen.push(&candidates[index]);
+        search(view, sla, candidates, index + 1, left - 1, chosen, best);
+        chosen.pop();
+    }
+}
+
+/// Whether choosing `nodes` among the candidates makes more sets than the
+/// exact placement tries
+fn too_many_placements(candidates: usize, nodes: usize) -> bool {
+    let nodes = nodes.min(candidates - nodes);
+    let mut placements: u128 = 1;
+    for chosen in 0..nodes {
+        // Number of sets of `chosen + 1` nodes, exact at every step
+        placements =
+            placements * (candidates - chosen) as u128 / (chosen + 1) as u128;
+        if placements > MAX_EXACT_PLACEMENTS {
+            return true;
+        }
+    }
+    false
+}
+
+/// Exhaustive search of the optimal placement over the whole tree, see
+/// [PlacementCost] for the objective: every set of nodes able to host the
+/// sla within its latency budget is tried, one node per replica when the
+/// sla spreads them, a single one otherwise. Exponential in the number of
+/// replicas, so the [greedy] placement is used instead above
+/// [MAX_EXACT_PLACEMENTS] sets. The optimal nodes come first, then the
+/// other candidates as fallbacks, closest first.
+pub fn exact(view: &GlobalView, target: &NodeId, sla: &Sla) -> Vec<NodeId> {
+    let mut candidates: Vec<_> = latencies_from(view, target)
+        .into_iter()
+        .filter(|(id, latency)| {
+            *latency <= sla.latency_max && can_host(&view[id], sla)
+        })
+        .collect();
+    // Deterministic enumeration, and fallbacks closest first
+    candidates.sort_by(|(a, a_latency), (b, b_latency)| {
+        a_latency
+            .get::<second>()
+            .total_cmp(&b_latency.get::<second>())
+            .then_with(|| a.to_string().cmp(&b.to_string()))
+    });
+    let nodes = if sla.spread_replicas { sla.replicas as usize } else { 1 };
+    if candidates.len() < nodes {
+        return Vec::new();
+    }
+    if too_many_placements(candidates.len(), nodes) {
+        warn!(
+            "Too many placements of {} over {} nodes, placing greedily",
+            sla.id,
+            candidates.len()
+        );
+        return greedy(view, target, sla);
+    }
+
+    let mut best = None;
+    search(view, sla, &candidates, 0, nodes, &mut Vec::new(), &mut best);
+    let Some((_, mut ranked)) = best else {
+        return Vec::new();
+    };
+    ranked.extend(
+        candidates
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| !ranked.contains(id))
+            .collect::<Vec<_>>(),
+    );
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helper::uom_helper::cpu_ratio::millicpu;
+    use model::view::node::NodeCapacity;
+    use uom::si::information::megabyte;
+    use uom::si::rational64::Information;
+    use uom::si::time::millisecond;
+    use uuid::Uuid;
+
+    /// The nodes of the view, by their name
+    struct View {
+        view:  GlobalView,
+        names: HashMap<&'static str, NodeId>,
+    }
+
+    impl View {
+        fn id(&self, name: &str) -> NodeId { self.names[name].clone() }
+
+        fn ids(&self, names: &[&str]) -> Vec<NodeId> {
+            names.iter().map(|name| self.id(name)).collect()
+        }
+    }
+
+    /// Nodes as (name, parent, latency to the parent in ms, free millicpu),
+    /// parents first
+    fn get_view(nodes: &[(&'static str, Option<&str>, f64, i64)]) -> View {
+        let names: HashMap<_, _> = nodes
+            .iter()
+            .map(|(name, ..)| (*name, NodeId::from(Uuid::new_v4())))
+            .collect();
+        let mut view = GlobalView::new();
+        for (name, parent, latency, free_cpu) in nodes {
+            let mut data = NodeRecord::new(
+                "127.0.0.1".parse().unwrap(),
+                0.into(),
+                0.into(),
+                &[],
+            );
+            data.capacity = Some(NodeCapacity {
+                max_free_cpu:      Ratio::new::<millicpu>(
+                    num_rational::Ratio::new(*free_cpu, 1),
+                ),
+                max_free_memory:   Information::new::<megabyte>(
+                    num_rational::Ratio::new(1000, 1),
+                ),
+                latency_to_parent: parent
+                    .map(|_| Time::new::<millisecond>(*latency)),
+            });
+            let parent = parent.map(|parent| names[parent].clone());
+            if let Some(parent) = &parent {
+                view.get_mut(parent)
+                    .unwrap()
+                    .children
+                    .push(names[name].clone());
+            }
+            view.insert(
+                names[name].clone(),
+                Node { parent, children: vec![], data },
+            );
+        }
+        View { view, names }
+    }
+
+    fn sla(latency_max_ms: f64, replicas: u64, spread_replicas: bool) -> Sla {
+        Sla {
+            id: Uuid::new_v4().into(),
+            memory: Information::new::<megabyte>(num_rational::Ratio::new(
+                10, 1,
+            )),
+            cpu: Ratio::new::<millicpu>(num_rational::Ratio::new(100, 1)),
+            latency_max: Time::new::<millisecond>(latency_max_ms),
+            duration: Time::new::<second>(5.0),
+            replicas,
+            function_image: "toto".to_string(),
+            function_live_name: "toto".to_string(),
+            data_flow: vec![],
+            env_vars: vec![],
+            env_process: None,
+            input_max_size: Information::new::<megabyte>(
+                num_rational::Ratio::new(1, 1),
+            ),
+            max_price: None,
+            spread_replicas,
+        }
+    }
+
+    /// The target is full, its close children are below it and the way up
+    /// to the root is longer
+    fn fog() -> View {
+        get_view(&[
+            ("root", None, 0.0, 1000),
+            ("up", Some("root"), 10.0, 1000),
+            ("target", Some("up"), 5.0, 0),
+            ("busy", Some("target"), 2.0, 300),
+            ("idle", Some("target"), 2.0, 900),
+            ("far", Some("root"), 1.0, 1000),
+        ])
+    }
+
+    #[test]
+    fn test_greedy_climbs_to_the_root() {
+        let fog = fog();
+        let target = fog.id("target");
+
+        let ranked = greedy(&fog.view, &target, &sla(20.0, 1, false));
+        assert_eq!(ranked, fog.ids(&["up", "root"]));
+
+        // The root is out of the latency budget
+        let ranked = greedy(&fog.view, &target, &sla(10.0, 1, false));
+        assert_eq!(ranked, fog.ids(&["up"]));
+    }
+
+    #[test]
+    fn test_exact_looks_at_the_whole_tree() {
+        let fog = fog();
+        let target = fog.id("target");
+
+        // The children are the closest, the idlest of them is the best
+        let ranked = exact(&fog.view, &target, &sla(20.0, 1, false));
+        assert_eq!(ranked, fog.ids(&["idle", "busy", "up", "root", "far"]));
+
+        let ranked = exact(&fog.view, &target, &sla(10.0, 1, false));
+        assert_eq!(ranked, fog.ids(&["idle", "busy", "up"]));
+    }
+
+    #[test]
+    fn test_exact_spread_replicas() {
+        let fog = get_view(&[
+            ("root", None, 0.0, 1000),
+            ("target", Some("root"), 4.0, 0),
+            ("left", Some("target"), 2.0, 1000),
+            ("busy", Some("target"), 2.0, 150),
+            ("right", Some("target"), 2.0, 1000),
+            ("behind", Some("busy"), 1.0, 1000),
+        ]);
+        let target = fog.id("target");
+        let sorted = |mut nodes: Vec<NodeId>| {
+            nodes.sort_by_key(|id| id.to_string());
+            nodes
+        };
+
+        // Every pair of the children is as close, the busy one would be
+        // left with the least cpu
+        let ranked = exact(&fog.view, &target, &sla(10.0, 2, true));
+        assert_eq!(
+            sorted(ranked[..2].to_vec()),
+            sorted(fog.ids(&["left", "right"]))
+        );
+        assert_eq!(ranked.len(), 5);
+
+        // Closer than any set without the busy one
+        let ranked = exact(&fog.view, &target, &sla(10.0, 3, true));
+        assert_eq!(
+            sorted(ranked[..3].to_vec()),
+            sorted(fog.ids(&["left", "busy", "right"]))
+        );
+
+        // Not enough nodes within the budget for the replicas
+        assert!(exact(&fog.view, &target, &sla(2.5, 4, true)).is_empty());
+    }
+
+    #[test]
+    fn test_exact_falls_back_on_greedy() {
+        let mut nodes = vec![("root", None, 0.0, 1000)];
+        nodes.extend((0..40).map(|index| {
+            let name: &'static str =
+                Box::leak(format!("child{}", index).into());
+            (name, Some("root"), 1.0, 1000)
+        }));
+        let fog = get_view(&nodes);
+        let target = fog.id("child0");
+
+        assert!(!too_many_placements(40, 1));
+        assert!(too_many_placements(40, 20));
+
+        // Too many sets of 20 nodes among 41 to try them all
+        let sla = sla(10.0, 20, true);
+        let ranked = exact(&fog.view, &target, &sla);
+        assert_eq!(ranked, greedy(&fog.view, &target, &sla));
+        assert_eq!(ranked, fog.ids(&["child0", "root"]));
+    }
+}
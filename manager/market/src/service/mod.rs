@@ -1,3 +1,4 @@
 pub(crate) mod auction;
 pub(crate) mod faas;
 pub(crate) mod fog_node_network;
+pub(crate) mod global_placement;
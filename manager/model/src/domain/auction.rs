@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 use crate::dto::function::ChosenBid;
 use crate::view::auction::BidProposal;
@@ -20,3 +22,96 @@ pub struct AuctionSummary {
 pub struct AuctionResult {
     pub chosen_bid: ChosenBid,
 }
+
+/// How the market chooses the winning bid and the price paid for it. The
+/// bids are costs: the lowest one is the best.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum AuctionMechanismKind {
+    /// The lowest bid wins and is paid as is
+    FirstPrice,
+    /// The lowest bid wins and is paid the second lowest one (Vickrey)
+    #[default]
+    SecondPrice,
+    /// A bid drawn uniformly at random wins and is paid as is
+    Random,
+    /// Second price among the bids up to the reserve, the winner is paid at
+    /// most the reserve
+    ReservePrice { reserve: f64 },
+    /// The lowest score wins, the score being the bid plus the weight times
+    /// the accumulated latency in milliseconds. The winner is paid so that
+    /// its score matches the second lowest one.
+    WeightedScore { latency_weight: f64 },
+}
+
+impl AuctionMechanismKind {
+    /// Whether the parameter of the mechanism, if any, is one it can run
+    /// with
+    pub fn is_valid(&self) -> bool {
+        match self {
+            AuctionMechanismKind::ReservePrice { reserve: parameter }
+            | AuctionMechanismKind::WeightedScore {
+                latency_weight: parameter,
+            } => is_valid_parameter(*parameter),
+            _ => true,
+        }
+    }
+}
+
+fn is_valid_parameter(parameter: f64) -> bool {
+    parameter.is_finite() && parameter >= 0.0
+}
+
+impl fmt::Display for AuctionMechanismKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuctionMechanismKind::FirstPrice => write!(f, "first_price"),
+            AuctionMechanismKind::SecondPrice => write!(f, "second_price"),
+            AuctionMechanismKind::Random => write!(f, "random"),
+            AuctionMechanismKind::ReservePrice { reserve } => {
+                write!(f, "reserve_price:{}", reserve)
+            }
+            AuctionMechanismKind::WeightedScore { latency_weight } => {
+                write!(f, "weighted_score:{}", latency_weight)
+            }
+        }
+    }
+}
+
+/// Parse the name of the mechanism, followed by its parameter if any, eg.
+/// `reserve_price:12.5`
+impl FromStr for AuctionMechanismKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, parameter) = match s.split_once(':') {
+            Some((name, parameter)) => (name, Some(parameter)),
+            None => (s, None),
+        };
+        let parameter = || -> Result<f64, Self::Err> {
+            let parameter: f64 = parameter
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Missing the parameter of '{}'", name)
+                })?
+                .trim()
+                .parse()?;
+            anyhow::ensure!(
+                is_valid_parameter(parameter),
+                "The parameter of '{}' must be a positive number",
+                name
+            );
+            Ok(parameter)
+        };
+        match name.trim() {
+            "first_price" => Ok(AuctionMechanismKind::FirstPrice),
+            "second_price" => Ok(AuctionMechanismKind::SecondPrice),
+            "random" => Ok(AuctionMechanismKind::Random),
+            "reserve_price" => Ok(AuctionMechanismKind::ReservePrice {
+                reserve: parameter()?,
+            }),
+            "weighted_score" => Ok(AuctionMechanismKind::WeightedScore {
+                latency_weight: parameter()?,
+            }),
+            _ => anyhow::bail!("Unknown auction mechanism '{}'", s),
+        }
+    }
+}
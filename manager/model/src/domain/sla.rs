@@ -1,8 +1,13 @@
 use crate::{NodeId, SlaId};
+use helper::http_error::{ErrorCode, HttpError};
+use helper::uom_helper::cpu_ratio::millicpu;
 use helper::uom_helper::{cpu, information, time};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use uom::si::f64::Time;
+use uom::si::information::byte;
 use uom::si::rational64::{Information, Ratio};
+use uom::si::time::{day, second};
 
 /// Describe the SLA of a function submitted to be provisioned
 #[serde_with::serde_as]
@@ -37,6 +42,131 @@ pub struct Sla {
 
     #[serde_as(as = "information::Helper")]
     pub input_max_size: Information,
+
+    /// Highest price the client accepts to pay, no limit if none. Only the
+    /// nodes whose bids are prices bid on an sla with a maximum price
+    #[serde(default)]
+    pub max_price: Option<f64>,
+
+    /// Place each replica on a distinct fog node instead of all of them on
+    /// the winning one
+    #[serde(default)]
+    pub spread_replicas: bool,
+}
+
+/// Why a SLA cannot be accepted
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "reason", content = "detail", rename_all = "camelCase")]
+pub enum SlaValidationError {
+    #[error("The duration must be positive")]
+    NonPositiveDuration,
+    #[error("The duration must not exceed {0} days")]
+    DurationTooLong(f64),
+    #[error("The maximum latency must be positive")]
+    NonPositiveLatencyMax,
+    #[error("The maximum input size must be positive")]
+    NonPositiveInputMaxSize,
+    #[error("The cpu must be positive")]
+    NonPositiveCpu,
+    #[error("The memory must be positive")]
+    NonPositiveMemory,
+    #[error("At least one replica is required")]
+    NoReplicas,
+    #[error("The function image is empty")]
+    EmptyFunctionImage,
+    #[error("Invalid environment variable name: {0:?}")]
+    InvalidEnvVarName(String),
+    #[error("The maximum price must be positive")]
+    NonPositiveMaxPrice,
+    #[error("The extension must be positive")]
+    NonPositiveExtension,
+    #[error("The extension must not exceed {0} days")]
+    ExtensionTooLong(f64),
+    #[error(
+        "Invalid auction mechanism {0}, its parameter must be a positive \
+         number"
+    )]
+    InvalidAuctionMechanism(String),
+}
+
+impl From<SlaValidationError> for HttpError {
+    fn from(err: SlaValidationError) -> Self {
+        HttpError::new(ErrorCode::BadRequest, err.clone()).with_detail(&err)
+    }
+}
+
+impl Sla {
+    /// Longest duration a function is provisioned for, in days
+    pub const MAX_DURATION_DAYS: f64 = 365.0;
+
+    /// How many replicas a single fog node hosts
+    pub fn replicas_per_node(&self) -> u64 {
+        if self.spread_replicas {
+            1
+        } else {
+            self.replicas
+        }
+    }
+
+    /// Memory used on a single fog node by the replicas it hosts
+    pub fn memory_per_node(&self) -> Information {
+        self.memory
+            * num_rational::Ratio::new(self.replicas_per_node() as i64, 1)
+    }
+
+    /// Cpu used on a single fog node by the replicas it hosts
+    pub fn cpu_per_node(&self) -> Ratio {
+        self.cpu * num_rational::Ratio::new(self.replicas_per_node() as i64, 1)
+    }
+
+    /// Check the SLA describes a function that can be provisioned
+    pub fn validate(&self) -> Result<(), SlaValidationError> {
+        // Not greater also refuses NaNs
+        let zero = Time::new::<second>(0.0);
+        if self.duration.partial_cmp(&zero) != Some(Ordering::Greater) {
+            return Err(SlaValidationError::NonPositiveDuration);
+        }
+        if self.duration > Time::new::<day>(Self::MAX_DURATION_DAYS) {
+            return Err(SlaValidationError::DurationTooLong(
+                Self::MAX_DURATION_DAYS,
+            ));
+        }
+        if self.latency_max.partial_cmp(&zero) != Some(Ordering::Greater) {
+            return Err(SlaValidationError::NonPositiveLatencyMax);
+        }
+        if self.input_max_size
+            <= Information::new::<byte>(num_rational::Ratio::new(0, 1))
+        {
+            return Err(SlaValidationError::NonPositiveInputMaxSize);
+        }
+        if self.cpu <= Ratio::new::<millicpu>(num_rational::Ratio::new(0, 1)) {
+            return Err(SlaValidationError::NonPositiveCpu);
+        }
+        if self.memory
+            <= Information::new::<byte>(num_rational::Ratio::new(0, 1))
+        {
+            return Err(SlaValidationError::NonPositiveMemory);
+        }
+        if self.replicas == 0 {
+            return Err(SlaValidationError::NoReplicas);
+        }
+        if self.function_image.trim().is_empty() {
+            return Err(SlaValidationError::EmptyFunctionImage);
+        }
+        // Not greater also refuses NaNs
+        if self.max_price.is_some_and(|max_price| {
+            max_price.partial_cmp(&0.0) != Some(Ordering::Greater)
+        }) {
+            return Err(SlaValidationError::NonPositiveMaxPrice);
+        }
+        let re = lazy_regex::regex!(r"^[A-Za-z_][A-Za-z0-9_]*$");
+        if let Some((name, _)) =
+            self.env_vars.iter().find(|(name, _)| !re.is_match(name))
+        {
+            return Err(SlaValidationError::InvalidEnvVarName(name.clone()));
+        }
+        Ok(())
+    }
 }
 
 /// A point in the Fog
@@ -54,3 +184,141 @@ pub struct DataFlow {
     pub from: SlaFogPoint,
     pub to:   SlaFogPoint,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::information::megabyte;
+
+    fn sla() -> Sla {
+        Sla {
+            id:                 uuid::Uuid::new_v4().into(),
+            memory:             Information::new::<megabyte>(
+                num_rational::Ratio::new(100, 1),
+            ),
+            cpu:                Ratio::new::<millicpu>(
+                num_rational::Ratio::new(100, 1),
+            ),
+            latency_max:        Time::new::<second>(1.0),
+            duration:           Time::new::<second>(10.0),
+            replicas:           1,
+            function_image:     "toto".to_string(),
+            function_live_name: "toto".to_string(),
+            data_flow:          vec![],
+            env_vars:           vec![("TOTO_1".to_string(), "1".to_string())],
+            env_process:        None,
+            input_max_size:     Information::new::<megabyte>(
+                num_rational::Ratio::new(1, 1),
+            ),
+            max_price:          None,
+            spread_replicas:    false,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(sla().validate(), Ok(()));
+        assert_eq!(
+            Sla { duration: Time::new::<second>(f64::NAN), ..sla() }
+                .validate(),
+            Err(SlaValidationError::NonPositiveDuration)
+        );
+        for duration in [Sla::MAX_DURATION_DAYS + 1.0, f64::INFINITY] {
+            assert_eq!(
+                Sla { duration: Time::new::<day>(duration), ..sla() }
+                    .validate(),
+                Err(SlaValidationError::DurationTooLong(
+                    Sla::MAX_DURATION_DAYS
+                ))
+            );
+        }
+        assert_eq!(
+            Sla {
+                cpu: Ratio::new::<millicpu>(num_rational::Ratio::new(0, 1)),
+                ..sla()
+            }
+            .validate(),
+            Err(SlaValidationError::NonPositiveCpu)
+        );
+        assert_eq!(
+            Sla { replicas: 0, ..sla() }.validate(),
+            Err(SlaValidationError::NoReplicas)
+        );
+        assert_eq!(
+            Sla { function_image: " ".to_string(), ..sla() }.validate(),
+            Err(SlaValidationError::EmptyFunctionImage)
+        );
+        assert_eq!(
+            Sla {
+                env_vars: vec![("1TOTO".to_string(), "1".to_string())],
+                ..sla()
+            }
+            .validate(),
+            Err(SlaValidationError::InvalidEnvVarName("1TOTO".to_string()))
+        );
+        assert_eq!(
+            Sla { max_price: Some(0.0), ..sla() }.validate(),
+            Err(SlaValidationError::NonPositiveMaxPrice)
+        );
+        assert_eq!(
+            Sla { max_price: Some(f64::NAN), ..sla() }.validate(),
+            Err(SlaValidationError::NonPositiveMaxPrice)
+        );
+        assert_eq!(Sla { max_price: Some(10.0), ..sla() }.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_extension() {
+        use crate::view::sla::ExtendSla;
+
+        let extension = |duration| ExtendSla { duration };
+        assert_eq!(extension(Time::new::<second>(5.0)).validate(), Ok(()));
+        for duration in [0.0, -5.0, f64::NAN] {
+            assert_eq!(
+                extension(Time::new::<second>(duration)).validate(),
+                Err(SlaValidationError::NonPositiveExtension)
+            );
+        }
+        for duration in [ExtendSla::MAX_DAYS + 1.0, f64::INFINITY] {
+            assert_eq!(
+                extension(Time::new::<day>(duration)).validate(),
+                Err(SlaValidationError::ExtensionTooLong(ExtendSla::MAX_DAYS))
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_auction_mechanism() {
+        use crate::domain::auction::AuctionMechanismKind::{
+            self, ReservePrice, WeightedScore,
+        };
+        use crate::view::sla::{PutSla, PutSlaRequest};
+
+        let request = |auction_mechanism| PutSlaRequest {
+            sla: serde_json::from_value(serde_json::to_value(sla()).unwrap())
+                .unwrap(),
+            target_node: uuid::Uuid::new_v4().into(),
+            auction_mechanism,
+        };
+        for mechanism in [
+            None,
+            Some(AuctionMechanismKind::Random),
+            Some(ReservePrice { reserve: 12.5 }),
+            Some(WeightedScore { latency_weight: 0.0 }),
+        ] {
+            assert!(PutSla::try_from(request(mechanism)).is_ok());
+        }
+        for mechanism in [
+            ReservePrice { reserve: -1.0 },
+            ReservePrice { reserve: f64::INFINITY },
+            WeightedScore { latency_weight: f64::NAN },
+        ] {
+            assert_eq!(
+                PutSla::try_from(request(Some(mechanism))).err(),
+                Some(SlaValidationError::InvalidAuctionMechanism(
+                    mechanism.to_string()
+                ))
+            );
+        }
+    }
+}
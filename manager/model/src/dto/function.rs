@@ -2,14 +2,16 @@ use crate::domain::sla::Sla;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proposed {
-    pub bid:  f64,
-    pub sla:  Sla,
-    pub node: String,
+    pub bid:         f64,
+    pub sla:         Sla,
+    pub node:        String,
+    /// When the bid was made, the paying timeout runs from it
+    pub proposed_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Paid {
     pub bid:     f64,
     pub sla:     Sla,
@@ -17,25 +19,27 @@ pub struct Paid {
     pub paid_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Provisioned {
     pub bid:           f64,
     pub sla:           Sla,
     pub node:          String,
     pub function_name: String,
     pub opened_port:   i32,
+    pub paid_at:       DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Live {
     pub bid:           f64,
     pub sla:           Sla,
     pub node:          String,
     pub function_name: String,
     pub opened_port:   i32,
+    pub paid_at:       DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Finished {
     pub bid:  f64,
     pub sla:  Sla,
@@ -48,7 +52,7 @@ pub trait Finishable {
 
 impl Proposed {
     pub fn new(bid: f64, sla: Sla, node: String) -> Self {
-        Self { bid, sla, node }
+        Self { bid, sla, node, proposed_at: Utc::now() }
     }
 
     pub fn to_paid(self, paid_at: DateTime<Utc>) -> Paid {
@@ -68,13 +72,14 @@ impl Paid {
             sla: self.sla,
             node: self.node,
             opened_port,
+            paid_at: self.paid_at,
         }
     }
 }
 impl Finishable for Paid {
     fn to_finished(&self) -> Finished {
         Finished {
-            bid:  self.bid.clone(),
+            bid:  self.bid,
             sla:  self.sla.clone(),
             node: self.node.clone(),
         }
@@ -88,6 +93,7 @@ impl Provisioned {
             sla:           self.sla,
             node:          self.node,
             opened_port:   self.opened_port,
+            paid_at:       self.paid_at,
         }
     }
 }
@@ -95,7 +101,7 @@ impl Provisioned {
 impl Finishable for Provisioned {
     fn to_finished(&self) -> Finished {
         Finished {
-            bid:  self.bid.clone(),
+            bid:  self.bid,
             sla:  self.sla.clone(),
             node: self.node.clone(),
         }
@@ -105,7 +111,7 @@ impl Finishable for Provisioned {
 impl Finishable for Live {
     fn to_finished(&self) -> Finished {
         Finished {
-            bid:  self.bid.clone(),
+            bid:  self.bid,
             sla:  self.sla.clone(),
             node: self.node.clone(),
         }
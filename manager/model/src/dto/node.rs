@@ -1,15 +1,17 @@
 use crate::view::auction::AcceptedBid;
+use crate::view::node::NodeCapacity;
 use crate::{
     BidId, FogNodeFaaSPortExternal, FogNodeHTTPPort, MarketHTTPPort, NodeId,
 };
+use chrono::{DateTime, Utc};
 #[cfg(feature = "offline")]
 use helper::uom_helper::time;
 use helper::uom_helper::{cpu, information, information_rate};
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
 use std::collections::HashMap;
 use std::fmt;
 use std::net::IpAddr;
+use std::str::FromStr;
 #[cfg(feature = "offline")]
 use uom::si::f64::Time;
 use uom::si::rational64::{Information, InformationRate, Ratio};
@@ -20,6 +22,220 @@ use uom::si::rational64::{Information, InformationRate, Ratio};
 )]
 pub struct MaxInFlight(usize);
 
+/// Placement algorithm used by a fog node to answer the bid requests
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum PlacementStrategyKind {
+    /// Bid and forward to all the neighbors within the latency budget
+    #[default]
+    Auction,
+    /// Place at the Edge first, then go up towards the Cloud
+    EdgeFirst,
+    /// Place at the Edge first, trying the furthest neighbors first
+    EdgeFurthest,
+    /// Edge-ward algorithm as described in https://arxiv.org/abs/1606.02007
+    EdgeWard,
+    /// Gather all the hosts accepting the sla, choose at random between the
+    /// 2 least cpu-utilized
+    MaxCpu,
+    /// Randomly explore 2 nodes, the one with the lowest utilisation wins
+    MinCpuRandom,
+    /// Only the Cloud, the root of the fog, bids on the slas
+    CloudOnly,
+    /// Cloud only allocation, with the Cloud nodes (IS_CLOUD env var)
+    /// chatting between them to find the best one
+    CloudOnlyV2,
+    /// Edge-ward placement refusing the functions when the latency is not
+    /// expected to be respected
+    EdgeWardV2,
+    /// Edge-ward placement trying to allocate on the furthest node up the
+    /// tree first
+    EdgeWardV3,
+}
+
+impl fmt::Display for PlacementStrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PlacementStrategyKind::Auction => "auction",
+            PlacementStrategyKind::EdgeFirst => "edge_first",
+            PlacementStrategyKind::EdgeFurthest => "edge_furthest",
+            PlacementStrategyKind::EdgeWard => "edge_ward",
+            PlacementStrategyKind::MaxCpu => "maxcpu",
+            PlacementStrategyKind::MinCpuRandom => "mincpurandom",
+            PlacementStrategyKind::CloudOnly => "cloud_only",
+            PlacementStrategyKind::CloudOnlyV2 => "cloud_only_v2",
+            PlacementStrategyKind::EdgeWardV2 => "edge_ward_v2",
+            PlacementStrategyKind::EdgeWardV3 => "edge_ward_v3",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parse the names previously used as cargo features, eg. `edge_first`
+impl FromStr for PlacementStrategyKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auction" => Ok(PlacementStrategyKind::Auction),
+            "edge_first" => Ok(PlacementStrategyKind::EdgeFirst),
+            "edge_furthest" => Ok(PlacementStrategyKind::EdgeFurthest),
+            "edge_ward" => Ok(PlacementStrategyKind::EdgeWard),
+            "maxcpu" => Ok(PlacementStrategyKind::MaxCpu),
+            "mincpurandom" => Ok(PlacementStrategyKind::MinCpuRandom),
+            "cloud_only" => Ok(PlacementStrategyKind::CloudOnly),
+            "cloud_only_v2" => Ok(PlacementStrategyKind::CloudOnlyV2),
+            "edge_ward_v2" => Ok(PlacementStrategyKind::EdgeWardV2),
+            "edge_ward_v3" => Ok(PlacementStrategyKind::EdgeWardV3),
+            _ => anyhow::bail!("Unknown placement strategy '{}'", s),
+        }
+    }
+}
+
+/// How the market chooses the node hosting a function
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum MarketPlacementKind {
+    /// Call the fog for bids, the nodes place the function among themselves
+    #[default]
+    Auction,
+    /// From the global view, the closest node able to host the function on
+    /// the way up from the target node to the root
+    Greedy,
+    /// From the global view, the optimal nodes found by an exhaustive search
+    /// over the whole tree, greedy above a bounded number of sets
+    Exact,
+}
+
+impl fmt::Display for MarketPlacementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MarketPlacementKind::Auction => "auction",
+            MarketPlacementKind::Greedy => "greedy",
+            MarketPlacementKind::Exact => "exact",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MarketPlacementKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auction" => Ok(MarketPlacementKind::Auction),
+            "greedy" => Ok(MarketPlacementKind::Greedy),
+            "exact" => Ok(MarketPlacementKind::Exact),
+            _ => anyhow::bail!("Unknown market placement '{}'", s),
+        }
+    }
+}
+
+/// Base pricing used by a fog node to value an incoming sla
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum BidValuationKind {
+    /// Constant rates on the cpu and memory, given by ENV variables
+    LinearRates,
+    /// Rates increasing quadratically with the cpu utilisation
+    #[default]
+    QuadraticRates,
+}
+
+/// Adjustment applied on top of the base valuation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BidValuationModifier {
+    /// The bid becomes the cpu utilisation of the node, the base valuation
+    /// being kept as the price
+    CpuUtilisation,
+    /// Advantage when the node is the data source of the function
+    Reduction,
+}
+
+/// Valuation of the bids: a base pricing followed by the modifiers, applied
+/// in order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BidValuationConfig {
+    #[serde(default)]
+    pub base:      BidValuationKind,
+    #[serde(default)]
+    pub modifiers: Vec<BidValuationModifier>,
+}
+
+impl fmt::Display for BidValuationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BidValuationKind::LinearRates => "linear_rates",
+            BidValuationKind::QuadraticRates => "quadratic_rates",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BidValuationKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear_rates" => Ok(BidValuationKind::LinearRates),
+            "quadratic_rates" => Ok(BidValuationKind::QuadraticRates),
+            _ => anyhow::bail!("Unknown bid valuation '{}'", s),
+        }
+    }
+}
+
+impl fmt::Display for BidValuationModifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BidValuationModifier::CpuUtilisation => "cpu_utilisation",
+            BidValuationModifier::Reduction => "reduction",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BidValuationModifier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cpu_utilisation" => Ok(BidValuationModifier::CpuUtilisation),
+            "reduction" => Ok(BidValuationModifier::Reduction),
+            _ => anyhow::bail!("Unknown bid valuation modifier '{}'", s),
+        }
+    }
+}
+
+impl fmt::Display for BidValuationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+        for modifier in &self.modifiers {
+            write!(f, ",{}", modifier)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a comma separated list: the base valuation followed by the
+/// modifiers, eg. `quadratic_rates,reduction`
+impl FromStr for BidValuationConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+        let base = parts
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Missing base bid valuation"))?
+            .parse()?;
+        let modifiers =
+            parts.map(str::parse).collect::<Result<Vec<_>, _>>()?;
+        Ok(BidValuationConfig { base, modifiers })
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Node<T> {
     pub parent:   Option<NodeId>,
@@ -29,6 +245,18 @@ pub struct Node<T> {
     pub data: T,
 }
 
+/// Whether a node still sends its heartbeats to the market
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Liveness {
+    #[default]
+    Alive,
+    /// No heartbeat received for a while, the node may be gone
+    Suspected,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NodeRecord {
     /// URI, only in the case of the market node
@@ -37,6 +265,11 @@ pub struct NodeRecord {
     pub port_faas:     FogNodeFaaSPortExternal,
     pub tags:          Vec<String>,
     pub accepted_bids: HashMap<BidId, AcceptedBid>,
+    /// Last registration or heartbeat of the node
+    pub last_seen:     DateTime<Utc>,
+    pub liveness:      Liveness,
+    /// Last capacity the node reported
+    pub capacity:      Option<NodeCapacity>,
 }
 
 impl NodeRecord {
@@ -52,6 +285,9 @@ impl NodeRecord {
             port_faas,
             tags: Vec::from(tags),
             accepted_bids: HashMap::new(),
+            last_seen: Utc::now(),
+            liveness: Liveness::Alive,
+            capacity: None,
         }
     }
 }
@@ -99,7 +335,8 @@ pub enum NodeCategory {
 
 #[derive(Debug)]
 pub struct NodeSituationData {
-    pub situation:                         NodeCategory,
+    /// Changes when the node moves under another parent
+    pub situation:                         std::sync::RwLock<NodeCategory>,
     pub my_id:                             NodeId,
     pub my_public_ip:                      IpAddr,
     pub my_private_ip:                     IpAddr,
@@ -110,6 +347,8 @@ pub struct NodeSituationData {
     pub reserved_cpu:                      Ratio,
     pub max_in_flight_functions_proposals: MaxInFlight,
     pub my_advertised_bandwidth:           InformationRate,
+    pub placement_strategy:                PlacementStrategyKind,
+    pub bid_valuation:                     BidValuationConfig,
     pub children: dashmap::DashMap<NodeId, NodeDescription>,
 }
 
@@ -129,6 +368,10 @@ pub struct NodeSituationDisk {
     pub max_in_flight_functions_proposals: MaxInFlight,
     #[serde_as(as = "information_rate::Helper")]
     pub my_advertised_bandwidth:           InformationRate,
+    #[serde(default)]
+    pub placement_strategy:                PlacementStrategyKind,
+    #[serde(default)]
+    pub bid_valuation:                     BidValuationConfig,
 }
 
 /// Loads node configuration from file
@@ -155,10 +398,12 @@ impl NodeSituationData {
             reserved_cpu,
             max_in_flight_functions_proposals,
             my_advertised_bandwidth,
+            placement_strategy,
+            bid_valuation,
         } = disk;
 
         Self {
-            situation,
+            situation: std::sync::RwLock::new(situation),
             my_id,
             my_public_ip,
             my_private_ip,
@@ -170,6 +415,8 @@ impl NodeSituationData {
             max_in_flight_functions_proposals,
             children: dashmap::DashMap::new(),
             my_advertised_bandwidth,
+            placement_strategy,
+            bid_valuation,
         }
     }
 }
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use helper::chrono as chrono_helper;
+use helper::uom_helper::{cpu, information, information_rate, ratio, time};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use uom::si::f64::{Ratio as FloatRatio, Time};
+use uom::si::rational64::{Information, InformationRate, Ratio};
+
+use super::super::{FogNodeHTTPPort, NodeId, SlaId};
+use super::function::{FunctionState, FunctionStatus};
+
+/// The functions tracked by the fog node, grouped by state
+pub type TrackedFunctions = HashMap<FunctionState, Vec<FunctionStatus>>;
+
+/// Resources of a k8s node as accounted by the fog node
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeResources {
+    pub node: String,
+
+    #[serde_as(as = "information::Helper")]
+    pub used_memory: Information,
+
+    #[serde_as(as = "cpu::Helper")]
+    pub used_cpu: Ratio,
+
+    /// Held by the bids that are not paid yet
+    #[serde_as(as = "information::Helper")]
+    pub reserved_memory: Information,
+
+    #[serde_as(as = "cpu::Helper")]
+    pub reserved_cpu: Ratio,
+
+    #[serde_as(as = "information::Helper")]
+    pub available_memory: Information,
+
+    #[serde_as(as = "cpu::Helper")]
+    pub available_cpu: Ratio,
+}
+
+/// Latency estimated to a neighbor, unknown until enough pings answered
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NeighborLatency {
+    pub id: NodeId,
+
+    #[serde_as(as = "Option<time::Helper>")]
+    #[serde(default)]
+    pub median: Option<Time>,
+
+    #[serde_as(as = "Option<time::Helper>")]
+    #[serde(default)]
+    pub average: Option<Time>,
+
+    #[serde_as(as = "Option<time::Helper>")]
+    #[serde(default)]
+    pub interquantile_range: Option<Time>,
+
+    #[serde_as(as = "Option<ratio::Helper>")]
+    #[serde(default)]
+    pub packet_loss: Option<FloatRatio>,
+}
+
+/// A child registered to the fog node
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildNode {
+    pub id:        NodeId,
+    pub ip:        IpAddr,
+    pub port_http: FogNodeHTTPPort,
+
+    #[serde_as(as = "information_rate::Helper")]
+    pub advertised_bandwidth: InformationRate,
+}
+
+/// A oneshot job scheduled on the fog node
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTask {
+    /// What the job does
+    pub task: String,
+    pub sla:  SlaId,
+
+    /// The k8s node concerned by the job, if any
+    pub node: Option<String>,
+
+    #[serde_as(as = "chrono_helper::DateTimeHelper")]
+    pub deadline: DateTime<Utc>,
+}
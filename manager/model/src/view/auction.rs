@@ -10,7 +10,7 @@ use uom::si::rational64::InformationRate;
 use uom::si::time::millisecond;
 
 use super::super::domain::sla::Sla;
-use super::super::{BidId, NodeId};
+use super::super::{BidId, BidRequestId, NodeId};
 
 #[derive(Debug)]
 pub struct Latency {
@@ -79,8 +79,15 @@ pub struct BidRequest<'a> {
     pub node_origin:              NodeId,
     pub sla:                      &'a Sla,
     pub accumulated_latency:      AccumulatedLatency,
-    #[cfg(feature = "mincpurandom")]
+    /// Number of bids the requester wants to gather, only used by the
+    /// mincpurandom placement
     pub nb_propositions_required: usize,
+    /// Same for all the copies of a call for bids, to drop the repeated ones
+    pub request_id:               BidRequestId,
+    /// How many more times the request may be forwarded
+    pub hop_budget:               u32,
+    /// The nodes the request already went through
+    pub visited:                  Vec<NodeId>,
 }
 
 /// Same as [`BidRequest`](BidRequest) but with an owned SLA
@@ -91,8 +98,34 @@ pub struct BidRequestOwned {
     pub node_origin:              NodeId,
     pub sla:                      Sla,
     pub accumulated_latency:      AccumulatedLatency,
-    #[cfg(feature = "mincpurandom")]
     pub nb_propositions_required: usize,
+    pub request_id:               BidRequestId,
+    pub hop_budget:               u32,
+    pub visited:                  Vec<NodeId>,
+}
+
+impl BidRequestOwned {
+    /// The request `me` forwards to its neighbors, None once the hop budget
+    /// is spent
+    pub fn follow_up(
+        &self,
+        me: NodeId,
+        accumulated_latency: AccumulatedLatency,
+        nb_propositions_required: usize,
+    ) -> Option<BidRequest<'_>> {
+        let hop_budget = self.hop_budget.checked_sub(1)?;
+        let mut visited = self.visited.clone();
+        visited.push(me.clone());
+        Some(BidRequest {
+            node_origin: me,
+            sla: &self.sla,
+            accumulated_latency,
+            nb_propositions_required,
+            request_id: self.request_id.clone(),
+            hop_budget,
+            visited,
+        })
+    }
 }
 
 /// A bid
@@ -107,17 +140,51 @@ pub struct Bid {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AcceptedBid {
     pub chosen:    InstanciatedBid,
+    /// The instances on the other nodes, when the sla spreads its replicas
+    #[serde(default)]
+    pub replicas:  Vec<InstanciatedBid>,
     pub proposals: BidProposals,
     pub sla:       Sla,
 }
 
+impl AcceptedBid {
+    /// Every instance of the function, the chosen one first
+    pub fn instances(&self) -> impl Iterator<Item = &InstanciatedBid> {
+        std::iter::once(&self.chosen).chain(self.replicas.iter())
+    }
+
+    /// The instance at the index, as in [`AcceptedBid::instances`]
+    pub fn instance_mut(&mut self, index: usize) -> &mut InstanciatedBid {
+        match index {
+            0 => &mut self.chosen,
+            _ => &mut self.replicas[index - 1],
+        }
+    }
+
+    /// One accepted bid per instance, as recorded for the node hosting it
+    pub fn split(&self) -> Vec<AcceptedBid> {
+        self.instances()
+            .map(|instance| AcceptedBid {
+                chosen:    instance.clone(),
+                replicas:  Vec::new(),
+                proposals: self.proposals.clone(),
+                sla:       self.sla.clone(),
+            })
+            .collect()
+    }
+}
+
 /// The bid proposal and the node who issued it
+#[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BidProposal {
-    pub node_id: NodeId,
-    pub id:      BidId,
-    pub bid:     f64,
+    pub node_id:             NodeId,
+    pub id:                  BidId,
+    pub bid:                 f64,
+    /// Median latency from the node where the call for bids started
+    #[serde_as(as = "helper::uom_helper::time::Helper")]
+    pub accumulated_latency: Time,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
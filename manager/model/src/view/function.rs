@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use helper::chrono as chrono_helper;
+use helper::uom_helper::time;
+use serde::{Deserialize, Serialize};
+use uom::si::f64::Time;
+
+use super::super::domain::sla::Sla;
+use super::super::{NodeId, SlaId};
+use super::auction::AcceptedBid;
+
+/// Step of the lifecycle of a function on the node hosting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FunctionState {
+    Proposed,
+    Paid,
+    Provisioned,
+    Live,
+    Finished,
+    /// The function disappeared from the cluster before its end
+    Failed,
+    /// The proposal was not paid in time
+    Expired,
+}
+
+/// The state of a function, as tracked by the fog node hosting it
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionStatus {
+    pub id:    SlaId,
+    pub state: FunctionState,
+
+    /// Unknown once the proposal expired
+    pub sla: Option<Sla>,
+
+    /// The k8s node hosting the function
+    pub node: Option<String>,
+
+    pub function_name: Option<String>,
+
+    pub opened_port: Option<i32>,
+
+    #[serde_as(as = "Option<chrono_helper::DateTimeHelper>")]
+    #[serde(default)]
+    pub paid_at: Option<DateTime<Utc>>,
+
+    /// When the duration of the sla ends, once paid
+    #[serde_as(as = "Option<chrono_helper::DateTimeHelper>")]
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+
+    /// Until [FunctionStatus::ends_at] while the function runs
+    #[serde_as(as = "Option<time::Helper>")]
+    #[serde(default)]
+    pub remaining: Option<Time>,
+}
+
+/// The function auctioned by the market, along with its state on the node
+/// that won the auction
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GetFunction {
+    pub node_id:  NodeId,
+    pub bid:      AcceptedBid,
+    pub status:   FunctionStatus,
+    /// The state of the other instances, in the order of
+    /// [`AcceptedBid::replicas`]
+    #[serde(default)]
+    pub replicas: Vec<FunctionStatus>,
+}
@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod auction;
+pub mod function;
 pub mod node;
 pub mod routing;
 pub mod sla;
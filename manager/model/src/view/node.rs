@@ -1,16 +1,13 @@
 use chrono::{DateTime, Utc};
-use helper::uom_helper::information_rate;
-#[cfg(feature = "offline")]
-use helper::uom_helper::time;
+use helper::uom_helper::{cpu, information, information_rate, time};
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
 use std::collections::HashMap;
 use std::net::IpAddr;
-#[cfg(feature = "offline")]
 use uom::si::f64::Time;
-use uom::si::rational64::InformationRate;
+use uom::si::rational64::{Information, InformationRate, Ratio};
 
-use crate::dto::node::NodeRecord;
+use crate::domain::sla::Sla;
+use crate::dto::node::{Liveness, NodeRecord};
 use crate::view::auction::AcceptedBid;
 use crate::{BidId, FogNodeFaaSPortExternal, FogNodeHTTPPort};
 use helper::chrono as chrono_helper;
@@ -62,6 +59,9 @@ pub enum RegisterNode {
         tags:                 Vec<String>,
         #[serde_as(as = "information_rate::Helper")]
         advertised_bandwidth: InformationRate,
+        /// Reported for the placement computed by the market
+        #[serde(default)]
+        capacity:             Option<NodeCapacity>,
     },
     Node {
         parent:               NodeId,
@@ -75,9 +75,99 @@ pub enum RegisterNode {
         #[cfg(feature = "offline")]
         #[serde_as(as = "time::Helper")]
         offline_latency:      Time,
+        /// Reported for the placement computed by the market
+        #[serde(default)]
+        capacity:             Option<NodeCapacity>,
     },
 }
 
+/// What a node can still host and how far it is from its parent, as
+/// reported to the market along with its registration and heartbeats
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeCapacity {
+    /// Largest free cpu left on one of the k8s nodes
+    #[serde_as(as = "cpu::Helper")]
+    pub max_free_cpu:      Ratio,
+    /// Largest free memory left on one of the k8s nodes
+    #[serde_as(as = "information::Helper")]
+    pub max_free_memory:   Information,
+    /// None for the node connected to the market, or until measured
+    #[serde_as(as = "Option<time::Helper>")]
+    #[serde(default)]
+    pub latency_to_parent: Option<Time>,
+}
+
+impl NodeCapacity {
+    /// Whether the free resources are enough for the replicas of the sla
+    /// the node would host, the same way the fog nodes check it
+    pub fn can_host(&self, sla: &Sla) -> bool {
+        sla.cpu_per_node() < self.max_free_cpu
+            && sla.memory_per_node() < self.max_free_memory
+    }
+}
+
+/// A node leaving the network, relayed up to the market like
+/// [RegisterNode]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeregisterNode {
+    pub node_id: NodeId,
+    /// None for the node connected to the market
+    pub parent:  Option<NodeId>,
+}
+
+/// Where to re-attach the node receiving it
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveNodeRequest {
+    pub parent_id:             NodeId,
+    pub parent_node_ip:        IpAddr,
+    pub parent_node_port_http: FogNodeHTTPPort,
+    #[cfg(feature = "offline")]
+    #[serde_as(as = "time::Helper")]
+    pub parent_latency:        Time,
+}
+
+/// A node re-attached under another parent along with its subtree, relayed
+/// up to the market through both its old and its new parent
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveNode {
+    pub node_id:              NodeId,
+    pub from:                 NodeId,
+    pub to:                   NodeId,
+    pub ip:                   IpAddr,
+    pub port_http:            FogNodeHTTPPort,
+    #[serde_as(as = "information_rate::Helper")]
+    pub advertised_bandwidth: InformationRate,
+    #[cfg(feature = "offline")]
+    #[serde_as(as = "time::Helper")]
+    pub offline_latency:      Time,
+}
+
+/// What the subtree of a node can still host at best, pushed to its parent
+/// to spare it calls for bids that cannot be satisfied down there. The
+/// largest free cpu and the largest free memory may come from different k8s
+/// nodes: the summary overestimates the subtree, it never underestimates it.
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacitySummary {
+    pub node_id:         NodeId,
+    #[serde_as(as = "cpu::Helper")]
+    pub max_free_cpu:    Ratio,
+    #[serde_as(as = "information::Helper")]
+    pub max_free_memory: Information,
+    /// Latency from the node to the closest node of its subtree with free
+    /// resources
+    #[serde_as(as = "time::Helper")]
+    pub min_latency:     Time,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GetFogNodes {
@@ -85,6 +175,8 @@ pub struct GetFogNodes {
     pub ip:            IpAddr,
     pub tags:          Vec<String>,
     pub accepted_bids: HashMap<BidId, AcceptedBid>,
+    pub liveness:      Liveness,
+    pub capacity:      Option<NodeCapacity>,
 }
 
 impl From<(NodeId, NodeRecord)> for GetFogNodes {
@@ -94,6 +186,8 @@ impl From<(NodeId, NodeRecord)> for GetFogNodes {
             ip: record.ip,
             tags: record.tags,
             accepted_bids: record.accepted_bids,
+            liveness: record.liveness,
+            capacity: record.capacity,
         }
     }
 }
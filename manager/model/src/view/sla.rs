@@ -1,13 +1,13 @@
 use super::super::domain::sla::Sla;
 use super::super::NodeId;
-use crate::domain::sla::DataFlow;
-use helper::uom_helper::cpu_ratio::millicpu;
+use crate::domain::auction::AuctionMechanismKind;
+use crate::domain::sla::{DataFlow, SlaValidationError};
 use helper::uom_helper::{cpu, information, time};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use uom::si::f64::Time;
-use uom::si::information::byte;
 use uom::si::rational64::{Information, Ratio};
-use uom::si::time::second;
+use uom::si::time::{day, second};
 
 /// Describe the SLA of a function submitted to be provisioned
 #[serde_with::serde_as]
@@ -45,24 +45,21 @@ pub struct SlaRequest {
     pub env_vars: Option<Vec<(String, String)>>,
 
     pub env_process: Option<String>,
+
+    /// Highest price the client accepts to pay
+    #[serde(default)]
+    pub max_price: Option<f64>,
+
+    /// Place each replica on a distinct fog node
+    #[serde(default)]
+    pub spread_replicas: bool,
 }
 
-impl From<SlaRequest> for Sla {
-    fn from(val: SlaRequest) -> Self {
-        assert!(val.duration > Time::new::<second>(0.0));
-        assert!(val.latency_max > Time::new::<second>(0.0));
-        assert!(
-            val.input_max_size
-                > Information::new::<byte>(num_rational::Ratio::new(0, 1))
-        );
-        assert!(
-            val.cpu > Ratio::new::<millicpu>(num_rational::Ratio::new(0, 1))
-        );
-        assert!(
-            val.memory
-                > Information::new::<byte>(num_rational::Ratio::new(0, 1))
-        );
-        Sla {
+impl TryFrom<SlaRequest> for Sla {
+    type Error = SlaValidationError;
+
+    fn try_from(val: SlaRequest) -> Result<Self, Self::Error> {
+        let sla = Sla {
             id:                 uuid::Uuid::new_v4().into(),
             memory:             val.memory,
             cpu:                val.cpu,
@@ -75,7 +72,11 @@ impl From<SlaRequest> for Sla {
             env_vars:           val.env_vars.unwrap_or_default(),
             env_process:        val.env_process,
             input_max_size:     val.input_max_size,
-        }
+            max_price:          val.max_price,
+            spread_replicas:    val.spread_replicas,
+        };
+        sla.validate()?;
+        Ok(sla)
     }
 }
 
@@ -84,22 +85,76 @@ impl From<SlaRequest> for Sla {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PutSlaRequest {
-    pub sla:         SlaRequest,
-    pub target_node: NodeId,
+    pub sla:               SlaRequest,
+    pub target_node:       NodeId,
+    /// Overrides the auction mechanism configured on the market
+    #[serde(default)]
+    pub auction_mechanism: Option<AuctionMechanismKind>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PutSla {
-    pub sla:         Sla,
-    pub target_node: NodeId,
+    pub sla:               Sla,
+    pub target_node:       NodeId,
+    pub auction_mechanism: Option<AuctionMechanismKind>,
 }
 
-impl From<PutSlaRequest> for PutSla {
-    fn from(value: PutSlaRequest) -> Self {
-        PutSla {
-            sla:         value.sla.into(),
-            target_node: value.target_node,
+impl TryFrom<PutSlaRequest> for PutSla {
+    type Error = SlaValidationError;
+
+    fn try_from(value: PutSlaRequest) -> Result<Self, Self::Error> {
+        // Same values as the ones accepted from the env
+        if let Some(mechanism) =
+            value.auction_mechanism.filter(|mechanism| !mechanism.is_valid())
+        {
+            return Err(SlaValidationError::InvalidAuctionMechanism(
+                mechanism.to_string(),
+            ));
         }
+        Ok(PutSla {
+            sla:               value.sla.try_into()?,
+            target_node:       value.target_node,
+            auction_mechanism: value.auction_mechanism,
+        })
     }
 }
+
+/// Extend the duration of a running function
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendSla {
+    /// Duration added to the current one
+    #[serde_as(as = "time::Helper")]
+    pub duration: Time,
+}
+
+impl ExtendSla {
+    /// Longest extension granted at once, in days
+    pub const MAX_DAYS: f64 = 365.0;
+
+    /// Check the extension pushes the end of the function back by a
+    /// duration the node can schedule
+    pub fn validate(&self) -> Result<(), SlaValidationError> {
+        // Not greater also refuses NaNs
+        let zero = Time::new::<second>(0.0);
+        if self.duration.partial_cmp(&zero) != Some(Ordering::Greater) {
+            return Err(SlaValidationError::NonPositiveExtension);
+        }
+        if self.duration > Time::new::<day>(Self::MAX_DAYS) {
+            return Err(SlaValidationError::ExtensionTooLong(Self::MAX_DAYS));
+        }
+        Ok(())
+    }
+}
+
+/// The extension granted by the node hosting the function
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SlaExtension {
+    /// The sla with its extended duration
+    pub sla:   Sla,
+    /// Price of the additional duration
+    pub price: f64,
+}
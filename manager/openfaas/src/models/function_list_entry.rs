@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 #[serde(rename_all = "camelCase")]
 pub struct FunctionListEntry {
     /// The name of the function
-    name:               String,
+    pub name:           String,
     /// The fully qualified docker image name of the function
     image:              String,
     /// The amount of invocations for the specified function
@@ -52,20 +52,24 @@
                     config = {
                       Env = [
                         "SERVER_PORT=3003"
+                        "AUCTION_MAX_FALLBACKS=2"
+                        "BID_HOP_BUDGET=16"
+                        "NODE_SUSPECT_TIMEOUT_MSECS=15000"
+                        "NODE_EVICTION_TIMEOUT_MSECS=60000"
+                        "MARKET_PLACEMENT=auction"
                         "LD_LIBRARY_PATH=${lib.makeLibraryPath [pkgs.openssl_3]}"
-                      ];
+                      ]
+                      ++ nixpkgs.lib.optional (settings.strategy == "random") "AUCTION_MECHANISM=random";
                     };
-                    features = nixpkgs.lib.optional (settings.strategy != "default_strategy") "${settings.strategy}";
+                    features = [];
                   };
                 }
               )
               (
                 nixpkgs.lib.cartesianProduct {
-                  # Do not forget to run cargo2nix at each new features added
                   strategy = [
                     "default_strategy"
                     "random"
-                    "mincpurandom"
                   ];
                 }
               )
@@ -83,15 +87,18 @@
                     config = {
                       Env = [
                         "FUNCTION_LIVE_TIMEOUT_MSECS=120000"
+                        "FUNCTION_PAYING_TIMEOUT_MSECS=60000"
+                        "HEARTBEAT_INTERVAL_MSECS=5000"
+                        "BID_TIMEOUT_LATENCY_FACTOR=20"
+                        "BID_TIMEOUT_MIN_MSECS=200"
+                        "FUNCTION_TRACKING_PATH=/var/lib/fog_node/function_tracking"
+                        "PLACEMENT_STRATEGY=${settings.strategy}"
+                        "BID_VALUATION=${settings.valuation}${nixpkgs.lib.optionalString (settings.complication != "no_complication") ",${settings.complication}"}"
                         "LD_LIBRARY_PATH=${lib.makeLibraryPath [pkgs.openssl_3]}"
                       ];
+                      Volumes = {"/var/lib/fog_node" = {};};
                     };
-                    features =
-                      [
-                        "${settings.strategy}"
-                      ]
-                      ++ nixpkgs.lib.optional (settings.valuation != "valuation_resources") "${settings.valuation}"
-                      ++ nixpkgs.lib.optional (settings.complication != "no_complication") "${settings.complication}";
+                    features = [];
                   };
                 }
               )
@@ -100,10 +107,12 @@
                   # Do not forget to run cargo2nix at each new features added
                   strategy = [
                     "auction"
-                    "auction_reduction"
+                    "cloud_only"
+                    "cloud_only_v2"
                     "edge_first"
                     "edge_furthest"
                     "edge_ward"
+                    "edge_ward_v2"
                     "edge_ward_v3"
                     "maxcpu"
                     "mincpurandom"
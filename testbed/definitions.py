@@ -160,12 +160,24 @@ spec:
           value: "{enable_collector}"
         - name: FUNCTION_LIVE_TIMEOUT_MSECS
           value: "120000"
+        - name: FUNCTION_PAYING_TIMEOUT_MSECS
+          value: "60000"
+        - name: HEARTBEAT_INTERVAL_MSECS
+          value: "5000"
+        - name: BID_TIMEOUT_LATENCY_FACTOR
+          value: "20"
+        - name: BID_TIMEOUT_MIN_MSECS
+          value: "200"
+        - name: FUNCTION_TRACKING_PATH
+          value: "/var/lib/fog_node/function_tracking"
 {additional_env_vars}
         ports:
         - containerPort: 30003
         volumeMounts:
         - name: log-storage-fog-node
           mountPath: /var/log
+        - name: function-tracking-fog-node
+          mountPath: /var/lib/fog_node
       - name: sidecar-logs
         image: ghcr.io/volodiapg/busybox:latest
         args: [/bin/sh, -c, 'tail -n+1 -F /mnt/log/{node_name}.log']
@@ -176,6 +188,8 @@ spec:
       volumes:
       - name: log-storage-fog-node
         emptyDir: {{}}
+      - name: function-tracking-fog-node
+        emptyDir: {{}}
 """
 
 MARKET_DEPLOYMENT = """apiVersion: v1
@@ -244,6 +258,16 @@ spec:
           value: "{enable_collector}"
         - name: INSTANCE_NAME
           value: "marketplace"
+        - name: AUCTION_MAX_FALLBACKS
+          value: "2"
+        - name: BID_HOP_BUDGET
+          value: "16"
+        - name: NODE_SUSPECT_TIMEOUT_MSECS
+          value: "15000"
+        - name: NODE_EVICTION_TIMEOUT_MSECS
+          value: "60000"
+        - name: MARKET_PLACEMENT
+          value: "auction"
         volumeMounts:
         - name: log-storage-market
           mountPath: /var/log